@@ -2,6 +2,7 @@ extern crate dpdk_sys;
 
 use std::env;
 use std::ffi;
+use std::mem::offset_of;
 use std::os::raw::*;
 
 fn main() {
@@ -22,5 +23,49 @@ fn main() {
 
         assert_eq!(dpdk_sys::rte_is_power_of_2(7), 0);
         assert_eq!(dpdk_sys::rte_is_power_of_2(16), 1);
+
+        // Protocol headers are generated with their fields, not as opaque blobs.
+        assert_eq!(
+            std::mem::size_of::<dpdk_sys::rte_ether_hdr>(),
+            dpdk_sys::RTE_ETHER_HDR_LEN as usize
+        );
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_ipv4_hdr>(), 20);
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_udp_hdr>(), 8);
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_tcp_hdr>(), 20);
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_ipv6_hdr>(), 40);
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_vlan_hdr>(), 4);
+        // `rte_arp_ipv4` is opaque, but must keep its packed 20-byte size.
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_arp_ipv4>(), 20);
+        assert_eq!(std::mem::size_of::<dpdk_sys::rte_arp_hdr>(), 28);
+
+        // Field offsets must match the wire format, i.e. no padding was inserted.
+        assert_eq!(offset_of!(dpdk_sys::rte_ether_hdr, src_addr), 6);
+        assert_eq!(offset_of!(dpdk_sys::rte_ether_hdr, ether_type), 12);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, total_length), 2);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, fragment_offset), 6);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, next_proto_id), 9);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, hdr_checksum), 10);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, src_addr), 12);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv4_hdr, dst_addr), 16);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv6_hdr, payload_len), 4);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv6_hdr, proto), 6);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv6_hdr, hop_limits), 7);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv6_hdr, src_addr), 8);
+        assert_eq!(offset_of!(dpdk_sys::rte_ipv6_hdr, dst_addr), 24);
+        assert_eq!(offset_of!(dpdk_sys::rte_udp_hdr, dgram_len), 4);
+        assert_eq!(offset_of!(dpdk_sys::rte_udp_hdr, dgram_cksum), 6);
+        assert_eq!(offset_of!(dpdk_sys::rte_tcp_hdr, sent_seq), 4);
+        assert_eq!(offset_of!(dpdk_sys::rte_tcp_hdr, recv_ack), 8);
+        assert_eq!(offset_of!(dpdk_sys::rte_tcp_hdr, data_off), 12);
+        assert_eq!(offset_of!(dpdk_sys::rte_tcp_hdr, tcp_flags), 13);
+        assert_eq!(offset_of!(dpdk_sys::rte_tcp_hdr, cksum), 16);
+        assert_eq!(offset_of!(dpdk_sys::rte_arp_hdr, arp_opcode), 6);
+        assert_eq!(offset_of!(dpdk_sys::rte_arp_hdr, arp_data), 8);
+
+        let mut ip: dpdk_sys::rte_ipv4_hdr = std::mem::zeroed();
+        ip.src_addr = u32::to_be(0x0a00_0001);
+        ip.hdr_checksum = 0;
+        let ip_bytes = std::slice::from_raw_parts(&ip as *const _ as *const u8, 20);
+        assert_eq!(&ip_bytes[12..16], &[10, 0, 0, 1]);
     }
 }
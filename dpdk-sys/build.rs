@@ -704,6 +704,12 @@ impl State {
             }
         }
 
+        // `rte_errno` is a per-lcore (thread local) variable hidden behind a macro,
+        // which bindgen cannot express. Expose it as a getter function instead.
+        static_def_list.push(format!("int {prefix}rte_errno (void)", prefix = PREFIX));
+        static_impl_list.push("{ return rte_errno; }".to_string());
+        self.static_functions.push("rte_errno".to_string());
+
         if env::var("CARGO_FEATURE_CONSTANTS_CACHE").is_ok() {
             println!("cargo:warning=Using cached constants data");
             let cache_path = self.project_path.join("gen/constants.rs.cache");
@@ -964,7 +970,9 @@ impl State {
             .clang_arg("-DALLOW_INTERNAL_API") // We will not use internal API, but it is necessary to generate bindings.
             .opaque_type("vmbus_bufring")
             .opaque_type("rte_avp_desc")
-            .opaque_type("rte_.*_hdr")
+            // Protocol headers (`rte_ether_hdr`, `rte_ipv4_hdr`, ...) keep their fields.
+            // `rte_arp_ipv4` is packed but contains `rte_ether_addr`, which is
+            // `__rte_aligned(2)`; Rust rejects that combination (E0588), so it stays opaque.
             .opaque_type("rte_arp_ipv4")
             .opaque_type("__*")
             .generate()
@@ -1,7 +1,5 @@
 use rust_dpdk::*;
-use std::ffi::CString;
-use std::os::raw::{c_char, c_int};
-use std::ptr;
+use std::os::raw::c_int;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -21,26 +19,19 @@ fn main() {
     println!("启动 DPDK 示例程序...");
 
     // 初始化 DPDK EAL
-    let args = vec![
-        CString::new("basic_dpdk").unwrap(),
-        CString::new("-l").unwrap(),
-        CString::new("0-1").unwrap(),  // 使用核心 0 和 1
-        CString::new("--no-pci").unwrap(),  // 不使用 PCI 设备，适合虚拟环境测试
-        // 使用 null 驱动代替 pcap 驱动
-        CString::new("--vdev=net_null0").unwrap(),
-    ];
-
-    let mut c_args: Vec<*mut c_char> = args
-        .iter()
-        .map(|arg| arg.as_ptr() as *mut c_char)
-        .collect();
-
     println!("初始化 DPDK EAL...");
-    let ret = unsafe { rte_eal_init(c_args.len() as c_int, c_args.as_mut_ptr()) };
-    if ret < 0 {
-        eprintln!("无法初始化 EAL: {}", ret);
-        return;
-    }
+    let eal = match EalBuilder::new("basic_dpdk")
+        .lcores(0..=1) // 使用核心 0 和 1
+        .no_pci(true) // 不使用 PCI 设备，适合虚拟环境测试
+        .vdev("net_null0") // 使用 null 驱动代替 pcap 驱动
+        .init()
+    {
+        Ok(eal) => eal,
+        Err(e) => {
+            eprintln!("无法初始化 EAL: {}", e);
+            return;
+        }
+    };
 
     // 设置信号处理器
     unsafe {
@@ -57,121 +48,65 @@ fn main() {
 
     if nb_ports == 0 {
         eprintln!("没有可用的网络端口");
-        unsafe { rte_eal_cleanup() };
         return;
     }
 
-    // 配置第一个端口
+    // 为接收队列分配内存池
+    let mbuf_pool = match PktMbufPool::builder("rx_mbuf_pool")
+        .count(8192) // 元素数量
+        .cache_size(256) // 缓存大小
+        .socket_id(unsafe { rte_socket_id() } as i32)
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("无法创建 mbuf 池: {}", e);
+            return;
+        }
+    };
+
+    // 配置并启动第一个端口：1 个接收队列、1 个发送队列，开启混杂模式
     let port_id = 0;
     let nb_rxd = 128; // 接收描述符数量
     let nb_txd = 512; // 发送描述符数量
 
-    // 配置以太网设备
-    let mut port_conf: rte_eth_conf = unsafe { std::mem::zeroed() };
-    port_conf.rxmode.max_rx_pkt_len = RTE_ETHER_MAX_LEN as u32;
-
     println!("配置端口 {}...", port_id);
-    let ret = unsafe {
-        rte_eth_dev_configure(
-            port_id,
-            1, // 接收队列数量
-            1, // 发送队列数量
-            &port_conf,
-        )
-    };
-    if ret < 0 {
-        eprintln!("无法配置端口 {}: {}", port_id, ret);
-        unsafe { rte_eal_cleanup() };
-        return;
-    }
-
-    // 调整接收和发送描述符数量
-    let mut rx_desc = nb_rxd;
-    let mut tx_desc = nb_txd;
-    unsafe {
-        rte_eth_dev_adjust_nb_rx_tx_desc(port_id, &mut rx_desc, &mut tx_desc);
-    }
-
-    // 为接收队列分配内存池
-    let pool_name = CString::new("rx_mbuf_pool").unwrap();
-    let mp = unsafe {
-        rte_pktmbuf_pool_create(
-            pool_name.as_ptr(),
-            8192,        // 元素数量
-            256,         // 缓存大小
-            0,           // 私有数据大小
-            RTE_MBUF_DEFAULT_BUF_SIZE as u16,
-            rte_socket_id() as i32,
-        )
+    let (port, mut rx_queues, mut tx_queues) = match Port::configure(port_id)
+        .rx_queues(1, nb_rxd, &mbuf_pool)
+        .tx_queues(1, nb_txd)
+        .promiscuous(true)
+        .start()
+    {
+        Ok(started) => started,
+        Err(e) => {
+            eprintln!("无法启动端口 {}: {}", port_id, e);
+            return;
+        }
     };
-    if mp.is_null() {
-        eprintln!("无法创建 mbuf 池");
-        unsafe { rte_eal_cleanup() };
-        return;
-    }
+    let mut rx_queue = rx_queues.remove(0);
+    let mut tx_queue = tx_queues.remove(0);
 
-    // 设置接收队列
-    let rx_queue_id = 0;
-    let ret = unsafe {
-        rte_eth_rx_queue_setup(
-            port_id,
-            rx_queue_id,
-            rx_desc,
-            rte_eth_dev_socket_id(port_id) as u32,
-            ptr::null(),
-            mp,
-        )
-    };
-    if ret < 0 {
-        eprintln!("无法设置接收队列: {}", ret);
-        unsafe { rte_eal_cleanup() };
-        return;
+    // 等待链路连通
+    match port.wait_for_link_up(Duration::from_secs(9)) {
+        Ok(link) => println!("端口 {}: {}", port_id, link),
+        Err(e) => eprintln!("端口 {} 链路未连通: {}", port_id, e),
     }
 
-    // 设置发送队列
-    let tx_queue_id = 0;
-    let ret = unsafe {
-        rte_eth_tx_queue_setup(
+    // 获取端口 MAC 地址
+    match port.mac_addr() {
+        Ok(mac_addr) => println!(
+            "端口 {} MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
             port_id,
-            tx_queue_id,
-            tx_desc,
-            rte_eth_dev_socket_id(port_id) as u32,
-            ptr::null(),
-        )
-    };
-    if ret < 0 {
-        eprintln!("无法设置发送队列: {}", ret);
-        unsafe { rte_eal_cleanup() };
-        return;
-    }
-
-    // 启动端口
-    println!("启动端口 {}...", port_id);
-    let ret = unsafe { rte_eth_dev_start(port_id) };
-    if ret < 0 {
-        eprintln!("无法启动端口 {}: {}", port_id, ret);
-        unsafe { rte_eal_cleanup() };
-        return;
+            mac_addr[0],
+            mac_addr[1],
+            mac_addr[2],
+            mac_addr[3],
+            mac_addr[4],
+            mac_addr[5]
+        ),
+        Err(e) => eprintln!("无法获取端口 {} 的 MAC 地址: {}", port_id, e),
     }
 
-    // 获取端口 MAC 地址
-    let mut mac_addr: rte_ether_addr = unsafe { std::mem::zeroed() };
-    unsafe { rte_eth_macaddr_get(port_id, &mut mac_addr) };
-    
-    println!(
-        "端口 {} MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
-        port_id,
-        mac_addr.addr_bytes[0],
-        mac_addr.addr_bytes[1],
-        mac_addr.addr_bytes[2],
-        mac_addr.addr_bytes[3],
-        mac_addr.addr_bytes[4],
-        mac_addr.addr_bytes[5]
-    );
-
-    // 启用混杂模式
-    unsafe { rte_eth_promiscuous_enable(port_id) };
-
     println!("开始接收数据包...");
     println!("按 Ctrl+C 退出");
 
@@ -191,40 +126,22 @@ fn main() {
     let mut total_rx_packets = 0;
     let mut total_tx_packets = 0;
 
+    let mut batch = MbufBatch::new(32);
     while !force_quit.load(Ordering::SeqCst) {
         // 接收数据包
-        let mut rx_mbufs: [*mut rte_mbuf; 32] = [ptr::null_mut(); 32];
-        let nb_rx = unsafe {
-            rte_eth_rx_burst(
-                port_id,
-                rx_queue_id,
-                rx_mbufs.as_mut_ptr(),
-                rx_mbufs.len() as u16,
-            )
-        };
+        let nb_rx = rx_queue.recv(&mut batch);
 
         if nb_rx > 0 {
-            total_rx_packets += nb_rx as usize;
+            total_rx_packets += nb_rx;
             println!("接收到 {} 个数据包，总计: {}", nb_rx, total_rx_packets);
 
             // 简单处理：将接收到的数据包原样发送回去
-            let nb_tx = unsafe {
-                rte_eth_tx_burst(
-                    port_id,
-                    tx_queue_id,
-                    rx_mbufs.as_mut_ptr(),
-                    nb_rx,
-                )
-            };
+            let nb_tx = tx_queue.send(&mut batch);
 
-            total_tx_packets += nb_tx as usize;
+            total_tx_packets += nb_tx;
 
             // 释放未发送的数据包
-            if nb_tx < nb_rx {
-                for i in nb_tx..nb_rx {
-                    unsafe { rte_pktmbuf_free(rx_mbufs[i as usize]) };
-                }
-            }
+            batch.clear();
         }
 
         // 短暂休眠，避免 CPU 使用率过高
@@ -235,13 +152,13 @@ fn main() {
     println!("总接收数据包: {}", total_rx_packets);
     println!("总发送数据包: {}", total_tx_packets);
 
-    // 停止端口
-    unsafe {
-        rte_eth_dev_stop(port_id);
-        rte_eth_dev_close(port_id);
-    }
+    // 停止并关闭端口，随后释放内存池
+    drop(rx_queue);
+    drop(tx_queue);
+    drop(port);
+    drop(mbuf_pool);
 
     // 清理 EAL
-    unsafe { rte_eal_cleanup() };
+    drop(eal);
     println!("程序退出");
 }
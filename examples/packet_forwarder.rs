@@ -1,7 +1,10 @@
+use rust_dpdk::arp::ArpResponder;
+use rust_dpdk::icmp::{IcmpResponder, Icmpv6Responder};
+use rust_dpdk::ethdev::{RxOffload, TxOffload};
+use rust_dpdk::packet::{ChecksumMode, Ipv4Hdr, Ipv6Hdr, L4Type, PacketBuilder, TcpHdr, UdpHdr};
 use rust_dpdk::*;
-use std::ffi::CString;
-use std::os::raw::{c_char, c_int, c_void};
-use std::ptr;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::os::raw::c_int;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -26,403 +29,199 @@ extern "C" fn signal_handler(_signum: c_int) {
 }
 
 // 数据包转发逻辑
-fn process_packet(mbuf: *mut rte_mbuf) {
-    unsafe {
-        // 获取以太网头部
-        // 手动实现 rte_pktmbuf_mtod 宏的功能
-        // 原始宏定义: #define rte_pktmbuf_mtod(m, t) ((t)((char *)(m)->buf_addr + (m)->data_off))
-        let eth_hdr_ptr = ((*mbuf).buf_addr as *mut u8).add((*mbuf).data_off as usize);
-        
-        // 以太网头部结构:
-        // struct rte_ether_hdr {
-        //     struct rte_ether_addr d_addr; /**< Destination address. */
-        //     struct rte_ether_addr s_addr; /**< Source address. */
-        //     uint16_t ether_type;          /**< Frame type. */
-        // }
-        
-        // 交换源和目标 MAC 地址
-        // 每个 MAC 地址是 6 字节
-        for i in 0..RTE_ETHER_ADDR_LEN as usize {
-            let tmp = *eth_hdr_ptr.add(i);
-            *eth_hdr_ptr.add(i) = *eth_hdr_ptr.add(i + RTE_ETHER_ADDR_LEN as usize);
-            *eth_hdr_ptr.add(i + RTE_ETHER_ADDR_LEN as usize) = tmp;
-        }
-        
-        // 获取以太网类型 (大端序)
-        // 以太网类型在以太网头部偏移 12 字节的位置
-        let ether_type_ptr = eth_hdr_ptr.add(12) as *const u16;
-        let ether_type = *ether_type_ptr;
-        
-        // 检查是否是 IPv4 数据包 (0x0800, 但在内存中是 0x0008 因为是大端序)
-        let ether_type_ipv4 = ((RTE_ETHER_TYPE_IPV4 as u16) >> 8) | ((RTE_ETHER_TYPE_IPV4 as u16) << 8);
-        if ether_type == ether_type_ipv4 {
-            // IP 头部在以太网头部之后
-            let ip_hdr_ptr = eth_hdr_ptr.add(RTE_ETHER_HDR_LEN as usize);
-            
-            // IPv4 头部结构:
-            // struct rte_ipv4_hdr {
-            //     uint8_t  version_ihl;   /**< version and header length */
-            //     uint8_t  type_of_service;/**< type of service */
-            //     uint16_t total_length;  /**< length of packet */
-            //     uint16_t packet_id;     /**< packet ID */
-            //     uint16_t fragment_offset;/**< fragmentation offset */
-            //     uint8_t  time_to_live;  /**< time to live */
-            //     uint8_t  next_proto_id; /**< protocol ID */
-            //     uint16_t hdr_checksum;  /**< header checksum */
-            //     uint32_t src_addr;      /**< source address */
-            //     uint32_t dst_addr;      /**< destination address */
-            // }
-            
-            // 源 IP 地址在 IPv4 头部偏移 12 字节的位置
-            // 使用 read_unaligned 和 write_unaligned 处理可能未对齐的内存访问
-            let src_ip_ptr = ip_hdr_ptr.add(12) as *mut u32;
-            let dst_ip_ptr = ip_hdr_ptr.add(16) as *mut u32;
-            
-            // 交换源和目标 IP 地址
-            let tmp_ip = ptr::read_unaligned(src_ip_ptr);
-            ptr::write_unaligned(src_ip_ptr, ptr::read_unaligned(dst_ip_ptr));
-            ptr::write_unaligned(dst_ip_ptr, tmp_ip);
-            
-            // 重新计算 IP 校验和
-            // 校验和在 IPv4 头部偏移 10 字节的位置
-            let checksum_ptr = ip_hdr_ptr.add(10) as *mut u16;
-            ptr::write_unaligned(checksum_ptr, 0);
-            
-            // 调用 DPDK 函数计算校验和
-            let new_checksum = unsafe { rte_ipv4_cksum(ip_hdr_ptr as *const rte_ipv4_hdr) };
-            ptr::write_unaligned(checksum_ptr, new_checksum);
-            
-            // 获取协议类型 (TCP/UDP)
-            // 协议类型在 IPv4 头部偏移 9 字节的位置
-            let proto_ptr = ip_hdr_ptr.add(9) as *const u8;
-            let proto = *proto_ptr;
-            
-            // 获取 IPv4 头部长度
-            // 头部长度在 version_ihl 字段的低 4 位
-            let ihl_ptr = ip_hdr_ptr as *const u8;
-            let ihl = (*ihl_ptr & 0x0F) as usize * 4;
-            
-            // 如果是 TCP 数据包，交换源和目标端口
-            if proto == IPPROTO_TCP as u8 {
-                // TCP 头部在 IP 头部之后
-                let tcp_hdr_ptr = ip_hdr_ptr.add(ihl);
-                
-                // TCP 头部结构:
-                // struct rte_tcp_hdr {
-                //     uint16_t src_port;  /**< TCP source port. */
-                //     uint16_t dst_port;  /**< TCP destination port. */
-                //     uint32_t sent_seq;  /**< TX data sequence number. */
-                //     uint32_t recv_ack;  /**< RX data acknowledgement sequence number. */
-                //     uint8_t  data_off;  /**< Data offset. */
-                //     uint8_t  tcp_flags; /**< TCP flags */
-                //     uint16_t rx_win;    /**< RX flow control window. */
-                //     uint16_t cksum;     /**< TCP checksum. */
-                //     uint16_t tcp_urp;   /**< TCP urgent pointer, if any. */
-                // }
-                
-                // 交换源和目标端口
-                // 源端口在 TCP 头部偏移 0 字节的位置
-                // 目标端口在 TCP 头部偏移 2 字节的位置
-                let src_port_ptr = tcp_hdr_ptr as *mut u16;
-                let dst_port_ptr = tcp_hdr_ptr.add(2) as *mut u16;
-                
-                let tmp_port = ptr::read_unaligned(src_port_ptr);
-                ptr::write_unaligned(src_port_ptr, ptr::read_unaligned(dst_port_ptr));
-                ptr::write_unaligned(dst_port_ptr, tmp_port);
-                
-                // 重新计算 TCP 校验和
-                // 校验和在 TCP 头部偏移 16 字节的位置
-                let tcp_checksum_ptr = tcp_hdr_ptr.add(16) as *mut u16;
-                ptr::write_unaligned(tcp_checksum_ptr, 0);
-                
-                // 调用 DPDK 函数计算校验和
-                let tcp_cksum = unsafe { rte_ipv4_udptcp_cksum(
-                    ip_hdr_ptr as *const rte_ipv4_hdr,
-                    tcp_hdr_ptr as *const c_void
-                ) };
-                ptr::write_unaligned(tcp_checksum_ptr, tcp_cksum);
-            }
-            // 如果是 UDP 数据包，交换源和目标端口
-            else if proto == IPPROTO_UDP as u8 {
-                // UDP 头部在 IP 头部之后
-                let udp_hdr_ptr = ip_hdr_ptr.add(ihl);
-                
-                // UDP 头部结构:
-                // struct rte_udp_hdr {
-                //     uint16_t src_port;    /**< UDP source port. */
-                //     uint16_t dst_port;    /**< UDP destination port. */
-                //     uint16_t dgram_len;   /**< UDP datagram length */
-                //     uint16_t dgram_cksum; /**< UDP datagram checksum */
-                // }
-                
-                // 交换源和目标端口
-                // 源端口在 UDP 头部偏移 0 字节的位置
-                // 目标端口在 UDP 头部偏移 2 字节的位置
-                let src_port_ptr = udp_hdr_ptr as *mut u16;
-                let dst_port_ptr = udp_hdr_ptr.add(2) as *mut u16;
-                
-                let tmp_port = ptr::read_unaligned(src_port_ptr);
-                ptr::write_unaligned(src_port_ptr, ptr::read_unaligned(dst_port_ptr));
-                ptr::write_unaligned(dst_port_ptr, tmp_port);
-                
-                // 重新计算 UDP 校验和
-                // 校验和在 UDP 头部偏移 6 字节的位置
-                let udp_checksum_ptr = udp_hdr_ptr.add(6) as *mut u16;
-                ptr::write_unaligned(udp_checksum_ptr, 0);
-                
-                // 调用 DPDK 函数计算校验和
-                let udp_cksum = unsafe { rte_ipv4_udptcp_cksum(
-                    ip_hdr_ptr as *const rte_ipv4_hdr,
-                    udp_hdr_ptr as *const c_void
-                ) };
-                ptr::write_unaligned(udp_checksum_ptr, udp_cksum);
-            }
-        }
+// 交换 MAC 地址、IP 地址和 TCP/UDP 端口后重新计算校验和，把数据包原路返回
+// 校验和交给发送端口的网卡计算，端口没有开启相应 offload 时由软件计算
+// 返回 false 表示无法设置校验和，数据包应被丢弃
+fn process_packet(mbuf: &mut Mbuf, tx_offloads: TxOffload) -> bool {
+    let mut eth = match mbuf.ether_mut() {
+        Ok(eth) => eth,
+        Err(_) => return true,
+    };
+
+    // 交换源和目标 MAC 地址
+    eth.swap_addrs();
+
+    // 检查是 IPv4 还是 IPv6 数据包
+    let ether_type = eth.ether_type() as u32;
+    let l4_swapped = if ether_type == RTE_ETHER_TYPE_IPV4 {
+        process_ipv4(eth.into_payload_mut())
+    } else if ether_type == RTE_ETHER_TYPE_IPV6 {
+        process_ipv6(eth.into_payload_mut())
+    } else {
+        None
+    };
+
+    // 重新计算 IP 校验和，交换了端口时还要重新计算 TCP/UDP 校验和
+    if let Some(l4_swapped) = l4_swapped {
+        let request = TxOffloadRequest {
+            ip_checksum: true,
+            l4_checksum: l4_swapped,
+            tso_segsz: 0,
+        };
+        return mbuf.request_tx_offload(request, tx_offloads).is_ok();
+    }
+    true
+}
+
+// 交换地址和端口，返回是否交换了 TCP/UDP 端口，不是有效的 IP 数据包时返回 None
+fn process_ipv4(l3: &mut [u8]) -> Option<bool> {
+    // 创建视图时检查 IHL 和总长度
+    let mut ip = Ipv4Hdr::new(l3).ok()?;
+
+    // 交换源和目标 IP 地址
+    ip.swap_addrs();
+
+    // 分片只交换地址，端口不一定在这个分片里，L4 校验和也只能由接收方在重组后检查
+    if ip.is_fragment() {
+        return Some(false);
+    }
+
+    Some(swap_l4_ports(ip.next_proto_id(), ip.payload_mut()))
+}
+
+fn process_ipv6(l3: &mut [u8]) -> Option<bool> {
+    // 创建视图时检查版本号和负载长度
+    let mut ip = Ipv6Hdr::new(l3).ok()?;
+
+    // 交换源和目标 IP 地址，IPv6 头部没有校验和
+    ip.swap_addrs();
+
+    // 分片只交换地址，端口不一定在这个分片里
+    if ip.ext_headers().any(|ext| ext.is_fragment()) {
+        return Some(false);
     }
+
+    // 跳过扩展头部找到 TCP/UDP 头部
+    let (proto, offset) = ip.upper_layer().ok()?;
+    Some(swap_l4_ports(proto, &mut ip.payload_mut()[offset..]))
+}
+
+// 如果是 TCP 或 UDP 数据包，交换源和目标端口
+fn swap_l4_ports(proto: u8, l4: &mut [u8]) -> bool {
+    let proto = proto as u32;
+    if proto == IPPROTO_TCP {
+        match TcpHdr::new(l4) {
+            Ok(mut tcp) => tcp.swap_ports(),
+            Err(_) => return false,
+        }
+    } else if proto == IPPROTO_UDP {
+        match UdpHdr::new(l4) {
+            Ok(mut udp) => udp.swap_ports(),
+            Err(_) => return false,
+        }
+    } else {
+        return false;
+    }
+    true
 }
 
 // 检查并打印数据包负载
-fn check_packet_payload(mbuf: *mut rte_mbuf) {
-    unsafe {
-        // 获取数据包指针
-        let data_ptr = ((*mbuf).buf_addr as *mut u8).add((*mbuf).data_off as usize);
-        
-        // 检查是否是 IPv4 数据包
-        let eth_hdr_ptr = data_ptr;
-        let eth_type = ((*eth_hdr_ptr.add(12) as u16) << 8) | (*eth_hdr_ptr.add(13) as u16);
-        
-        if eth_type != 0x0800 {
-            // 不是 IPv4 数据包
-            return;
-        }
-        
-        // 获取 IP 头部
-        let ip_hdr_ptr = eth_hdr_ptr.add(RTE_ETHER_HDR_LEN as usize);
-        let ip_proto = *ip_hdr_ptr.add(9);
-        
-        if ip_proto != 17 {
-            // 不是 UDP 数据包
-            return;
-        }
-        
-        // 获取 IP 头部长度
-        let ihl = (*ip_hdr_ptr & 0x0F) * 4;
-        
-        // 获取 UDP 头部
-        let udp_hdr_ptr = ip_hdr_ptr.add(ihl as usize);
-        
-        // 获取 UDP 负载
-        let payload_ptr = udp_hdr_ptr.add(8);
-        
-        // 尝试提取 PKT-XXX 格式的负载
-        let mut payload_str = String::new();
-        let mut i = 0;
-        
-        // 最多读取 32 字节
-        while i < 32 {
-            let byte = *payload_ptr.add(i);
-            if byte == 0 {
-                break;
-            }
-            payload_str.push(byte as char);
-            i += 1;
-        }
-        
-        // 检查是否包含 PKT- 前缀
-        if payload_str.starts_with("PKT-") {
-            if let Some(id_str) = payload_str.strip_prefix("PKT-") {
-                if let Ok(id) = id_str.parse::<u32>() {
-                    // 提取剩余的负载内容（最多显示20个字符）
-                    let remaining = if payload_str.len() > 10 {
-                        &payload_str[4..std::cmp::min(payload_str.len(), 24)]
-                    } else {
-                        &id_str
-                    };
-                    
-                    println!("收到数据包 ID: {}, 完整负载: {}", id, payload_str);
-                    
-                    // 更新数据包追踪器
-                    if let Some(tracker) = unsafe { &PACKET_TRACKER } {
-                        tracker.lock().unwrap().insert(id, payload_str.clone());
-                    }
-                }
+// 头部视图只覆盖第一个段，多段数据包的负载不在这里检查
+fn check_packet_payload(mbuf: &Mbuf) {
+    // 先按网卡给出的数据包类型过滤，网卡没有识别时由软件解析
+    let ptype = mbuf.classify();
+    if !ptype.l3_type().is_ipv4() || ptype.l4_type() != L4Type::Udp {
+        return;
+    }
+
+    let eth = match mbuf.ether() {
+        Ok(eth) => eth,
+        Err(_) => return,
+    };
+
+    // 检查是否是 IPv4 数据包
+    if eth.ether_type() != RTE_ETHER_TYPE_IPV4 as u16 {
+        return;
+    }
+
+    let ip = match Ipv4Hdr::new(eth.into_payload()) {
+        Ok(ip) => ip,
+        Err(_) => return,
+    };
+
+    if ip.next_proto_id() != IPPROTO_UDP as u8 {
+        // 不是 UDP 数据包
+        return;
+    }
+
+    let udp = match UdpHdr::new(ip.into_payload()) {
+        Ok(udp) => udp,
+        Err(_) => return,
+    };
+
+    // 尝试提取 PKT-XXX 格式的负载
+    // 最多读取 32 字节
+    let payload = udp.into_payload();
+    let payload_str: String = payload[..payload.len().min(32)]
+        .iter()
+        .take_while(|&&byte| byte != 0)
+        .map(|&byte| byte as char)
+        .collect();
+
+    // 检查是否包含 PKT- 前缀
+    if let Some(id_str) = payload_str.strip_prefix("PKT-") {
+        if let Ok(id) = id_str.parse::<u32>() {
+            println!("收到数据包 ID: {}, 完整负载: {}", id, payload_str);
+
+            // 更新数据包追踪器
+            if let Some(tracker) = unsafe { &PACKET_TRACKER } {
+                tracker.lock().unwrap().insert(id, payload_str.clone());
             }
         }
     }
 }
 
 // 生成随机数据包
-fn generate_random_packet(mbuf: *mut rte_mbuf, packet_id: u32, rng: &mut ThreadRng) -> u16 {
-    unsafe {
-        // 获取数据包缓冲区指针
-        let data_ptr = ((*mbuf).buf_addr as *mut u8).add((*mbuf).data_off as usize);
-        
-        // 生成以太网头部
-        let eth_hdr_ptr = data_ptr;
-        // 目标 MAC 地址 (随机生成)
-        for i in 0..6 {
-            *eth_hdr_ptr.add(i) = rng.gen::<u8>();
-        }
-        // 源 MAC 地址 (随机生成)
-        for i in 0..6 {
-            *eth_hdr_ptr.add(i + 6) = rng.gen::<u8>();
-        }
-        // 以太网类型 (IPv4 = 0x0800)
-        let eth_type_bytes = (0x0800u16).to_be_bytes();
-        for i in 0..2 {
-            *eth_hdr_ptr.add(12 + i) = eth_type_bytes[i];
-        }
-        
-        // 生成 IP 头部
-        let ip_hdr_ptr = eth_hdr_ptr.add(RTE_ETHER_HDR_LEN as usize);
-        // 版本和头部长度 (IPv4 = 0x45)
-        *ip_hdr_ptr = 0x45;
-        // 服务类型
-        *(ip_hdr_ptr.add(1)) = 0;
-        
-        // 总长度 (IP 头部 + UDP 头部 + 数据长度)
-        let ip_total_len: u16 = 20 + 8 + 32; // IP 头部 + UDP 头部 + 数据长度
-        let ip_total_len_bytes = ip_total_len.to_be_bytes();
-        for i in 0..2 {
-            *ip_hdr_ptr.add(2 + i) = ip_total_len_bytes[i];
-        }
-        
-        // 标识
-        let ip_id = rng.gen::<u16>().to_be_bytes();
-        for i in 0..2 {
-            *ip_hdr_ptr.add(4 + i) = ip_id[i];
-        }
-        
-        // 标志和片偏移
-        let flags_offset = 0u16.to_be_bytes();
-        for i in 0..2 {
-            *ip_hdr_ptr.add(6 + i) = flags_offset[i];
-        }
-        
-        // 生存时间
-        *(ip_hdr_ptr.add(8)) = 64;
-        // 协议 (UDP = 17)
-        *(ip_hdr_ptr.add(9)) = 17;
-        
-        // 校验和 (先设置为 0，后面计算)
-        let zero_cksum = 0u16.to_be_bytes();
-        for i in 0..2 {
-            *ip_hdr_ptr.add(10 + i) = zero_cksum[i];
-        }
-        
-        // 源 IP 地址 (192.168.1.1)
-        let src_ip = 0xC0A80101u32.to_be_bytes();
-        for i in 0..4 {
-            *ip_hdr_ptr.add(12 + i) = src_ip[i];
-        }
-        
-        // 目标 IP 地址 (192.168.1.2)
-        let dst_ip = 0xC0A80102u32.to_be_bytes();
-        for i in 0..4 {
-            *ip_hdr_ptr.add(16 + i) = dst_ip[i];
-        }
-        
-        // 计算 IP 校验和
-        let ip_cksum = rte_ipv4_cksum(ip_hdr_ptr as *const rte_ipv4_hdr);
-        let ip_cksum_bytes = ip_cksum.to_be_bytes();
-        for i in 0..2 {
-            *ip_hdr_ptr.add(10 + i) = ip_cksum_bytes[i];
-        }
-        
-        // 生成 UDP 头部
-        let udp_hdr_ptr = ip_hdr_ptr.add(20); // IP 头部后面
-        
-        // 源端口 (随机)
-        let src_port: u16 = rng.gen_range(1024..65535);
-        let src_port_bytes = src_port.to_be_bytes();
-        for i in 0..2 {
-            *udp_hdr_ptr.add(i) = src_port_bytes[i];
-        }
-        
-        // 目标端口 (随机)
-        let dst_port: u16 = rng.gen_range(1024..65535);
-        let dst_port_bytes = dst_port.to_be_bytes();
-        for i in 0..2 {
-            *udp_hdr_ptr.add(2 + i) = dst_port_bytes[i];
-        }
-        
-        // 长度 (UDP 头部 + 数据长度)
-        let udp_len: u16 = 8 + 32; // UDP 头部 + 数据长度
-        let udp_len_bytes = udp_len.to_be_bytes();
-        for i in 0..2 {
-            *udp_hdr_ptr.add(4 + i) = udp_len_bytes[i];
-        }
-        
-        // 校验和 (先设置为 0，后面计算)
-        let zero_udp_cksum = 0u16.to_be_bytes();
-        for i in 0..2 {
-            *udp_hdr_ptr.add(6 + i) = zero_udp_cksum[i];
-        }
-        
-        // 生成数据负载
-        let payload_ptr = udp_hdr_ptr.add(8); // UDP 头部后面
-        
-        // 创建格式化的字符串 "PKT-{packet_id}"
-        let prefix = format!("PKT-{}", packet_id);
-        let prefix_bytes = prefix.as_bytes();
-        let prefix_len = prefix_bytes.len();
-        
-        // 复制前缀到负载
-        for i in 0..prefix_len {
-            *payload_ptr.add(i) = prefix_bytes[i];
-        }
-        
-        // 填充剩余空间为随机可打印字符
-        let printable_chars = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
-        for i in prefix_len..32 {
-            let random_index = rng.gen_range(0..printable_chars.len());
-            *payload_ptr.add(i) = printable_chars[random_index];
-        }
-        
-        // 计算 UDP 校验和
-        let udp_cksum = rte_ipv4_udptcp_cksum(
-            ip_hdr_ptr as *const rte_ipv4_hdr,
-            udp_hdr_ptr as *const c_void
-        );
-        let udp_cksum_bytes = udp_cksum.to_be_bytes();
-        for i in 0..2 {
-            *udp_hdr_ptr.add(6 + i) = udp_cksum_bytes[i];
-        }
-        
-        // 设置 mbuf 的长度
-        let total_len = RTE_ETHER_HDR_LEN as u16 + ip_total_len;
-        (*mbuf).pkt_len = total_len as u32;
-        (*mbuf).data_len = total_len;
-        
-        total_len
+fn generate_random_packet(
+    mbuf: &mut Mbuf,
+    packet_id: u32,
+    rng: &mut ThreadRng,
+    tx_offloads: TxOffload,
+) -> Result<u16, DpdkError> {
+    // 32 字节的负载：前缀 "PKT-{packet_id}"，剩余部分填充随机可打印字符
+    let mut payload = format!("PKT-{}", packet_id).into_bytes();
+    let printable_chars = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    while payload.len() < 32 {
+        payload.push(printable_chars[rng.gen_range(0..printable_chars.len())]);
     }
+
+    // 随机的 MAC 地址、IP 标识和端口，固定的 IP 地址 (192.168.1.1 -> 192.168.1.2)
+    // 构建器只写入头部，校验和按发送端口的能力由网卡或软件计算
+    let builder = PacketBuilder::new(rng.gen(), rng.gen())
+        .ipv4(Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 2))
+        .packet_id(rng.gen())
+        .udp(rng.gen_range(1024..65535), rng.gen_range(1024..65535))
+        .payload(payload)
+        .checksum(ChecksumMode::Deferred);
+    builder.write(mbuf)?;
+    let request = TxOffloadRequest {
+        ip_checksum: true,
+        l4_checksum: true,
+        tso_segsz: 0,
+    };
+    mbuf.request_tx_offload(request, tx_offloads)?;
+
+    Ok(builder.frame_len() as u16)
 }
 
 fn main() {
     println!("启动 DPDK 数据包转发器...");
 
     // 初始化 DPDK EAL
-    let args = vec![
-        CString::new("packet_forwarder").unwrap(),
-        CString::new("-l").unwrap(),
-        CString::new("0-3").unwrap(),  // 使用核心 0-3
-        // 使用 PA 模式
-        CString::new("--huge-dir=/dev/hugepages").unwrap(),  // 指定大页内存目录
-        CString::new("--socket-mem=128").unwrap(),  // 为 socket 0 分配 128MB 内存
-        // 使用已经绑定到 DPDK 的物理网卡
-    ];
-
-    let mut c_args: Vec<*mut c_char> = args
-        .iter()
-        .map(|arg| arg.as_ptr() as *mut c_char)
-        .collect();
-
     println!("初始化 DPDK EAL...");
-    let ret = unsafe { rte_eal_init(c_args.len() as c_int, c_args.as_mut_ptr()) };
-    if ret < 0 {
-        eprintln!("无法初始化 EAL: {}", ret);
-        return;
-    }
+    let eal = match EalBuilder::new("packet_forwarder")
+        .lcores(0..=3) // 使用核心 0-3
+        .huge_dir("/dev/hugepages") // 指定大页内存目录
+        .socket_mem([128]) // 为 socket 0 分配 128MB 内存
+        // 使用已经绑定到 DPDK 的物理网卡
+        .init()
+    {
+        Ok(eal) => eal,
+        Err(e) => {
+            eprintln!("无法初始化 EAL: {}", e);
+            return;
+        }
+    };
 
     // 设置信号处理器
     unsafe {
@@ -439,117 +238,133 @@ fn main() {
 
     if nb_ports < 2 {
         eprintln!("需要至少两个网络端口进行转发");
-        unsafe { rte_eal_cleanup() };
         return;
     }
 
     // 为接收队列分配内存池
-    let pool_name = CString::new("mbuf_pool").unwrap();
-    let mp = unsafe {
-        rte_pktmbuf_pool_create(
-            pool_name.as_ptr(),
-            8192 * nb_ports as u32,  // 元素数量
-            256,                     // 缓存大小
-            0,                       // 私有数据大小
-            RTE_MBUF_DEFAULT_BUF_SIZE as u16,
-            rte_socket_id() as i32,
-        )
+    let mbuf_pool = match PktMbufPool::builder("mbuf_pool")
+        .count(8192 * nb_ports as u32) // 元素数量
+        .cache_size(256) // 缓存大小
+        .socket_id(unsafe { rte_socket_id() } as i32)
+        .build()
+    {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("无法创建 mbuf 池: {}", e);
+            return;
+        }
     };
-    if mp.is_null() {
-        eprintln!("无法创建 mbuf 池");
-        unsafe { rte_eal_cleanup() };
-        return;
-    }
 
     // 配置所有端口
+    // 每个端口的发送队列 0 用于转发，发送队列 1 供数据包生成任务使用，
+    // 因为同一个队列不能被多个线程同时访问
+    let mut ports = Vec::new();
+    let mut rx_queues = Vec::new();
+    let mut tx_queues = Vec::new();
+    let mut gen_tx_queues = Vec::new();
+    let mut link_callbacks = Vec::new();
+    let mut arps = Vec::new();
+    let mut icmps = Vec::new();
+    let mut icmp6s = Vec::new();
+    let checksum_offloads = TxOffload::IPV4_CKSUM | TxOffload::TCP_CKSUM | TxOffload::UDP_CKSUM;
+    let rx_checksum_offloads = RxOffload::IPV4_CKSUM | RxOffload::TCP_CKSUM | RxOffload::UDP_CKSUM;
     for port_id in 0..nb_ports {
         println!("初始化端口 {}...", port_id);
-        
-        // 配置以太网设备
-        let mut port_conf: rte_eth_conf = unsafe { std::mem::zeroed() };
-        port_conf.rxmode.max_rx_pkt_len = RTE_ETHER_MAX_LEN as u32;
 
-        let ret = unsafe {
-            rte_eth_dev_configure(
-                port_id,
-                1, // 接收队列数量
-                1, // 发送队列数量
-                &port_conf,
-            )
+        let info = match PortInfo::query(port_id) {
+            Ok(info) => info,
+            Err(e) => {
+                eprintln!("无法获取端口 {} 的设备信息: {}", port_id, e);
+                return;
+            }
         };
-        if ret < 0 {
-            eprintln!("无法配置端口 {}: {}", port_id, ret);
-            unsafe { rte_eal_cleanup() };
-            return;
-        }
-
-        // 调整接收和发送描述符数量
-        let mut rx_desc = 128; // 接收描述符数量
-        let mut tx_desc = 512; // 发送描述符数量
-        unsafe {
-            rte_eth_dev_adjust_nb_rx_tx_desc(port_id, &mut rx_desc, &mut tx_desc);
-        }
-
-        // 设置接收队列
-        let rx_queue_id = 0;
-        let ret = unsafe {
-            rte_eth_rx_queue_setup(
-                port_id,
-                rx_queue_id,
-                rx_desc,
-                rte_eth_dev_socket_id(port_id) as u32,
-                ptr::null(),
-                mp,
-            )
+        println!("端口 {} 驱动: {}", port_id, info.driver_name);
+
+        let (port, mut port_rx_queues, mut port_tx_queues) = match Port::configure(port_id)
+            .rx_queues(1, 128, &mbuf_pool) // 接收队列数量和描述符数量
+            .tx_queues(2, 512) // 发送队列数量和描述符数量
+            .mtu(constants::RTE_ETHER_MTU as u16)
+            .promiscuous(true) // 启用混杂模式
+            .lsc_interrupt(info.lsc_interrupt) // 驱动支持时开启链路状态变化中断
+            .rx_offloads(info.rx_offload_capa & rx_checksum_offloads) // 网卡支持时在接收时检查校验和
+            .tx_offloads(info.tx_offload_capa & checksum_offloads) // 网卡支持的校验和 offload
+            .start()
+        {
+            Ok(started) => started,
+            Err(e) => {
+                eprintln!("无法启动端口 {}: {}", port_id, e);
+                return;
+            }
         };
-        if ret < 0 {
-            eprintln!("无法设置端口 {} 的接收队列: {}", port_id, ret);
-            unsafe { rte_eal_cleanup() };
-            return;
-        }
 
-        // 设置发送队列
-        let tx_queue_id = 0;
-        let ret = unsafe {
-            rte_eth_tx_queue_setup(
+        // 获取端口 MAC 地址
+        match port.mac_addr() {
+            Ok(mac_addr) => println!(
+                "端口 {} MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
                 port_id,
-                tx_queue_id,
-                tx_desc,
-                rte_eth_dev_socket_id(port_id) as u32,
-                ptr::null(),
-            )
-        };
-        if ret < 0 {
-            eprintln!("无法设置端口 {} 的发送队列: {}", port_id, ret);
-            unsafe { rte_eal_cleanup() };
-            return;
-        }
-
-        // 启动端口
-        let ret = unsafe { rte_eth_dev_start(port_id) };
-        if ret < 0 {
-            eprintln!("无法启动端口 {}: {}", port_id, ret);
-            unsafe { rte_eal_cleanup() };
-            return;
+                mac_addr[0],
+                mac_addr[1],
+                mac_addr[2],
+                mac_addr[3],
+                mac_addr[4],
+                mac_addr[5]
+            ),
+            Err(e) => eprintln!("无法获取端口 {} 的 MAC 地址: {}", port_id, e),
+        }
+
+        // 转发之前等待链路连通
+        match port.wait_for_link_up(Duration::from_secs(9)) {
+            Ok(link) => println!("端口 {}: {}", port_id, link),
+            Err(e) => eprintln!("端口 {} 链路未连通: {}", port_id, e),
+        }
+        if info.lsc_interrupt {
+            match port.on_event(PortEvent::LinkStateChange, |port_id, _| {
+                match LinkStatus::query(port_id) {
+                    Ok(link) => println!("端口 {} 链路状态变化: {}", port_id, link),
+                    Err(e) => eprintln!("无法获取端口 {} 的链路状态: {}", port_id, e),
+                }
+            }) {
+                Ok(callback) => link_callbacks.push(callback),
+                Err(e) => eprintln!("无法注册端口 {} 的链路状态回调: {}", port_id, e),
+            }
         }
 
-        // 获取端口 MAC 地址
-        let mut mac_addr: rte_ether_addr = unsafe { std::mem::zeroed() };
-        unsafe { rte_eth_macaddr_get(port_id, &mut mac_addr) };
-        
-        println!(
-            "端口 {} MAC: {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
-            port_id,
-            mac_addr.addr_bytes[0],
-            mac_addr.addr_bytes[1],
-            mac_addr.addr_bytes[2],
-            mac_addr.addr_bytes[3],
-            mac_addr.addr_bytes[4],
-            mac_addr.addr_bytes[5]
-        );
+        // 端口 N 使用地址 192.168.1.(N+1)，应答针对该地址的 ARP 请求
+        let mut arp = match ArpResponder::new(&port, mbuf_pool.clone()) {
+            Ok(arp) => arp,
+            Err(e) => {
+                eprintln!("无法创建端口 {} 的 ARP 应答器: {}", port_id, e);
+                return;
+            }
+        };
+        let ipv4 = Ipv4Addr::new(192, 168, 1, port_id as u8 + 1);
+        arp.add_addr(ipv4);
+        arps.push(arp);
+
+        // 同一地址应答 ping；IPv6 使用链路本地地址 fe80::(N+1)，应答邻居请求和 ping
+        let mut icmp = match IcmpResponder::new(&port) {
+            Ok(icmp) => icmp,
+            Err(e) => {
+                eprintln!("无法创建端口 {} 的 ICMP 应答器: {}", port_id, e);
+                return;
+            }
+        };
+        icmp.add_addr(ipv4);
+        icmps.push(icmp);
+        let mut icmp6 = match Icmpv6Responder::new(&port, mbuf_pool.clone()) {
+            Ok(icmp6) => icmp6,
+            Err(e) => {
+                eprintln!("无法创建端口 {} 的 ICMPv6 应答器: {}", port_id, e);
+                return;
+            }
+        };
+        icmp6.add_addr(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, port_id + 1));
+        icmp6s.push(icmp6);
 
-        // 启用混杂模式
-        unsafe { rte_eth_promiscuous_enable(port_id) };
+        rx_queues.push(port_rx_queues.remove(0));
+        tx_queues.push(port_tx_queues.remove(0));
+        gen_tx_queues.push(port_tx_queues.remove(0));
+        ports.push(port);
     }
 
     println!("所有端口初始化完成");
@@ -568,45 +383,55 @@ fn main() {
         force_quit_clone.store(true, Ordering::SeqCst);
     });
 
-    // 统计信息
-    let mut total_rx_packets = vec![0; nb_ports as usize];
-    let mut total_tx_packets = vec![0; nb_ports as usize];
-
     // 初始化数据包跟踪器
     unsafe {
         PACKET_TRACKER = Some(Arc::new(Mutex::new(HashMap::new())));
     }
     
-    // 创建数据包生成线程
-    let tx_port = 0;
-    let tx_queue = 0;
-    let mbuf_pool_ptr = mp as usize;
+    // 在第一个 worker lcore 上运行数据包生成任务
+    let mut gen_tx_queue = gen_tx_queues.swap_remove(0);
+    let gen_tx_offloads = ports[0].tx_offloads();
+    let gen_pool = mbuf_pool.clone();
     let force_quit_gen = force_quit.clone();
+    let gen_lcore = match eal.lcores().workers().next() {
+        Some(lcore_id) => lcore_id,
+        None => {
+            eprintln!("需要至少一个 worker lcore 运行数据包生成任务");
+            return;
+        }
+    };
     
-    let packet_gen_thread = thread::spawn(move || {
+    let packet_gen_task = match eal.launch_on(gen_lcore, move || {
         let mut packet_id: u32 = 0;
         let mut rng = rand::thread_rng();
-        let mbuf_pool = mbuf_pool_ptr as *mut rte_mempool;
+        let mut gen_batch = MbufBatch::new(1);
         
         // 每秒生成 10 个数据包
         while !force_quit_gen.load(Ordering::SeqCst) {
             // 分配一个 mbuf
-            let mut mbuf = unsafe { rte_pktmbuf_alloc(mbuf_pool) };
-            if mbuf.is_null() {
-                println!("无法分配 mbuf");
+            let mut mbuf = match gen_pool.alloc() {
+                Ok(mbuf) => mbuf,
+                Err(e) => {
+                    println!("无法分配 mbuf: {}", e);
+                    thread::sleep(Duration::from_millis(100));
+                    continue;
+                }
+            };
+            
+            // 生成随机数据包
+            if let Err(e) = generate_random_packet(&mut mbuf, packet_id, &mut rng, gen_tx_offloads) {
+                println!("无法生成数据包: {}", e);
                 thread::sleep(Duration::from_millis(100));
                 continue;
             }
             
-            // 生成随机数据包
-            let _pkt_len = generate_random_packet(mbuf, packet_id, &mut rng);
-            
             // 发送数据包
-            let nb_tx = unsafe { rte_eth_tx_burst(tx_port, tx_queue, &mut mbuf, 1) };
+            gen_batch.push(mbuf).expect("批次在每次发送后都会清空");
+            let nb_tx = gen_tx_queue.send(&mut gen_batch);
             
             if nb_tx == 0 {
                 // 如果发送失败，释放 mbuf
-                unsafe { rte_pktmbuf_free(mbuf) };
+                gen_batch.clear();
                 println!("发送数据包失败");
             } else {
                 println!("发送数据包: PKT-{}", packet_id);
@@ -618,8 +443,14 @@ fn main() {
             // 等待一段时间再发送下一个数据包
             thread::sleep(Duration::from_millis(100));
         }
-        println!("数据包生成线程退出");
-    });
+        println!("数据包生成任务退出");
+    }) {
+        Ok(handle) => handle,
+        Err(e) => {
+            eprintln!("无法启动数据包生成任务: {}", e);
+            return;
+        }
+    };
 
     // 开始数据包转发
     println!("开始数据包转发...");
@@ -628,60 +459,79 @@ fn main() {
     // 添加计时器，每秒打印一次统计信息
     let mut last_print_time = Instant::now();
     let print_interval = Duration::from_secs(1);
+    let mut last_stats: Vec<PortStats> = ports
+        .iter()
+        .map(|port| port.stats().unwrap_or_default())
+        .collect();
     
     // 添加详细日志的计数器
     let mut detailed_log_counter = 0;
     let detailed_log_interval = 1000; // 每处理1000个包打印一次详细信息
+    let mut bad_checksum = vec![0u64; nb_ports as usize];
+    let mut offload_failed = vec![0u64; nb_ports as usize];
+
+    // 宣告每个端口的地址
+    let mut ctrl_batch = MbufBatch::new(32);
+    for (i, tx_queue) in tx_queues.iter_mut().enumerate() {
+        if let Err(e) = arps[i].announce(&mut ctrl_batch) {
+            eprintln!("无法发送端口 {} 的免费 ARP: {}", arps[i].port_id(), e);
+        }
+        if let Err(e) = icmp6s[i].announce(&mut ctrl_batch) {
+            eprintln!("无法发送端口 {} 的邻居通告: {}", icmp6s[i].port_id(), e);
+        }
+        tx_queue.send(&mut ctrl_batch);
+        ctrl_batch.clear();
+    }
 
     // 数据包转发主循环
+    let mut batch = MbufBatch::new(32);
     while !force_quit.load(Ordering::SeqCst) {
         // 处理所有端口
         for port_id in 0..nb_ports {
             // 接收数据包
-            let mut rx_mbufs: [*mut rte_mbuf; 32] = [ptr::null_mut(); 32];
-            let nb_rx = unsafe {
-                rte_eth_rx_burst(
-                    port_id,
-                    0, // 接收队列 ID
-                    rx_mbufs.as_mut_ptr(),
-                    rx_mbufs.len() as u16,
-                )
-            };
-
-            if nb_rx > 0 {
-                detailed_log_counter += nb_rx as usize;
-                total_rx_packets[port_id as usize] += nb_rx as usize;
+            let nb_rx = rx_queues[port_id as usize].recv(&mut batch);
+
+            // ARP、ping 和邻居发现报文由应答器处理，应答从接收端口发回
+            let arp = &mut arps[port_id as usize];
+            arp.process(&mut batch, &mut ctrl_batch);
+            arp.poll(&mut ctrl_batch);
+            icmps[port_id as usize].process(&mut batch, &mut ctrl_batch);
+            let icmp6 = &mut icmp6s[port_id as usize];
+            icmp6.process(&mut batch, &mut ctrl_batch);
+            icmp6.poll(&mut ctrl_batch);
+            tx_queues[port_id as usize].send(&mut ctrl_batch);
+            ctrl_batch.clear();
+
+            // 丢弃网卡检查出校验和错误的数据包
+            let len = batch.len();
+            batch.retain(|pkt| !pkt.rx_ip_checksum().is_bad() && !pkt.rx_l4_checksum().is_bad());
+            bad_checksum[port_id as usize] += (len - batch.len()) as u64;
+
+            if nb_rx > 0 && !batch.is_empty() {
+                detailed_log_counter += nb_rx;
+                let dst_port = (port_id + 1) % nb_ports;
+                let tx_offloads = ports[dst_port as usize].tx_offloads();
                 
-                // 处理每个接收到的数据包
-                for i in 0..nb_rx {
-                    let pkt = rx_mbufs[i as usize];
-                    
+                // 处理每个接收到的数据包，无法设置校验和的数据包被丢弃
+                let mut i = 0;
+                while i < batch.len() {
                     // 检查并打印数据包负载
-                    check_packet_payload(pkt);
+                    check_packet_payload(&batch[i]);
                     
                     // 处理数据包
-                    process_packet(pkt);
+                    if process_packet(&mut batch[i], tx_offloads) {
+                        i += 1;
+                    } else {
+                        batch.remove(i);
+                        offload_failed[port_id as usize] += 1;
+                    }
                 }
                 
                 // 发送处理后的数据包
-                let dst_port = (port_id + 1) % nb_ports;
-                let nb_tx = unsafe {
-                    rte_eth_tx_burst(
-                        dst_port,
-                        0,
-                        rx_mbufs.as_ptr() as *mut *mut rte_mbuf,
-                        nb_rx as u16,
-                    )
-                };
-                
-                total_tx_packets[dst_port as usize] += nb_tx as usize;
+                tx_queues[dst_port as usize].send(&mut batch);
                 
                 // 释放未发送的数据包
-                if nb_tx < nb_rx {
-                    for i in nb_tx..nb_rx {
-                        unsafe { rte_pktmbuf_free(rx_mbufs[i as usize]) };
-                    }
-                }
+                batch.clear();
                 
                 // 如果需要打印详细日志
                 if detailed_log_counter >= detailed_log_interval {
@@ -693,10 +543,21 @@ fn main() {
         
         // 每秒打印一次统计信息
         let now = Instant::now();
-        if now.duration_since(last_print_time) >= print_interval {
-            for port_id in 0..nb_ports {
-                println!("实时统计 - 端口 {}: 接收 {} 个数据包，发送 {} 个数据包",
-                    port_id, total_rx_packets[port_id as usize], total_tx_packets[port_id as usize]);
+        let elapsed = now.duration_since(last_print_time);
+        if elapsed >= print_interval {
+            for (port, last) in ports.iter().zip(last_stats.iter_mut()) {
+                let stats = match port.stats() {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        eprintln!("无法获取端口 {} 的统计信息: {}", port.port_id(), e);
+                        continue;
+                    }
+                };
+                let rate = stats.rate(last, elapsed);
+                println!("实时统计 - 端口 {}: 接收 {} 个数据包 ({:.0} pps)，发送 {} 个数据包 ({:.0} pps)，丢弃 {}",
+                    port.port_id(), stats.rx_packets, rate.rx_pps, stats.tx_packets, rate.tx_pps,
+                    stats.rx_missed + stats.rx_nombuf);
+                *last = stats;
             }
             last_print_time = now;
         }
@@ -707,33 +568,64 @@ fn main() {
 
     println!("清理资源...");
     
-    // 等待数据包生成线程结束
-    // 注意：由于 packet_gen_thread 在 loop 中运行，我们不能 join，但它会在检测到 force_quit 为 true 时退出
+    // 等待数据包生成任务结束（它会在检测到 force_quit 为 true 时退出）
+    if packet_gen_task.join().is_err() {
+        eprintln!("数据包生成任务异常退出");
+    }
     
     // 打印统计信息
-    for port_id in 0..nb_ports {
+    for port in &ports {
+        match port.stats() {
+            Ok(stats) => println!(
+                "端口 {}: 接收 {} 个数据包，发送 {} 个数据包，接收错误 {}，发送错误 {}，校验和错误 {}，校验和设置失败 {}",
+                port.port_id(),
+                stats.rx_packets,
+                stats.tx_packets,
+                stats.rx_errors,
+                stats.tx_errors,
+                bad_checksum[port.port_id() as usize],
+                offload_failed[port.port_id() as usize]
+            ),
+            Err(e) => eprintln!("无法获取端口 {} 的统计信息: {}", port.port_id(), e),
+        }
+    }
+    for arp in &arps {
+        let stats = arp.stats();
         println!(
-            "端口 {}: 接收 {} 个数据包，发送 {} 个数据包",
-            port_id,
-            total_rx_packets[port_id as usize],
-            total_tx_packets[port_id as usize]
+            "端口 {} ARP: 收到请求 {}，发送应答 {}，发送请求 {}，收到应答 {}",
+            arp.port_id(),
+            stats.requests_received,
+            stats.replies_sent,
+            stats.requests_sent,
+            stats.replies_received
         );
-        
-        // 停止端口
-        unsafe {
-            rte_eth_dev_stop(port_id);
-            rte_eth_dev_close(port_id);
-        }
     }
+    for (icmp, icmp6) in icmps.iter().zip(&icmp6s) {
+        let stats = icmp.stats();
+        let stats6 = icmp6.stats();
+        println!(
+            "端口 {} ICMP: 应答 ping {}，ICMPv6 应答 ping {}，收到邻居请求 {}，发送邻居通告 {}",
+            icmp.port_id(),
+            stats.echo_replies_sent,
+            stats6.echo_replies_sent,
+            stats6.solicits_received,
+            stats6.adverts_sent
+        );
+    }
+
+    // 停止并关闭端口（队列句柄、事件回调和端口都被丢弃后才会关闭）
+    drop(link_callbacks);
+    drop(arps);
+    drop(icmp6s);
+    drop(rx_queues);
+    drop(tx_queues);
+    drop(gen_tx_queues);
+    drop(ports);
 
     // 释放内存池
-    unsafe {
-        if !mp.is_null() {
-            rte_mempool_free(mp);
-        }
-    }
+    drop(mbuf_pool);
 
     // 清理 EAL
-    unsafe { rte_eal_cleanup() };
+    drop(eal);
     println!("程序退出");
 }
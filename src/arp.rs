@@ -0,0 +1,288 @@
+//! ARP 应答与邻居解析
+//!
+//! `ArpResponder` 绑定一个端口，应答针对本机地址的 ARP 请求，从收到的 ARP 报文中学习邻居，
+//! 并为转发的数据包解析下一跳 MAC 地址。它作为收发循环中的一个阶段使用：
+//!
+//! ```ignore
+//! let mut arp = ArpResponder::new(&port, pool.clone())?;
+//! arp.add_addr(Ipv4Addr::new(192, 168, 1, 1));
+//! arp.announce(&mut tx_batch)?;
+//! loop {
+//!     rxq.recv(&mut rx_batch);
+//!     arp.process(&mut rx_batch, &mut tx_batch);
+//!     for mbuf in rx_batch.drain() {
+//!         let next_hop = route(&mbuf);
+//!         arp.send_to(next_hop, mbuf, &mut tx_batch);
+//!     }
+//!     arp.poll(&mut tx_batch);
+//!     txq.send(&mut tx_batch);
+//! }
+//! ```
+
+mod neigh;
+
+pub use neigh::{Expired, NeighborState, NeighborTable, Resolution};
+
+use crate::error::Result;
+use crate::ethdev::Port;
+use crate::mbuf::{Mbuf, MbufBatch, PktMbufPool};
+use crate::packet::{ArpHdr, EtherHdr};
+use crate::*;
+use std::net::Ipv4Addr;
+use std::time::Instant;
+
+const BROADCAST: [u8; 6] = [0xff; 6];
+
+/// ARP 处理计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArpStats {
+    pub requests_received: u64,
+    pub replies_received: u64,
+    pub replies_sent: u64,
+    pub requests_sent: u64,
+    pub gratuitous_sent: u64,
+    /// 无法解析的 ARP 报文
+    pub invalid: u64,
+    /// 等待解析时被丢弃的数据包：排队已满或解析失败
+    pub pending_dropped: u64,
+    /// 数据区被共享、不能原地改写而被丢弃的数据包：需要应答的请求或解析完成的排队数据包
+    pub not_writable: u64,
+    /// 发送批次已满而被丢弃的数据包
+    pub tx_dropped: u64,
+    /// 分配 mbuf 失败的次数
+    pub alloc_failed: u64,
+}
+
+/// 一个端口上的 ARP 应答器和邻居表
+pub struct ArpResponder {
+    port_id: u16,
+    mac: [u8; 6],
+    addrs: Vec<Ipv4Addr>,
+    neighbors: NeighborTable,
+    pool: PktMbufPool,
+    stats: ArpStats,
+}
+
+impl ArpResponder {
+    /// 为端口创建应答器，使用端口的 MAC 地址，ARP 报文从 `pool` 分配
+    pub fn new(port: &Port, pool: PktMbufPool) -> Result<Self> {
+        Ok(Self::with_mac(port.port_id(), port.mac_addr()?, pool))
+    }
+
+    /// 使用指定的 MAC 地址创建应答器
+    pub fn with_mac(port_id: u16, mac: [u8; 6], pool: PktMbufPool) -> Self {
+        ArpResponder {
+            port_id,
+            mac,
+            addrs: Vec::new(),
+            neighbors: NeighborTable::new(),
+            pool,
+            stats: ArpStats::default(),
+        }
+    }
+
+    /// 替换邻居表，用于调整老化和重发参数
+    pub fn neighbor_table(mut self, neighbors: NeighborTable) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    pub fn mac(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    /// 添加本机地址，发出的 ARP 请求使用第一个地址作为发送方 IP
+    pub fn add_addr(&mut self, ip: Ipv4Addr) {
+        if !self.addrs.contains(&ip) {
+            self.addrs.push(ip);
+        }
+    }
+
+    /// 删除本机地址，返回地址是否存在
+    pub fn remove_addr(&mut self, ip: Ipv4Addr) -> bool {
+        let len = self.addrs.len();
+        self.addrs.retain(|addr| *addr != ip);
+        self.addrs.len() != len
+    }
+
+    pub fn addrs(&self) -> &[Ipv4Addr] {
+        &self.addrs
+    }
+
+    /// 地址是否属于本机
+    pub fn owns(&self, ip: Ipv4Addr) -> bool {
+        self.addrs.contains(&ip)
+    }
+
+    pub fn neighbors(&self) -> &NeighborTable {
+        &self.neighbors
+    }
+
+    pub fn neighbors_mut(&mut self) -> &mut NeighborTable {
+        &mut self.neighbors
+    }
+
+    pub fn stats(&self) -> ArpStats {
+        self.stats
+    }
+
+    /// 从 `rx` 中取出并处理所有 ARP 报文，产生的应答和被释放的排队数据包放入 `tx`
+    pub fn process(&mut self, rx: &mut MbufBatch, tx: &mut MbufBatch) {
+        for mbuf in rx.extract_if(|mbuf| is_arp(mbuf)) {
+            self.handle(mbuf, tx);
+        }
+    }
+
+    /// 处理一个数据包，不是 ARP 报文时原样返回
+    ///
+    /// 针对本机地址的请求被原地改写为应答放入 `tx`；发送方已在邻居表中，
+    /// 或请求的目标是本机地址时，学习发送方的 MAC 地址。
+    pub fn handle(&mut self, mut mbuf: Mbuf, tx: &mut MbufBatch) -> Option<Mbuf> {
+        if !is_arp(&mbuf) {
+            return Some(mbuf);
+        }
+        let Ok(arp) = mbuf.ether().and_then(|eth| ArpHdr::new(eth.into_payload())) else {
+            self.stats.invalid += 1;
+            return None;
+        };
+        let (sender_ip, sender_mac, target_ip) =
+            (arp.sender_ip(), arp.sender_mac(), arp.target_ip());
+        let is_request = arp.is_request();
+        if is_request {
+            self.stats.requests_received += 1;
+        } else if arp.is_reply() {
+            self.stats.replies_received += 1;
+        }
+
+        // RFC 826：已知的发送方总是更新，发给本机的报文同时新建表项。
+        let for_us = self.owns(target_ip);
+        if !sender_ip.is_unspecified() && (for_us || self.neighbors.contains(sender_ip)) {
+            let pending = self
+                .neighbors
+                .confirm(sender_ip, sender_mac, Instant::now());
+            for queued in pending {
+                self.send_resolved(queued, sender_mac, tx);
+            }
+        }
+
+        if !is_request || !for_us {
+            return None;
+        }
+        if !mbuf.is_writable() {
+            self.stats.not_writable += 1;
+            return None;
+        }
+        let mac = self.mac;
+        if let Ok(mut eth) = mbuf.ether_mut() {
+            eth.set_dst_addr(sender_mac);
+            eth.set_src_addr(mac);
+            if let Ok(mut arp) = ArpHdr::new(eth.into_payload_mut()) {
+                arp.make_reply(mac);
+            }
+        }
+        self.stats.replies_sent += 1;
+        self.push_tx(mbuf, tx);
+        None
+    }
+
+    /// 把数据包发往下一跳
+    ///
+    /// 数据包必须以以太网头部开头并已设置以太网类型。下一跳的 MAC 地址已知时填写
+    /// 以太网地址并放入 `tx`，否则排队并在需要时发送 ARP 请求，解析完成后由 `handle`
+    /// 放入发送批次。
+    pub fn send_to(&mut self, next_hop: Ipv4Addr, mbuf: Mbuf, tx: &mut MbufBatch) {
+        match self.neighbors.resolve(next_hop, mbuf, Instant::now()) {
+            Resolution::Resolved(mac, mbuf) => self.send_resolved(mbuf, mac, tx),
+            Resolution::Request => self.send_request(next_hop, tx),
+            Resolution::Queued => {}
+            Resolution::Dropped => self.stats.pending_dropped += 1,
+        }
+    }
+
+    /// 为所有本机地址发送免费 ARP
+    pub fn announce(&mut self, tx: &mut MbufBatch) -> Result<()> {
+        for i in 0..self.addrs.len() {
+            let mbuf = self.gratuitous(self.addrs[i])?;
+            self.stats.gratuitous_sent += 1;
+            self.push_tx(mbuf, tx);
+        }
+        Ok(())
+    }
+
+    /// 构造地址 `ip` 的免费 ARP 请求
+    pub fn gratuitous(&self, ip: Ipv4Addr) -> Result<Mbuf> {
+        self.build(constants::RTE_ARP_OP_REQUEST as u16, ip, ip)
+    }
+
+    /// 驱动邻居表的老化和请求重发，应在收发循环中定期调用
+    pub fn poll(&mut self, tx: &mut MbufBatch) {
+        let expired = self.neighbors.expire(Instant::now());
+        self.stats.pending_dropped += expired.dropped as u64;
+        for ip in expired.retransmit {
+            self.send_request(ip, tx);
+        }
+    }
+
+    fn send_request(&mut self, target_ip: Ipv4Addr, tx: &mut MbufBatch) {
+        let sender_ip = self.addrs.first().copied().unwrap_or(Ipv4Addr::UNSPECIFIED);
+        match self.build(constants::RTE_ARP_OP_REQUEST as u16, sender_ip, target_ip) {
+            Ok(mbuf) => {
+                self.stats.requests_sent += 1;
+                self.push_tx(mbuf, tx);
+            }
+            Err(_) => self.stats.alloc_failed += 1,
+        }
+    }
+
+    fn send_resolved(&mut self, mut mbuf: Mbuf, dst_mac: [u8; 6], tx: &mut MbufBatch) {
+        if !mbuf.is_writable() {
+            self.stats.not_writable += 1;
+            return;
+        }
+        match mbuf.ether_mut() {
+            Ok(mut eth) => {
+                eth.set_dst_addr(dst_mac);
+                eth.set_src_addr(self.mac);
+            }
+            Err(_) => {
+                self.stats.pending_dropped += 1;
+                return;
+            }
+        }
+        self.push_tx(mbuf, tx);
+    }
+
+    fn push_tx(&mut self, mbuf: Mbuf, tx: &mut MbufBatch) {
+        if tx.push(mbuf).is_err() {
+            self.stats.tx_dropped += 1;
+        }
+    }
+
+    /// 构造广播的 ARP 报文
+    fn build(&self, opcode: u16, sender_ip: Ipv4Addr, target_ip: Ipv4Addr) -> Result<Mbuf> {
+        let mut mbuf = self.pool.alloc()?;
+        let len = EtherHdr::<&[u8]>::LEN + ArpHdr::<&[u8]>::LEN;
+        let buf = mbuf.append(len)?;
+        buf.fill(0);
+        let mut eth = EtherHdr::new(buf)?;
+        eth.set_dst_addr(BROADCAST);
+        eth.set_src_addr(self.mac);
+        eth.set_ether_type(constants::RTE_ETHER_TYPE_ARP as u16);
+        let mut arp = ArpHdr::init(eth.into_payload_mut())?;
+        arp.set_opcode(opcode);
+        arp.set_sender_mac(self.mac);
+        arp.set_sender_ip(sender_ip);
+        arp.set_target_ip(target_ip);
+        Ok(mbuf)
+    }
+}
+
+fn is_arp(mbuf: &Mbuf) -> bool {
+    mbuf.ether()
+        .map(|eth| eth.ether_type() as u32 == constants::RTE_ETHER_TYPE_ARP)
+        .unwrap_or(false)
+}
@@ -0,0 +1,382 @@
+//! 邻居表，ARP 和 IPv6 邻居发现共用
+
+use crate::mbuf::Mbuf;
+use std::collections::hash_map::{Entry as MapEntry, HashMap};
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+/// 邻居表项的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborState {
+    /// 已解析出 MAC 地址
+    Reachable([u8; 6]),
+    /// 已发送请求，正在等待应答
+    Incomplete,
+}
+
+/// `NeighborTable::resolve` 的结果
+#[derive(Debug)]
+pub enum Resolution<P = Mbuf> {
+    /// MAC 地址已知，数据包原样返回
+    Resolved([u8; 6], P),
+    /// 数据包已排队，调用者需要发送 ARP 请求或邻居请求
+    Request,
+    /// 数据包已排队，请求已经发出
+    Queued,
+    /// 排队的数据包已满，数据包被释放
+    Dropped,
+}
+
+/// `NeighborTable::expire` 的结果
+#[derive(Debug)]
+pub struct Expired<A = Ipv4Addr> {
+    /// 需要重发请求的地址
+    pub retransmit: Vec<A>,
+    /// 因解析失败而释放的排队数据包数量
+    pub dropped: usize,
+}
+
+enum Entry<P> {
+    Reachable {
+        mac: [u8; 6],
+        confirmed: Instant,
+    },
+    Incomplete {
+        requested: Instant,
+        retries: u32,
+        pending: VecDeque<P>,
+    },
+}
+
+/// IP 地址到 MAC 地址的邻居表
+///
+/// 已解析的表项在 `reachable_time` 之后老化；未解析的表项缓存等待发送的数据包，
+/// 每隔 `retrans_time` 重发一次请求，超过 `max_retries` 次后删除表项并释放数据包。
+/// 所有方法都接受当前时间，老化和重发由调用者定期调用 `expire` 驱动。
+///
+/// 地址类型默认为 `Ipv4Addr`（ARP），IPv6 邻居发现使用 `NeighborTable<Ipv6Addr>`。
+/// 排队的数据包类型默认为 `Mbuf`。
+pub struct NeighborTable<A = Ipv4Addr, P = Mbuf> {
+    entries: HashMap<A, Entry<P>>,
+    reachable_time: Duration,
+    retrans_time: Duration,
+    max_retries: u32,
+    max_pending: usize,
+}
+
+impl<A: Copy + Eq + Hash, P> NeighborTable<A, P> {
+    pub fn new() -> Self {
+        NeighborTable {
+            entries: HashMap::new(),
+            reachable_time: Duration::from_secs(300),
+            retrans_time: Duration::from_secs(1),
+            max_retries: 3,
+            max_pending: 16,
+        }
+    }
+
+    /// 已解析表项的有效时间，默认为 300 秒
+    pub fn reachable_time(mut self, time: Duration) -> Self {
+        self.reachable_time = time;
+        self
+    }
+
+    /// 请求的重发间隔，默认为 1 秒
+    pub fn retrans_time(mut self, time: Duration) -> Self {
+        self.retrans_time = time;
+        self
+    }
+
+    /// 首次请求之后的最大重发次数，默认为 3
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// 每个未解析地址最多缓存的数据包数量，默认为 16
+    pub fn max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// 表项数量，包括未解析的表项
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 是否存在该地址的表项
+    pub fn contains(&self, ip: A) -> bool {
+        self.entries.contains_key(&ip)
+    }
+
+    /// 查找未老化的 MAC 地址
+    pub fn lookup(&self, ip: A, now: Instant) -> Option<[u8; 6]> {
+        match self.entries.get(&ip)? {
+            Entry::Reachable { mac, confirmed }
+                if now.saturating_duration_since(*confirmed) < self.reachable_time =>
+            {
+                Some(*mac)
+            }
+            _ => None,
+        }
+    }
+
+    /// 记录地址的 MAC，返回此前排队等待该地址的数据包
+    pub fn confirm(&mut self, ip: A, mac: [u8; 6], now: Instant) -> VecDeque<P> {
+        let old = self.entries.insert(
+            ip,
+            Entry::Reachable {
+                mac,
+                confirmed: now,
+            },
+        );
+        match old {
+            Some(Entry::Incomplete { pending, .. }) => pending,
+            _ => VecDeque::new(),
+        }
+    }
+
+    /// 查找下一跳的 MAC 地址，未知时把数据包排队
+    pub fn resolve(&mut self, ip: A, mbuf: P, now: Instant) -> Resolution<P> {
+        if let Some(mac) = self.lookup(ip, now) {
+            return Resolution::Resolved(mac, mbuf);
+        }
+        match self.entries.entry(ip) {
+            MapEntry::Occupied(mut e) => match e.get_mut() {
+                Entry::Incomplete { pending, .. } => {
+                    if pending.len() >= self.max_pending {
+                        return Resolution::Dropped;
+                    }
+                    pending.push_back(mbuf);
+                    Resolution::Queued
+                }
+                // 表项已老化，重新解析
+                Entry::Reachable { .. } => {
+                    e.insert(Entry::incomplete(mbuf, now));
+                    Resolution::Request
+                }
+            },
+            MapEntry::Vacant(e) => {
+                e.insert(Entry::incomplete(mbuf, now));
+                Resolution::Request
+            }
+        }
+    }
+
+    /// 删除表项，同时释放排队的数据包
+    pub fn remove(&mut self, ip: A) -> bool {
+        self.entries.remove(&ip).is_some()
+    }
+
+    /// 删除老化的表项，找出需要重发请求的地址，丢弃解析失败的数据包
+    pub fn expire(&mut self, now: Instant) -> Expired<A> {
+        let mut expired = Expired {
+            retransmit: Vec::new(),
+            dropped: 0,
+        };
+        let reachable_time = self.reachable_time;
+        let retrans_time = self.retrans_time;
+        let max_retries = self.max_retries;
+        self.entries.retain(|ip, entry| match entry {
+            Entry::Reachable { confirmed, .. } => {
+                now.saturating_duration_since(*confirmed) < reachable_time
+            }
+            Entry::Incomplete {
+                requested,
+                retries,
+                pending,
+            } => {
+                if now.saturating_duration_since(*requested) < retrans_time {
+                    return true;
+                }
+                if *retries >= max_retries {
+                    expired.dropped += pending.len();
+                    return false;
+                }
+                *retries += 1;
+                *requested = now;
+                expired.retransmit.push(*ip);
+                true
+            }
+        });
+        expired
+    }
+
+    /// 遍历所有表项
+    pub fn iter(&self) -> impl Iterator<Item = (A, NeighborState)> + '_ {
+        self.entries.iter().map(|(ip, entry)| {
+            let state = match entry {
+                Entry::Reachable { mac, .. } => NeighborState::Reachable(*mac),
+                Entry::Incomplete { .. } => NeighborState::Incomplete,
+            };
+            (*ip, state)
+        })
+    }
+}
+
+impl<A: Copy + Eq + Hash, P> Default for NeighborTable<A, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> Entry<P> {
+    fn incomplete(mbuf: P, now: Instant) -> Self {
+        Entry::Incomplete {
+            requested: now,
+            retries: 0,
+            pending: VecDeque::from([mbuf]),
+        }
+    }
+}
+
+impl<A, P> fmt::Debug for NeighborTable<A, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NeighborTable")
+            .field("len", &self.entries.len())
+            .field("reachable_time", &self.reachable_time)
+            .field("retrans_time", &self.retrans_time)
+            .field("max_retries", &self.max_retries)
+            .field("max_pending", &self.max_pending)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: u32 = 0x0a00_0001;
+    const MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 1];
+
+    fn table() -> NeighborTable<u32, u32> {
+        NeighborTable::new()
+            .reachable_time(Duration::from_secs(10))
+            .retrans_time(Duration::from_secs(1))
+            .max_retries(2)
+            .max_pending(2)
+    }
+
+    #[test]
+    fn resolve_queues_until_confirmed() {
+        let now = Instant::now();
+        let mut neigh = table();
+        assert!(matches!(neigh.resolve(IP, 1, now), Resolution::Request));
+        assert!(matches!(neigh.resolve(IP, 2, now), Resolution::Queued));
+        assert_eq!(neigh.lookup(IP, now), None);
+        assert_eq!(
+            neigh.iter().collect::<Vec<_>>(),
+            [(IP, NeighborState::Incomplete)]
+        );
+
+        assert_eq!(neigh.confirm(IP, MAC, now), [1, 2]);
+        assert_eq!(neigh.lookup(IP, now), Some(MAC));
+        assert!(matches!(
+            neigh.resolve(IP, 3, now),
+            Resolution::Resolved(MAC, 3)
+        ));
+        assert_eq!(
+            neigh.iter().collect::<Vec<_>>(),
+            [(IP, NeighborState::Reachable(MAC))]
+        );
+    }
+
+    #[test]
+    fn resolve_drops_beyond_max_pending() {
+        let now = Instant::now();
+        let mut neigh = table();
+        assert!(matches!(neigh.resolve(IP, 1, now), Resolution::Request));
+        assert!(matches!(neigh.resolve(IP, 2, now), Resolution::Queued));
+        assert!(matches!(neigh.resolve(IP, 3, now), Resolution::Dropped));
+        assert_eq!(neigh.confirm(IP, MAC, now), [1, 2]);
+    }
+
+    #[test]
+    fn confirm_without_request() {
+        let now = Instant::now();
+        let mut neigh = table();
+        assert!(neigh.confirm(IP, MAC, now).is_empty());
+        assert_eq!(neigh.lookup(IP, now), Some(MAC));
+        assert_eq!(neigh.lookup(IP + 1, now), None);
+    }
+
+    #[test]
+    fn reachable_entry_ages_out() {
+        let now = Instant::now();
+        let mut neigh = table();
+        neigh.confirm(IP, MAC, now);
+        assert_eq!(
+            neigh.lookup(IP, now + Duration::from_millis(9_999)),
+            Some(MAC)
+        );
+        assert_eq!(neigh.lookup(IP, now + Duration::from_secs(10)), None);
+
+        // 老化但尚未被 `expire` 删除的表项重新解析
+        let later = now + Duration::from_secs(10);
+        assert!(matches!(neigh.resolve(IP, 1, later), Resolution::Request));
+        assert_eq!(neigh.confirm(IP, MAC, later), [1]);
+
+        let expired = neigh.expire(later + Duration::from_secs(10));
+        assert!(expired.retransmit.is_empty());
+        assert_eq!(expired.dropped, 0);
+        assert!(neigh.is_empty());
+    }
+
+    #[test]
+    fn expire_retransmits_then_gives_up() {
+        let now = Instant::now();
+        let mut neigh = table();
+        neigh.resolve(IP, 1, now);
+        neigh.resolve(IP, 2, now);
+
+        let expired = neigh.expire(now + Duration::from_millis(500));
+        assert!(expired.retransmit.is_empty());
+
+        let expired = neigh.expire(now + Duration::from_secs(1));
+        assert_eq!(expired.retransmit, [IP]);
+        // 重发间隔从上一次请求开始计算
+        let expired = neigh.expire(now + Duration::from_millis(1_500));
+        assert!(expired.retransmit.is_empty());
+        let expired = neigh.expire(now + Duration::from_secs(2));
+        assert_eq!(expired.retransmit, [IP]);
+        assert!(neigh.contains(IP));
+
+        let expired = neigh.expire(now + Duration::from_secs(3));
+        assert!(expired.retransmit.is_empty());
+        assert_eq!(expired.dropped, 2);
+        assert!(!neigh.contains(IP));
+    }
+
+    #[test]
+    fn confirm_during_retransmit() {
+        let now = Instant::now();
+        let mut neigh = table();
+        neigh.resolve(IP, 1, now);
+        let expired = neigh.expire(now + Duration::from_secs(1));
+        assert_eq!(expired.retransmit, [IP]);
+
+        assert_eq!(neigh.confirm(IP, MAC, now + Duration::from_secs(2)), [1]);
+        let expired = neigh.expire(now + Duration::from_secs(5));
+        assert!(expired.retransmit.is_empty());
+        assert_eq!(expired.dropped, 0);
+        assert_eq!(neigh.lookup(IP, now + Duration::from_secs(5)), Some(MAC));
+    }
+
+    #[test]
+    fn remove_entry() {
+        let now = Instant::now();
+        let mut neigh = table();
+        neigh.resolve(IP, 1, now);
+        assert_eq!(neigh.len(), 1);
+        assert!(neigh.remove(IP));
+        assert!(!neigh.remove(IP));
+        assert!(neigh.is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+//! EAL (Environment Abstraction Layer) 初始化
+//!
+//! `EalBuilder` 用类型化的参数生成 `rte_eal_init` 所需的命令行，
+//! 初始化成功后返回 `Eal` 守卫对象，在其被丢弃时自动调用 `rte_eal_cleanup`。
+
+use crate::error::{check_errno, DpdkError, Result};
+use crate::lcore::{self, LcoreJoinHandle, Lcores};
+use crate::*;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 进程内是否已经尝试过初始化 EAL。
+///
+/// DPDK 不支持在同一进程中重复初始化 EAL（即使之前已调用 `rte_eal_cleanup`），
+/// 因此该标志一旦置位就不会被清除。
+static EAL_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// IOVA 地址模式（`--iova-mode`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IovaMode {
+    /// 物理地址模式
+    Pa,
+    /// 虚拟地址模式
+    Va,
+}
+
+impl IovaMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            IovaMode::Pa => "pa",
+            IovaMode::Va => "va",
+        }
+    }
+}
+
+/// EAL 全局日志级别（`--log-level`），与 `RTE_LOG_*` 的取值一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Emergency = 1,
+    Alert = 2,
+    Critical = 3,
+    Error = 4,
+    Warning = 5,
+    Notice = 6,
+    Info = 7,
+    Debug = 8,
+}
+
+/// EAL 参数构建器
+///
+/// 每个类型化的设置方法对应一个 EAL 命令行选项，未覆盖的选项可以通过 `arg` 追加。
+#[derive(Debug, Clone)]
+pub struct EalBuilder {
+    program: String,
+    lcores: Option<Vec<u32>>,
+    main_lcore: Option<u32>,
+    memory_channels: Option<u32>,
+    socket_mem: Option<Vec<u32>>,
+    huge_dir: Option<String>,
+    no_huge: bool,
+    no_pci: bool,
+    vdevs: Vec<String>,
+    allow: Vec<String>,
+    block: Vec<String>,
+    file_prefix: Option<String>,
+    iova_mode: Option<IovaMode>,
+    log_level: Option<LogLevel>,
+    extra_args: Vec<String>,
+    app_args: Vec<String>,
+}
+
+impl EalBuilder {
+    /// 创建构建器，`program` 作为 `argv[0]`
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            lcores: None,
+            main_lcore: None,
+            memory_channels: None,
+            socket_mem: None,
+            huge_dir: None,
+            no_huge: false,
+            no_pci: false,
+            vdevs: Vec::new(),
+            allow: Vec::new(),
+            block: Vec::new(),
+            file_prefix: None,
+            iova_mode: None,
+            log_level: None,
+            extra_args: Vec::new(),
+            app_args: Vec::new(),
+        }
+    }
+
+    /// 设置使用的 lcore 列表（`-l`）
+    pub fn lcores(mut self, lcores: impl IntoIterator<Item = u32>) -> Self {
+        self.lcores = Some(lcores.into_iter().collect());
+        self
+    }
+
+    /// 设置主 lcore（`--main-lcore`）
+    pub fn main_lcore(mut self, lcore: u32) -> Self {
+        self.main_lcore = Some(lcore);
+        self
+    }
+
+    /// 设置内存通道数（`-n`）
+    pub fn memory_channels(mut self, channels: u32) -> Self {
+        self.memory_channels = Some(channels);
+        self
+    }
+
+    /// 设置每个 NUMA socket 预分配的内存，单位 MB（`--socket-mem`）
+    pub fn socket_mem(mut self, megabytes: impl IntoIterator<Item = u32>) -> Self {
+        self.socket_mem = Some(megabytes.into_iter().collect());
+        self
+    }
+
+    /// 设置大页内存挂载目录（`--huge-dir`）
+    pub fn huge_dir(mut self, dir: impl Into<String>) -> Self {
+        self.huge_dir = Some(dir.into());
+        self
+    }
+
+    /// 不使用大页内存（`--no-huge`）
+    pub fn no_huge(mut self, enable: bool) -> Self {
+        self.no_huge = enable;
+        self
+    }
+
+    /// 不扫描 PCI 设备（`--no-pci`）
+    pub fn no_pci(mut self, enable: bool) -> Self {
+        self.no_pci = enable;
+        self
+    }
+
+    /// 添加虚拟设备（`--vdev`），可多次调用
+    pub fn vdev(mut self, vdev: impl Into<String>) -> Self {
+        self.vdevs.push(vdev.into());
+        self
+    }
+
+    /// 仅使用指定的 PCI 设备（`-a`），可多次调用
+    pub fn allow(mut self, pci: impl Into<String>) -> Self {
+        self.allow.push(pci.into());
+        self
+    }
+
+    /// 忽略指定的 PCI 设备（`-b`），可多次调用
+    pub fn block(mut self, pci: impl Into<String>) -> Self {
+        self.block.push(pci.into());
+        self
+    }
+
+    /// 设置运行时文件前缀（`--file-prefix`），用于同一主机运行多个 DPDK 进程
+    pub fn file_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.file_prefix = Some(prefix.into());
+        self
+    }
+
+    /// 设置 IOVA 地址模式（`--iova-mode`）
+    pub fn iova_mode(mut self, mode: IovaMode) -> Self {
+        self.iova_mode = Some(mode);
+        self
+    }
+
+    /// 设置全局日志级别（`--log-level`）
+    pub fn log_level(mut self, level: LogLevel) -> Self {
+        self.log_level = Some(level);
+        self
+    }
+
+    /// 追加一个未被类型化的 EAL 参数
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.extra_args.push(arg.into());
+        self
+    }
+
+    /// 追加应用参数，这些参数位于 `--` 之后，初始化后由 `Eal::args` 返回
+    pub fn app_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.app_args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// 生成完整的命令行参数（包含 `argv[0]`）
+    pub fn to_args(&self) -> Vec<String> {
+        fn join(values: &[u32]) -> String {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        }
+
+        let mut args = vec![self.program.clone()];
+        if let Some(lcores) = &self.lcores {
+            args.push("-l".into());
+            args.push(join(lcores));
+        }
+        if let Some(lcore) = self.main_lcore {
+            args.push(format!("--main-lcore={}", lcore));
+        }
+        if let Some(channels) = self.memory_channels {
+            args.push("-n".into());
+            args.push(channels.to_string());
+        }
+        if let Some(socket_mem) = &self.socket_mem {
+            args.push(format!("--socket-mem={}", join(socket_mem)));
+        }
+        if let Some(dir) = &self.huge_dir {
+            args.push(format!("--huge-dir={}", dir));
+        }
+        if self.no_huge {
+            args.push("--no-huge".into());
+        }
+        if self.no_pci {
+            args.push("--no-pci".into());
+        }
+        for vdev in &self.vdevs {
+            args.push(format!("--vdev={}", vdev));
+        }
+        for pci in &self.allow {
+            args.push("-a".into());
+            args.push(pci.clone());
+        }
+        for pci in &self.block {
+            args.push("-b".into());
+            args.push(pci.clone());
+        }
+        if let Some(prefix) = &self.file_prefix {
+            args.push(format!("--file-prefix={}", prefix));
+        }
+        if let Some(mode) = self.iova_mode {
+            args.push(format!("--iova-mode={}", mode.as_str()));
+        }
+        if let Some(level) = self.log_level {
+            args.push(format!("--log-level={}", level as u32));
+        }
+        args.extend(self.extra_args.iter().cloned());
+        if !self.app_args.is_empty() {
+            args.push("--".into());
+            args.extend(self.app_args.iter().cloned());
+        }
+        args
+    }
+
+    /// 调用 `rte_eal_init` 初始化 EAL
+    ///
+    /// 同一进程中只能成功调用一次，之后的调用返回 `DpdkError::AlreadyInitialized`。
+    pub fn init(self) -> Result<Eal> {
+        let c_args = self
+            .to_args()
+            .into_iter()
+            .map(CString::new)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if EAL_INITIALIZED
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(DpdkError::AlreadyInitialized);
+        }
+
+        // `rte_eal_init` 可能会重排 argv，因此传入独立的指针数组，
+        // 并在初始化之后通过该数组读取剩余的应用参数。
+        let mut argv: Vec<*mut c_char> = c_args
+            .iter()
+            .map(|arg| arg.as_ptr() as *mut c_char)
+            .chain(std::iter::once(std::ptr::null_mut()))
+            .collect();
+        let argc = c_args.len();
+
+        let ret = check_errno("rte_eal_init", unsafe {
+            rte_eal_init(argc as c_int, argv.as_mut_ptr())
+        })?;
+
+        // argv[ret] 被 EAL 改写为程序名，之后才是应用参数。
+        let parsed = ret as usize;
+        let args = argv[(parsed + 1).min(argc)..argc]
+            .iter()
+            .map(|&arg| {
+                unsafe { CStr::from_ptr(arg) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+
+        Ok(Eal {
+            args,
+            _c_args: c_args,
+            _not_send: PhantomData,
+        })
+    }
+}
+
+/// 已初始化的 EAL
+///
+/// 被丢弃时调用 `rte_eal_cleanup`。在此之前应先释放所有端口、内存池等 DPDK 资源。
+/// 该类型既不是 `Send` 也不是 `Sync`，只能在执行初始化的主 lcore 上使用。
+pub struct Eal {
+    args: Vec<String>,
+    /// EAL 可能保留 argv 中的指针，因此在清理前保持这些字符串有效。
+    _c_args: Vec<CString>,
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Eal {
+    /// 创建 EAL 参数构建器
+    pub fn builder(program: impl Into<String>) -> EalBuilder {
+        EalBuilder::new(program)
+    }
+
+    /// EAL 未解析的剩余应用参数（不包含程序名）
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// 查询已启用的 lcore
+    pub fn lcores(&self) -> Lcores {
+        Lcores::new()
+    }
+
+    /// 在指定的 worker lcore 上执行闭包
+    ///
+    /// 闭包中的 panic 会被捕获，并在 `LcoreJoinHandle::join` 时以 `Err` 返回。
+    pub fn launch_on<F, T>(&self, lcore_id: u32, f: F) -> Result<LcoreJoinHandle<'_, T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        lcore::launch(self, lcore_id, f)
+    }
+
+    /// 在所有 worker lcore 上执行闭包，闭包的参数为所在的 lcore ID
+    pub fn launch_on_all_workers<F, T>(&self, f: F) -> Result<Vec<LcoreJoinHandle<'_, T>>>
+    where
+        F: Fn(u32) -> T + Send + Clone + 'static,
+        T: Send + 'static,
+    {
+        self.lcores()
+            .workers()
+            .map(|lcore_id| {
+                let f = f.clone();
+                self.launch_on(lcore_id, move || f(lcore_id))
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Eal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Eal").field("args", &self.args).finish()
+    }
+}
+
+impl Drop for Eal {
+    fn drop(&mut self) {
+        unsafe {
+            rte_eal_cleanup();
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! 统一的 DPDK 错误类型
+//!
+//! DPDK 的 C API 使用两种错误约定：
+//!
+//! 1. ethdev 等接口直接返回负的 errno（例如 `-EINVAL`）；
+//! 2. `rte_pktmbuf_pool_create`、`rte_mempool_create` 等接口返回 NULL（或 `-1`），
+//!    并通过线程局部的 `rte_errno` 给出原因。
+//!
+//! `check_ret`、`check_errno` 和 `check_ptr` 分别把这两种约定转换为 `Result`，
+//! 以便安全包装器统一使用 `?`。
+
+use crate::*;
+use std::ffi::{CStr, NulError};
+use std::fmt;
+use std::os::raw::c_int;
+use std::ptr::NonNull;
+
+/// 本库使用的 `Result` 类型
+pub type Result<T> = std::result::Result<T, DpdkError>;
+
+/// DPDK 操作错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DpdkError {
+    /// 当前进程已经初始化过 EAL
+    AlreadyInitialized,
+    /// 在调用 DPDK 之前由 Rust 侧检查出的无效参数
+    InvalidArgument(String),
+    /// DPDK 函数调用失败
+    Call {
+        /// 失败的 DPDK 函数名
+        op: &'static str,
+        /// 正的 errno 值
+        errno: i32,
+        /// 相关的端口 ID
+        port: Option<u16>,
+        /// 相关的队列 ID
+        queue: Option<u16>,
+    },
+}
+
+impl DpdkError {
+    /// 由函数名和 errno 构造错误，errno 的符号会被忽略
+    pub fn new(op: &'static str, errno: i32) -> Self {
+        DpdkError::Call {
+            op,
+            errno: errno.abs(),
+            port: None,
+            queue: None,
+        }
+    }
+
+    /// 由函数名和当前线程的 `rte_errno` 构造错误
+    pub fn last(op: &'static str) -> Self {
+        Self::new(op, unsafe { rte_errno() })
+    }
+
+    /// 构造无效参数错误
+    pub fn invalid(reason: impl Into<String>) -> Self {
+        DpdkError::InvalidArgument(reason.into())
+    }
+
+    /// 附加端口 ID
+    pub fn with_port(mut self, port_id: u16) -> Self {
+        if let DpdkError::Call { port, .. } = &mut self {
+            *port = Some(port_id);
+        }
+        self
+    }
+
+    /// 附加队列 ID
+    pub fn with_queue(mut self, queue_id: u16) -> Self {
+        if let DpdkError::Call { queue, .. } = &mut self {
+            *queue = Some(queue_id);
+        }
+        self
+    }
+
+    /// 失败的 DPDK 函数名
+    pub fn op(&self) -> Option<&'static str> {
+        match self {
+            DpdkError::Call { op, .. } => Some(op),
+            _ => None,
+        }
+    }
+
+    /// 错误对应的 errno（正值）
+    pub fn errno(&self) -> Option<i32> {
+        match self {
+            DpdkError::Call { errno, .. } => Some(*errno),
+            _ => None,
+        }
+    }
+
+    /// 由 `rte_strerror` 给出的错误描述
+    pub fn message(&self) -> Option<String> {
+        self.errno().map(strerror)
+    }
+}
+
+impl fmt::Display for DpdkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DpdkError::AlreadyInitialized => write!(f, "EAL 已经在当前进程中初始化过"),
+            DpdkError::InvalidArgument(reason) => write!(f, "无效参数: {}", reason),
+            DpdkError::Call {
+                op,
+                errno,
+                port,
+                queue,
+            } => {
+                write!(f, "{} 失败", op)?;
+                match (port, queue) {
+                    (Some(port), Some(queue)) => write!(f, " (端口 {}, 队列 {})", port, queue)?,
+                    (Some(port), None) => write!(f, " (端口 {})", port)?,
+                    (None, Some(queue)) => write!(f, " (队列 {})", queue)?,
+                    (None, None) => {}
+                }
+                write!(f, ": {} (errno {})", strerror(*errno), errno)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DpdkError {}
+
+impl From<NulError> for DpdkError {
+    fn from(e: NulError) -> Self {
+        DpdkError::InvalidArgument(format!("字符串中包含 NUL 字符: {}", e))
+    }
+}
+
+/// 调用 `rte_strerror` 获取 errno 的描述
+pub fn strerror(errno: i32) -> String {
+    unsafe { CStr::from_ptr(rte_strerror(errno)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// 检查返回负 errno 的调用（ethdev 约定），成功时返回原值
+pub fn check_ret(op: &'static str, ret: c_int) -> Result<c_int> {
+    if ret < 0 {
+        Err(DpdkError::new(op, ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// 检查返回 `-1` 并设置 `rte_errno` 的调用，成功时返回原值
+pub fn check_errno(op: &'static str, ret: c_int) -> Result<c_int> {
+    if ret < 0 {
+        Err(DpdkError::last(op))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// 检查返回 NULL 并设置 `rte_errno` 的调用
+pub fn check_ptr<T>(op: &'static str, ptr: *mut T) -> Result<NonNull<T>> {
+    NonNull::new(ptr).ok_or_else(|| DpdkError::last(op))
+}
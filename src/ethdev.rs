@@ -0,0 +1,24 @@
+//! 以太网设备
+//!
+//! `Port::configure` 返回的构建器负责配置、设置队列并启动端口，
+//! 启动后得到端口句柄以及每个收发队列的句柄。
+//!
+//! 每个收发队列在同一时刻只能被一个线程访问，这是 DPDK 对 `rte_eth_rx_burst`/
+//! `rte_eth_tx_burst` 的要求。`RxQueue`/`TxQueue` 句柄实现了 `Send` 而没有实现 `Sync`，
+//! 可以移动到 worker lcore 上使用，但不能在多个线程之间共享。
+
+mod event;
+mod info;
+mod link;
+mod port;
+mod queue;
+mod rss;
+mod stats;
+
+pub use event::{EventCallback, PortEvent};
+pub use info::{DescLimits, LinkSpeeds, PortInfo, RssHash, RxOffload, TxOffload};
+pub use link::{LinkDuplex, LinkStatus};
+pub use port::{Port, PortBuilder};
+pub use queue::{RxQueue, TxQueue};
+pub use rss::{RssConf, RssTuple};
+pub use stats::{PortRate, PortStats, QueueStats, Xstat};
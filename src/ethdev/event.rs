@@ -0,0 +1,232 @@
+//! 以太网设备事件回调
+//!
+//! 回调在 EAL 的中断线程中执行，应尽快返回；需要在其他线程处理的事件可以通过
+//! `Port::event_channel` 转发到 channel 中。
+
+use super::port::{Port, PortGuard};
+use crate::error::{check_ret, Result};
+use crate::*;
+use std::fmt;
+use std::os::raw::{c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+/// 以太网设备事件（`rte_eth_event_type`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortEvent {
+    /// 链路状态变化（`RTE_ETH_EVENT_INTR_LSC`）
+    LinkStateChange,
+    /// 队列状态变化
+    QueueState,
+    /// 设备需要复位
+    Reset,
+    /// 收到 VF 发来的 mailbox 消息
+    VfMbox,
+    /// MACsec 事件
+    Macsec,
+    /// 设备被移除
+    Removed,
+    /// 探测到新端口
+    New,
+    /// 端口被释放
+    Destroy,
+    /// IPsec 事件
+    Ipsec,
+    /// 有流规则老化
+    FlowAged,
+    /// 接收队列占用超过阈值
+    RxAvailThresh,
+    /// 本库未识别的事件
+    Unknown(rte_eth_event_type),
+}
+
+impl PortEvent {
+    fn from_raw(event: rte_eth_event_type) -> Self {
+        if event == rte_eth_event_type_RTE_ETH_EVENT_INTR_LSC {
+            PortEvent::LinkStateChange
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_QUEUE_STATE {
+            PortEvent::QueueState
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_INTR_RESET {
+            PortEvent::Reset
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_VF_MBOX {
+            PortEvent::VfMbox
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_MACSEC {
+            PortEvent::Macsec
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_INTR_RMV {
+            PortEvent::Removed
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_NEW {
+            PortEvent::New
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_DESTROY {
+            PortEvent::Destroy
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_IPSEC {
+            PortEvent::Ipsec
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_FLOW_AGED {
+            PortEvent::FlowAged
+        } else if event == rte_eth_event_type_RTE_ETH_EVENT_RX_AVAIL_THRESH {
+            PortEvent::RxAvailThresh
+        } else {
+            PortEvent::Unknown(event)
+        }
+    }
+
+    fn to_raw(self) -> rte_eth_event_type {
+        match self {
+            PortEvent::LinkStateChange => rte_eth_event_type_RTE_ETH_EVENT_INTR_LSC,
+            PortEvent::QueueState => rte_eth_event_type_RTE_ETH_EVENT_QUEUE_STATE,
+            PortEvent::Reset => rte_eth_event_type_RTE_ETH_EVENT_INTR_RESET,
+            PortEvent::VfMbox => rte_eth_event_type_RTE_ETH_EVENT_VF_MBOX,
+            PortEvent::Macsec => rte_eth_event_type_RTE_ETH_EVENT_MACSEC,
+            PortEvent::Removed => rte_eth_event_type_RTE_ETH_EVENT_INTR_RMV,
+            PortEvent::New => rte_eth_event_type_RTE_ETH_EVENT_NEW,
+            PortEvent::Destroy => rte_eth_event_type_RTE_ETH_EVENT_DESTROY,
+            PortEvent::Ipsec => rte_eth_event_type_RTE_ETH_EVENT_IPSEC,
+            PortEvent::FlowAged => rte_eth_event_type_RTE_ETH_EVENT_FLOW_AGED,
+            PortEvent::RxAvailThresh => rte_eth_event_type_RTE_ETH_EVENT_RX_AVAIL_THRESH,
+            PortEvent::Unknown(event) => event,
+        }
+    }
+}
+
+/// 已注册的事件回调，被丢弃时注销（`rte_eth_dev_callback_unregister`）
+///
+/// 为某个端口注册的回调持有端口的所有权，回调存在期间端口不会被关闭。
+///
+/// 注销时如果回调正在执行，会等待它返回，因此不能在回调内部丢弃自身。
+pub struct EventCallback {
+    port_id: u16,
+    event: rte_eth_event_type,
+    cb_fn: rte_eth_dev_cb_fn,
+    arg: *mut c_void,
+    free: unsafe fn(*mut c_void),
+    _port: Option<Arc<PortGuard>>,
+}
+
+// `arg` 指向的闭包是 `Send` 的，只在回调和 `Drop` 中访问。
+unsafe impl Send for EventCallback {}
+
+impl EventCallback {
+    /// 为所有端口（包括之后探测到的端口）注册回调，适用于 `New` 等事件
+    ///
+    /// 回调的参数为产生事件的端口 ID 和事件。
+    pub fn register_all<F>(event: PortEvent, f: F) -> Result<Self>
+    where
+        F: FnMut(u16, PortEvent) + Send + 'static,
+    {
+        Self::register(constants::RTE_ETH_ALL as u16, None, event, f)
+    }
+
+    fn register<F>(
+        port_id: u16,
+        port: Option<Arc<PortGuard>>,
+        event: PortEvent,
+        f: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(u16, PortEvent) + Send + 'static,
+    {
+        let event = event.to_raw();
+        let cb_fn: rte_eth_dev_cb_fn = Some(event_trampoline::<F>);
+        let arg = Box::into_raw(Box::new(Mutex::new(f))) as *mut c_void;
+        let ret = unsafe { rte_eth_dev_callback_register(port_id, event, cb_fn, arg) };
+        if let Err(e) = check_ret("rte_eth_dev_callback_register", ret) {
+            unsafe { free_handler::<F>(arg) };
+            return Err(e.with_port(port_id));
+        }
+        Ok(EventCallback {
+            port_id,
+            event,
+            cb_fn,
+            arg,
+            free: free_handler::<F>,
+            _port: port,
+        })
+    }
+
+    /// 回调对应的事件
+    pub fn event(&self) -> PortEvent {
+        PortEvent::from_raw(self.event)
+    }
+
+    /// 回调对应的端口，为所有端口注册时返回 `None`
+    pub fn port_id(&self) -> Option<u16> {
+        if self.port_id == constants::RTE_ETH_ALL as u16 {
+            None
+        } else {
+            Some(self.port_id)
+        }
+    }
+}
+
+impl Drop for EventCallback {
+    fn drop(&mut self) {
+        let ret = loop {
+            let ret = unsafe {
+                rte_eth_dev_callback_unregister(self.port_id, self.event, self.cb_fn, self.arg)
+            };
+            // 回调正在执行时返回 -EAGAIN。
+            if ret != -libc::EAGAIN {
+                break ret;
+            }
+            thread::yield_now();
+        };
+        // 注销失败时 DPDK 可能仍持有 `arg`，宁可泄漏也不释放。
+        if ret == 0 {
+            unsafe { (self.free)(self.arg) };
+        }
+    }
+}
+
+impl fmt::Debug for EventCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventCallback")
+            .field("port_id", &self.port_id())
+            .field("event", &self.event())
+            .finish()
+    }
+}
+
+/// `rte_eth_dev_callback_register` 的入口函数
+///
+/// 闭包中的 panic 在此处被捕获，不会跨越 FFI 边界；之后的事件照常调用闭包。
+unsafe extern "C" fn event_trampoline<F>(
+    port_id: u16,
+    event: rte_eth_event_type,
+    cb_arg: *mut c_void,
+    _ret_param: *mut c_void,
+) -> c_int
+where
+    F: FnMut(u16, PortEvent) + Send + 'static,
+{
+    let handler = &*(cb_arg as *const Mutex<F>);
+    // 锁在 `catch_unwind` 之外获取，闭包 panic 时锁不会中毒；即使中毒，闭包本身仍然可用。
+    let mut f = handler.lock().unwrap_or_else(PoisonError::into_inner);
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        f(port_id, PortEvent::from_raw(event));
+    }));
+    0
+}
+
+unsafe fn free_handler<F>(arg: *mut c_void) {
+    drop(Box::from_raw(arg as *mut Mutex<F>));
+}
+
+impl Port {
+    /// 注册本端口的事件回调，回调的参数为端口 ID 和事件
+    pub fn on_event<F>(&self, event: PortEvent, f: F) -> Result<EventCallback>
+    where
+        F: FnMut(u16, PortEvent) + Send + 'static,
+    {
+        EventCallback::register(self.port_id(), Some(self.guard.clone()), event, f)
+    }
+
+    /// 把本端口的事件转发到 channel 中
+    pub fn event_channel(&self, event: PortEvent) -> Result<(EventCallback, Receiver<PortEvent>)> {
+        let (tx, rx) = mpsc::channel();
+        let callback = self.on_event(event, move |_, event| {
+            let _ = tx.send(event);
+        })?;
+        Ok((callback, rx))
+    }
+}
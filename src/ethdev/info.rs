@@ -0,0 +1,269 @@
+//! 端口设备信息与能力查询
+
+use super::port::dev_info;
+use crate::error::Result;
+use crate::*;
+use bitflags::bitflags;
+use std::ffi::CStr;
+use std::ops::RangeInclusive;
+
+bitflags! {
+    /// 接收 offload 能力（`RTE_ETH_RX_OFFLOAD_*`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct RxOffload: u64 {
+        const VLAN_STRIP = constants::RTE_ETH_RX_OFFLOAD_VLAN_STRIP;
+        const IPV4_CKSUM = constants::RTE_ETH_RX_OFFLOAD_IPV4_CKSUM;
+        const UDP_CKSUM = constants::RTE_ETH_RX_OFFLOAD_UDP_CKSUM;
+        const TCP_CKSUM = constants::RTE_ETH_RX_OFFLOAD_TCP_CKSUM;
+        const TCP_LRO = constants::RTE_ETH_RX_OFFLOAD_TCP_LRO;
+        const QINQ_STRIP = constants::RTE_ETH_RX_OFFLOAD_QINQ_STRIP;
+        const OUTER_IPV4_CKSUM = constants::RTE_ETH_RX_OFFLOAD_OUTER_IPV4_CKSUM;
+        const MACSEC_STRIP = constants::RTE_ETH_RX_OFFLOAD_MACSEC_STRIP;
+        const VLAN_FILTER = constants::RTE_ETH_RX_OFFLOAD_VLAN_FILTER;
+        const VLAN_EXTEND = constants::RTE_ETH_RX_OFFLOAD_VLAN_EXTEND;
+        const SCATTER = constants::RTE_ETH_RX_OFFLOAD_SCATTER;
+        const TIMESTAMP = constants::RTE_ETH_RX_OFFLOAD_TIMESTAMP;
+        const SECURITY = constants::RTE_ETH_RX_OFFLOAD_SECURITY;
+        const KEEP_CRC = constants::RTE_ETH_RX_OFFLOAD_KEEP_CRC;
+        const SCTP_CKSUM = constants::RTE_ETH_RX_OFFLOAD_SCTP_CKSUM;
+        const OUTER_UDP_CKSUM = constants::RTE_ETH_RX_OFFLOAD_OUTER_UDP_CKSUM;
+        const RSS_HASH = constants::RTE_ETH_RX_OFFLOAD_RSS_HASH;
+        const BUFFER_SPLIT = constants::RTE_ETH_RX_OFFLOAD_BUFFER_SPLIT;
+
+        /// IPv4、UDP 和 TCP 校验和
+        const CHECKSUM = constants::RTE_ETH_RX_OFFLOAD_CHECKSUM;
+        /// VLAN 剥离、过滤和扩展
+        const VLAN = constants::RTE_ETH_RX_OFFLOAD_VLAN;
+    }
+}
+
+bitflags! {
+    /// 发送 offload 能力（`RTE_ETH_TX_OFFLOAD_*`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct TxOffload: u64 {
+        const VLAN_INSERT = constants::RTE_ETH_TX_OFFLOAD_VLAN_INSERT;
+        const IPV4_CKSUM = constants::RTE_ETH_TX_OFFLOAD_IPV4_CKSUM;
+        const UDP_CKSUM = constants::RTE_ETH_TX_OFFLOAD_UDP_CKSUM;
+        const TCP_CKSUM = constants::RTE_ETH_TX_OFFLOAD_TCP_CKSUM;
+        const SCTP_CKSUM = constants::RTE_ETH_TX_OFFLOAD_SCTP_CKSUM;
+        const TCP_TSO = constants::RTE_ETH_TX_OFFLOAD_TCP_TSO;
+        const UDP_TSO = constants::RTE_ETH_TX_OFFLOAD_UDP_TSO;
+        const OUTER_IPV4_CKSUM = constants::RTE_ETH_TX_OFFLOAD_OUTER_IPV4_CKSUM;
+        const QINQ_INSERT = constants::RTE_ETH_TX_OFFLOAD_QINQ_INSERT;
+        const VXLAN_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_VXLAN_TNL_TSO;
+        const GRE_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_GRE_TNL_TSO;
+        const IPIP_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_IPIP_TNL_TSO;
+        const GENEVE_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_GENEVE_TNL_TSO;
+        const MACSEC_INSERT = constants::RTE_ETH_TX_OFFLOAD_MACSEC_INSERT;
+        const MT_LOCKFREE = constants::RTE_ETH_TX_OFFLOAD_MT_LOCKFREE;
+        const MULTI_SEGS = constants::RTE_ETH_TX_OFFLOAD_MULTI_SEGS;
+        const MBUF_FAST_FREE = constants::RTE_ETH_TX_OFFLOAD_MBUF_FAST_FREE;
+        const SECURITY = constants::RTE_ETH_TX_OFFLOAD_SECURITY;
+        const UDP_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_UDP_TNL_TSO;
+        const IP_TNL_TSO = constants::RTE_ETH_TX_OFFLOAD_IP_TNL_TSO;
+        const OUTER_UDP_CKSUM = constants::RTE_ETH_TX_OFFLOAD_OUTER_UDP_CKSUM;
+        const SEND_ON_TIMESTAMP = constants::RTE_ETH_TX_OFFLOAD_SEND_ON_TIMESTAMP;
+    }
+}
+
+bitflags! {
+    /// RSS 哈希计算所用的报文类型和字段（`RTE_ETH_RSS_*`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct RssHash: u64 {
+        const IPV4 = constants::RTE_ETH_RSS_IPV4;
+        const FRAG_IPV4 = constants::RTE_ETH_RSS_FRAG_IPV4;
+        const NONFRAG_IPV4_TCP = constants::RTE_ETH_RSS_NONFRAG_IPV4_TCP;
+        const NONFRAG_IPV4_UDP = constants::RTE_ETH_RSS_NONFRAG_IPV4_UDP;
+        const NONFRAG_IPV4_SCTP = constants::RTE_ETH_RSS_NONFRAG_IPV4_SCTP;
+        const NONFRAG_IPV4_OTHER = constants::RTE_ETH_RSS_NONFRAG_IPV4_OTHER;
+        const IPV6 = constants::RTE_ETH_RSS_IPV6;
+        const FRAG_IPV6 = constants::RTE_ETH_RSS_FRAG_IPV6;
+        const NONFRAG_IPV6_TCP = constants::RTE_ETH_RSS_NONFRAG_IPV6_TCP;
+        const NONFRAG_IPV6_UDP = constants::RTE_ETH_RSS_NONFRAG_IPV6_UDP;
+        const NONFRAG_IPV6_SCTP = constants::RTE_ETH_RSS_NONFRAG_IPV6_SCTP;
+        const NONFRAG_IPV6_OTHER = constants::RTE_ETH_RSS_NONFRAG_IPV6_OTHER;
+        const L2_PAYLOAD = constants::RTE_ETH_RSS_L2_PAYLOAD;
+        const IPV6_EX = constants::RTE_ETH_RSS_IPV6_EX;
+        const IPV6_TCP_EX = constants::RTE_ETH_RSS_IPV6_TCP_EX;
+        const IPV6_UDP_EX = constants::RTE_ETH_RSS_IPV6_UDP_EX;
+        const PORT = constants::RTE_ETH_RSS_PORT;
+        const VXLAN = constants::RTE_ETH_RSS_VXLAN;
+        const GENEVE = constants::RTE_ETH_RSS_GENEVE;
+        const NVGRE = constants::RTE_ETH_RSS_NVGRE;
+        const GTPU = constants::RTE_ETH_RSS_GTPU;
+        const ETH = constants::RTE_ETH_RSS_ETH;
+        const S_VLAN = constants::RTE_ETH_RSS_S_VLAN;
+        const C_VLAN = constants::RTE_ETH_RSS_C_VLAN;
+        const ESP = constants::RTE_ETH_RSS_ESP;
+        const AH = constants::RTE_ETH_RSS_AH;
+        const L2TPV3 = constants::RTE_ETH_RSS_L2TPV3;
+        const PFCP = constants::RTE_ETH_RSS_PFCP;
+        const PPPOE = constants::RTE_ETH_RSS_PPPOE;
+        const ECPRI = constants::RTE_ETH_RSS_ECPRI;
+        const MPLS = constants::RTE_ETH_RSS_MPLS;
+        const IPV4_CHKSUM = constants::RTE_ETH_RSS_IPV4_CHKSUM;
+        const L4_CHKSUM = constants::RTE_ETH_RSS_L4_CHKSUM;
+        const L2TPV2 = constants::RTE_ETH_RSS_L2TPV2;
+        const L3_SRC_ONLY = constants::RTE_ETH_RSS_L3_SRC_ONLY;
+        const L3_DST_ONLY = constants::RTE_ETH_RSS_L3_DST_ONLY;
+        const L4_SRC_ONLY = constants::RTE_ETH_RSS_L4_SRC_ONLY;
+        const L4_DST_ONLY = constants::RTE_ETH_RSS_L4_DST_ONLY;
+        const L2_SRC_ONLY = constants::RTE_ETH_RSS_L2_SRC_ONLY;
+        const L2_DST_ONLY = constants::RTE_ETH_RSS_L2_DST_ONLY;
+
+        /// 所有 IP 报文
+        const IP = constants::RTE_ETH_RSS_IP;
+        /// 所有 TCP 报文
+        const TCP = constants::RTE_ETH_RSS_TCP;
+        /// 所有 UDP 报文
+        const UDP = constants::RTE_ETH_RSS_UDP;
+        /// 所有 SCTP 报文
+        const SCTP = constants::RTE_ETH_RSS_SCTP;
+    }
+}
+
+bitflags! {
+    /// 支持的链路速率（`RTE_ETH_LINK_SPEED_*`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct LinkSpeeds: u32 {
+        /// 禁用自协商，使用固定速率
+        const FIXED = constants::RTE_ETH_LINK_SPEED_FIXED;
+        const SPEED_10M_HD = constants::RTE_ETH_LINK_SPEED_10M_HD;
+        const SPEED_10M = constants::RTE_ETH_LINK_SPEED_10M;
+        const SPEED_100M_HD = constants::RTE_ETH_LINK_SPEED_100M_HD;
+        const SPEED_100M = constants::RTE_ETH_LINK_SPEED_100M;
+        const SPEED_1G = constants::RTE_ETH_LINK_SPEED_1G;
+        const SPEED_2_5G = constants::RTE_ETH_LINK_SPEED_2_5G;
+        const SPEED_5G = constants::RTE_ETH_LINK_SPEED_5G;
+        const SPEED_10G = constants::RTE_ETH_LINK_SPEED_10G;
+        const SPEED_20G = constants::RTE_ETH_LINK_SPEED_20G;
+        const SPEED_25G = constants::RTE_ETH_LINK_SPEED_25G;
+        const SPEED_40G = constants::RTE_ETH_LINK_SPEED_40G;
+        const SPEED_50G = constants::RTE_ETH_LINK_SPEED_50G;
+        const SPEED_56G = constants::RTE_ETH_LINK_SPEED_56G;
+        const SPEED_100G = constants::RTE_ETH_LINK_SPEED_100G;
+        const SPEED_200G = constants::RTE_ETH_LINK_SPEED_200G;
+    }
+}
+
+/// 收发描述符数量的限制（`rte_eth_desc_lim`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescLimits {
+    /// 每个队列最多的描述符数量
+    pub max: u16,
+    /// 每个队列最少的描述符数量
+    pub min: u16,
+    /// 描述符数量必须是该值的倍数
+    pub align: u16,
+    /// 每个数据包最多的段数
+    pub max_segs: u16,
+    /// 每个 MTU 大小的数据包最多的段数
+    pub max_mtu_segs: u16,
+}
+
+impl DescLimits {
+    fn from_raw(lim: &rte_eth_desc_lim) -> Self {
+        Self {
+            max: lim.nb_max,
+            min: lim.nb_min,
+            align: lim.nb_align,
+            max_segs: lim.nb_seg_max,
+            max_mtu_segs: lim.nb_mtu_seg_max,
+        }
+    }
+
+    /// 允许的描述符数量范围
+    pub fn range(&self) -> RangeInclusive<u16> {
+        self.min..=self.max
+    }
+}
+
+/// 端口的设备信息（`rte_eth_dev_info`）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInfo {
+    /// 端口 ID
+    pub port_id: u16,
+    /// 驱动名称
+    pub driver_name: String,
+    /// 对应的内核网络接口序号，没有时为 0
+    pub if_index: u32,
+    /// 最小 MTU
+    pub min_mtu: u16,
+    /// 最大 MTU
+    pub max_mtu: u16,
+    /// 可接收的最大帧长
+    pub max_rx_pktlen: u32,
+    /// 最多的接收队列数量
+    pub max_rx_queues: u16,
+    /// 最多的发送队列数量
+    pub max_tx_queues: u16,
+    /// 最多的 MAC 地址数量
+    pub max_mac_addrs: u32,
+    /// 接收描述符限制
+    pub rx_desc_lim: DescLimits,
+    /// 发送描述符限制
+    pub tx_desc_lim: DescLimits,
+    /// 端口级接收 offload 能力
+    pub rx_offload_capa: RxOffload,
+    /// 端口级发送 offload 能力
+    pub tx_offload_capa: TxOffload,
+    /// 队列级接收 offload 能力
+    pub rx_queue_offload_capa: RxOffload,
+    /// 队列级发送 offload 能力
+    pub tx_queue_offload_capa: TxOffload,
+    /// RSS 重定向表（RETA）的大小
+    pub reta_size: u16,
+    /// RSS 哈希密钥的字节数
+    pub hash_key_size: u8,
+    /// 支持的 RSS 哈希类型
+    pub flow_type_rss_offloads: RssHash,
+    /// 支持的链路速率
+    pub speed_capa: LinkSpeeds,
+    /// 是否支持链路状态变化中断（`RTE_ETH_DEV_INTR_LSC`）
+    pub lsc_interrupt: bool,
+}
+
+impl PortInfo {
+    /// 调用 `rte_eth_dev_info_get` 查询端口信息，端口无需事先配置
+    pub fn query(port_id: u16) -> Result<Self> {
+        Ok(Self::from_raw(port_id, &dev_info(port_id)?))
+    }
+
+    pub(crate) fn from_raw(port_id: u16, info: &rte_eth_dev_info) -> Self {
+        let driver_name = if info.driver_name.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(info.driver_name) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        Self {
+            port_id,
+            driver_name,
+            if_index: info.if_index,
+            min_mtu: info.min_mtu,
+            max_mtu: info.max_mtu,
+            max_rx_pktlen: info.max_rx_pktlen,
+            max_rx_queues: info.max_rx_queues,
+            max_tx_queues: info.max_tx_queues,
+            max_mac_addrs: info.max_mac_addrs,
+            rx_desc_lim: DescLimits::from_raw(&info.rx_desc_lim),
+            tx_desc_lim: DescLimits::from_raw(&info.tx_desc_lim),
+            rx_offload_capa: RxOffload::from_bits_retain(info.rx_offload_capa),
+            tx_offload_capa: TxOffload::from_bits_retain(info.tx_offload_capa),
+            rx_queue_offload_capa: RxOffload::from_bits_retain(info.rx_queue_offload_capa),
+            tx_queue_offload_capa: TxOffload::from_bits_retain(info.tx_queue_offload_capa),
+            reta_size: info.reta_size,
+            hash_key_size: info.hash_key_size,
+            flow_type_rss_offloads: RssHash::from_bits_retain(info.flow_type_rss_offloads),
+            speed_capa: LinkSpeeds::from_bits_retain(info.speed_capa),
+            lsc_interrupt: !info.dev_flags.is_null()
+                && unsafe { *info.dev_flags } & constants::RTE_ETH_DEV_INTR_LSC != 0,
+        }
+    }
+
+    /// 允许的 MTU 范围
+    pub fn mtu_range(&self) -> RangeInclusive<u16> {
+        self.min_mtu..=self.max_mtu
+    }
+}
@@ -0,0 +1,111 @@
+//! 链路状态查询
+
+use super::port::Port;
+use crate::error::{check_ret, DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::mem;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 轮询链路状态的间隔
+const LINK_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 链路双工模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkDuplex {
+    Half,
+    Full,
+}
+
+/// 链路状态（`rte_eth_link`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LinkStatus {
+    /// 链路速率（Mbps），链路断开或驱动无法获知时为 `None`
+    pub speed_mbps: Option<u32>,
+    pub duplex: LinkDuplex,
+    /// 是否通过自动协商建立
+    pub autoneg: bool,
+    /// 链路是否已连通
+    pub up: bool,
+}
+
+impl LinkStatus {
+    /// 查询端口当前的链路状态，不等待（`rte_eth_link_get_nowait`）
+    ///
+    /// 可以在事件回调中使用。
+    pub fn query(port_id: u16) -> Result<Self> {
+        let mut link: rte_eth_link = unsafe { mem::zeroed() };
+        check_ret("rte_eth_link_get_nowait", unsafe {
+            rte_eth_link_get_nowait(port_id, &mut link)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(Self::from_raw(&link))
+    }
+
+    pub(crate) fn from_raw(link: &rte_eth_link) -> Self {
+        let speed_mbps = if link.link_speed == constants::RTE_ETH_SPEED_NUM_NONE
+            || link.link_speed == constants::RTE_ETH_SPEED_NUM_UNKNOWN
+        {
+            None
+        } else {
+            Some(link.link_speed)
+        };
+        LinkStatus {
+            speed_mbps,
+            duplex: if link.link_duplex() as u32 == constants::RTE_ETH_LINK_FULL_DUPLEX {
+                LinkDuplex::Full
+            } else {
+                LinkDuplex::Half
+            },
+            autoneg: link.link_autoneg() as u32 == constants::RTE_ETH_LINK_AUTONEG,
+            up: link.link_status() as u32 == constants::RTE_ETH_LINK_UP,
+        }
+    }
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.up {
+            return write!(f, "链路断开");
+        }
+        match self.speed_mbps {
+            Some(speed) => write!(f, "链路连通，{} Mbps", speed)?,
+            None => write!(f, "链路连通，速率未知")?,
+        }
+        match self.duplex {
+            LinkDuplex::Full => write!(f, "，全双工")?,
+            LinkDuplex::Half => write!(f, "，半双工")?,
+        }
+        if self.autoneg {
+            write!(f, "，自动协商")?;
+        }
+        Ok(())
+    }
+}
+
+impl Port {
+    /// 当前链路状态，不等待（`rte_eth_link_get_nowait`）
+    pub fn link(&self) -> Result<LinkStatus> {
+        LinkStatus::query(self.port_id())
+    }
+
+    /// 等待链路连通，最多等待 `timeout`
+    ///
+    /// 超时仍未连通时返回 `ETIMEDOUT` 错误。
+    pub fn wait_for_link_up(&self, timeout: Duration) -> Result<LinkStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let link = self.link()?;
+            if link.up {
+                return Ok(link);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(DpdkError::new("rte_eth_link_get_nowait", libc::ETIMEDOUT)
+                    .with_port(self.port_id()));
+            }
+            thread::sleep(LINK_POLL_INTERVAL.min(deadline - now));
+        }
+    }
+}
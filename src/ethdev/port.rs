@@ -0,0 +1,416 @@
+//! 端口配置与生命周期
+
+use super::info::{DescLimits, PortInfo, RssHash, RxOffload, TxOffload};
+use super::queue::{RxQueue, TxQueue};
+use crate::error::{check_ret, DpdkError, Result};
+use crate::mbuf::PktMbufPool;
+use crate::*;
+use std::mem;
+use std::os::raw::c_uint;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// 已被 `PortBuilder::start` 占用的端口，防止同一端口被配置两次
+static CONFIGURED: [AtomicBool; constants::RTE_MAX_ETHPORTS as usize] =
+    [const { AtomicBool::new(false) }; constants::RTE_MAX_ETHPORTS as usize];
+
+/// 端口配置构建器，由 `Port::configure` 创建
+#[derive(Debug, Clone)]
+pub struct PortBuilder {
+    port_id: u16,
+    nb_rx_queues: u16,
+    nb_rx_desc: u16,
+    rx_pool: Option<PktMbufPool>,
+    nb_tx_queues: u16,
+    nb_tx_desc: u16,
+    rx_offloads: RxOffload,
+    tx_offloads: TxOffload,
+    rss: Option<RssHash>,
+    mtu: Option<u16>,
+    promiscuous: bool,
+    lsc_interrupt: bool,
+}
+
+impl PortBuilder {
+    /// 接收队列数量、每个队列的描述符数量以及接收缓冲区所用的内存池
+    pub fn rx_queues(mut self, count: u16, desc: u16, pool: &PktMbufPool) -> Self {
+        self.nb_rx_queues = count;
+        self.nb_rx_desc = desc;
+        self.rx_pool = Some(pool.clone());
+        self
+    }
+
+    /// 发送队列数量和每个队列的描述符数量
+    pub fn tx_queues(mut self, count: u16, desc: u16) -> Self {
+        self.nb_tx_queues = count;
+        self.nb_tx_desc = desc;
+        self
+    }
+
+    /// 在端口级开启的接收 offload
+    pub fn rx_offloads(mut self, offloads: RxOffload) -> Self {
+        self.rx_offloads = offloads;
+        self
+    }
+
+    /// 在端口级开启的发送 offload
+    pub fn tx_offloads(mut self, offloads: TxOffload) -> Self {
+        self.tx_offloads = offloads;
+        self
+    }
+
+    /// 开启 RSS，按 `hash` 指定的字段把数据包分散到各个接收队列
+    ///
+    /// 使用驱动默认的哈希密钥和均匀分布的重定向表，启动后可以用 `Port::set_rss_conf`
+    /// 和 `Port::set_reta` 修改。
+    pub fn rss(mut self, hash: RssHash) -> Self {
+        self.rss = Some(hash);
+        self
+    }
+
+    /// 端口 MTU，默认使用驱动的默认值（通常为 1500）
+    pub fn mtu(mut self, mtu: u16) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /// 启动后是否开启混杂模式
+    pub fn promiscuous(mut self, enable: bool) -> Self {
+        self.promiscuous = enable;
+        self
+    }
+
+    /// 是否开启链路状态变化中断，开启后才会产生 `PortEvent::LinkStateChange` 事件
+    pub fn lsc_interrupt(mut self, enable: bool) -> Self {
+        self.lsc_interrupt = enable;
+        self
+    }
+
+    /// 按 `rte_eth_dev_info_get` 给出的限制检查配置
+    fn validate(&self, info: &PortInfo) -> Result<()> {
+        let port_id = self.port_id;
+        if self.nb_rx_queues > info.max_rx_queues {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 最多支持 {} 个接收队列，请求 {} 个",
+                port_id, info.driver_name, info.max_rx_queues, self.nb_rx_queues
+            )));
+        }
+        if self.nb_tx_queues > info.max_tx_queues {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 最多支持 {} 个发送队列，请求 {} 个",
+                port_id, info.driver_name, info.max_tx_queues, self.nb_tx_queues
+            )));
+        }
+        if self.nb_rx_queues > 0 {
+            check_desc(port_id, "接收", self.nb_rx_desc, &info.rx_desc_lim)?;
+        }
+        if self.nb_tx_queues > 0 {
+            check_desc(port_id, "发送", self.nb_tx_desc, &info.tx_desc_lim)?;
+        }
+        let rx_missing = self.rx_offloads - info.rx_offload_capa;
+        if !rx_missing.is_empty() {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 不支持接收 offload {:?}",
+                port_id, info.driver_name, rx_missing
+            )));
+        }
+        let tx_missing = self.tx_offloads - info.tx_offload_capa;
+        if !tx_missing.is_empty() {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 不支持发送 offload {:?}",
+                port_id, info.driver_name, tx_missing
+            )));
+        }
+        if let Some(hash) = self.rss {
+            let missing = hash - info.flow_type_rss_offloads;
+            if !missing.is_empty() {
+                return Err(DpdkError::invalid(format!(
+                    "端口 {} ({}) 不支持 RSS 哈希类型 {:?}",
+                    port_id, info.driver_name, missing
+                )));
+            }
+        }
+        if self.lsc_interrupt && !info.lsc_interrupt {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 不支持链路状态变化中断",
+                port_id, info.driver_name
+            )));
+        }
+        if let Some(mtu) = self.mtu {
+            if !info.mtu_range().contains(&mtu) {
+                return Err(DpdkError::invalid(format!(
+                    "端口 {} 的 MTU 范围为 {}..={}，请求 {}",
+                    port_id, info.min_mtu, info.max_mtu, mtu
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 配置并启动端口，返回端口及其所有接收、发送队列的句柄
+    ///
+    /// 中途失败时已经配置的端口会被关闭。端口已经启动、尚未被丢弃时返回错误。
+    pub fn start(self) -> Result<(Port, Vec<RxQueue>, Vec<TxQueue>)> {
+        let port_id = self.port_id;
+        if unsafe { rte_eth_dev_is_valid_port(port_id) } == 0 {
+            return Err(DpdkError::invalid(format!("端口 {} 不存在", port_id)));
+        }
+        let claim = PortClaim::acquire(port_id)?;
+        let rx_pool = match (&self.rx_pool, self.nb_rx_queues) {
+            (_, 0) => None,
+            (Some(pool), _) => Some(pool.clone()),
+            (None, _) => return Err(DpdkError::invalid("接收队列需要 mbuf 内存池")),
+        };
+        let info = PortInfo::query(port_id)?;
+        self.validate(&info)?;
+
+        let mut conf: rte_eth_conf = unsafe { mem::zeroed() };
+        conf.rxmode.offloads = self.rx_offloads.bits();
+        conf.txmode.offloads = self.tx_offloads.bits();
+        if let Some(hash) = self.rss {
+            conf.rxmode.mq_mode = rte_eth_rx_mq_mode_RTE_ETH_MQ_RX_RSS;
+            conf.rx_adv_conf.rss_conf.rss_hf = hash.bits();
+        }
+        if let Some(mtu) = self.mtu {
+            conf.rxmode.mtu = mtu as u32;
+        }
+        conf.intr_conf.set_lsc(self.lsc_interrupt as u32);
+        check_ret("rte_eth_dev_configure", unsafe {
+            rte_eth_dev_configure(port_id, self.nb_rx_queues, self.nb_tx_queues, &conf)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+
+        // 从这里开始，出错时由 `PortGuard` 负责关闭端口。
+        let guard = Arc::new(PortGuard {
+            port_id,
+            nb_rx_queues: self.nb_rx_queues,
+            nb_tx_queues: self.nb_tx_queues,
+            tx_offloads: self.tx_offloads,
+            _rx_pool: rx_pool.clone(),
+            _claim: claim,
+        });
+
+        let mut nb_rx_desc = self.nb_rx_desc;
+        let mut nb_tx_desc = self.nb_tx_desc;
+        check_ret("rte_eth_dev_adjust_nb_rx_tx_desc", unsafe {
+            rte_eth_dev_adjust_nb_rx_tx_desc(port_id, &mut nb_rx_desc, &mut nb_tx_desc)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+
+        let socket_id = unsafe { rte_eth_dev_socket_id(port_id) } as c_uint;
+        if let Some(pool) = &rx_pool {
+            for queue_id in 0..self.nb_rx_queues {
+                check_ret("rte_eth_rx_queue_setup", unsafe {
+                    rte_eth_rx_queue_setup(
+                        port_id,
+                        queue_id,
+                        nb_rx_desc,
+                        socket_id,
+                        ptr::null(),
+                        pool.as_ptr(),
+                    )
+                })
+                .map_err(|e| e.with_port(port_id).with_queue(queue_id))?;
+            }
+        }
+        for queue_id in 0..self.nb_tx_queues {
+            check_ret("rte_eth_tx_queue_setup", unsafe {
+                rte_eth_tx_queue_setup(port_id, queue_id, nb_tx_desc, socket_id, ptr::null())
+            })
+            .map_err(|e| e.with_port(port_id).with_queue(queue_id))?;
+        }
+
+        check_ret("rte_eth_dev_start", unsafe { rte_eth_dev_start(port_id) })
+            .map_err(|e| e.with_port(port_id))?;
+
+        let mut port = Port { guard };
+        if self.promiscuous {
+            port.set_promiscuous(true)?;
+        }
+
+        let rx_queues = (0..self.nb_rx_queues)
+            .map(|queue_id| RxQueue::with_port(port.guard.clone(), queue_id))
+            .collect();
+        let tx_queues = (0..self.nb_tx_queues)
+            .map(|queue_id| TxQueue::with_port(port.guard.clone(), queue_id))
+            .collect();
+        Ok((port, rx_queues, tx_queues))
+    }
+}
+
+fn check_desc(port_id: u16, dir: &str, desc: u16, lim: &DescLimits) -> Result<()> {
+    if !lim.range().contains(&desc) {
+        return Err(DpdkError::invalid(format!(
+            "端口 {} 的{}描述符数量范围为 {}..={}，请求 {}",
+            port_id, dir, lim.min, lim.max, desc
+        )));
+    }
+    Ok(())
+}
+
+/// 调用 `rte_eth_dev_info_get` 获取设备信息
+pub(crate) fn dev_info(port_id: u16) -> Result<rte_eth_dev_info> {
+    let mut info: rte_eth_dev_info = unsafe { mem::zeroed() };
+    check_ret("rte_eth_dev_info_get", unsafe {
+        rte_eth_dev_info_get(port_id, &mut info)
+    })
+    .map_err(|e| e.with_port(port_id))?;
+    Ok(info)
+}
+
+/// 已配置端口的所有权，`Port` 和所有队列句柄都被丢弃后停止并关闭端口
+#[derive(Debug)]
+pub(crate) struct PortGuard {
+    pub(crate) port_id: u16,
+    nb_rx_queues: u16,
+    nb_tx_queues: u16,
+    tx_offloads: TxOffload,
+    // 接收队列中的 mbuf 在端口关闭时才归还，内存池必须比端口活得更久。
+    _rx_pool: Option<PktMbufPool>,
+    // 在 `drop` 关闭端口之后才释放占用。
+    _claim: PortClaim,
+}
+
+impl Drop for PortGuard {
+    fn drop(&mut self) {
+        unsafe {
+            rte_eth_dev_stop(self.port_id);
+            rte_eth_dev_close(self.port_id);
+        }
+    }
+}
+
+/// 对 `CONFIGURED` 中一个端口的占用，被丢弃时释放
+#[derive(Debug)]
+struct PortClaim(u16);
+
+impl PortClaim {
+    fn acquire(port_id: u16) -> Result<Self> {
+        // `rte_eth_dev_is_valid_port` 已经保证端口 ID 小于 `RTE_MAX_ETHPORTS`。
+        if CONFIGURED[port_id as usize]
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} 已经启动，需要先丢弃原来的 Port 和所有队列句柄",
+                port_id
+            )));
+        }
+        Ok(PortClaim(port_id))
+    }
+}
+
+impl Drop for PortClaim {
+    fn drop(&mut self) {
+        CONFIGURED[self.0 as usize].store(false, Ordering::Release);
+    }
+}
+
+/// 已启动的以太网端口
+///
+/// 队列句柄共享端口的所有权，`Port` 和由它返回的所有队列句柄都被丢弃后，
+/// 端口才会被停止（`rte_eth_dev_stop`）并关闭（`rte_eth_dev_close`）。
+#[derive(Debug)]
+pub struct Port {
+    pub(super) guard: Arc<PortGuard>,
+}
+
+impl Port {
+    /// 开始配置端口
+    pub fn configure(port_id: u16) -> PortBuilder {
+        PortBuilder {
+            port_id,
+            nb_rx_queues: 0,
+            nb_rx_desc: 1024,
+            rx_pool: None,
+            nb_tx_queues: 0,
+            nb_tx_desc: 1024,
+            rx_offloads: RxOffload::empty(),
+            tx_offloads: TxOffload::empty(),
+            rss: None,
+            mtu: None,
+            promiscuous: false,
+            lsc_interrupt: false,
+        }
+    }
+
+    /// 端口 ID
+    pub fn port_id(&self) -> u16 {
+        self.guard.port_id
+    }
+
+    /// 接收队列数量
+    pub fn nb_rx_queues(&self) -> u16 {
+        self.guard.nb_rx_queues
+    }
+
+    /// 发送队列数量
+    pub fn nb_tx_queues(&self) -> u16 {
+        self.guard.nb_tx_queues
+    }
+
+    /// 在端口级开启的发送 offload，即 `PortBuilder::tx_offloads` 的设置
+    pub fn tx_offloads(&self) -> TxOffload {
+        self.guard.tx_offloads
+    }
+
+    /// 设备信息与能力
+    pub fn info(&self) -> Result<PortInfo> {
+        PortInfo::query(self.port_id())
+    }
+
+    /// 端口所在的 NUMA socket，未知时为 `SOCKET_ID_ANY`
+    pub fn socket_id(&self) -> i32 {
+        unsafe { rte_eth_dev_socket_id(self.port_id()) }
+    }
+
+    /// 端口的 MAC 地址
+    pub fn mac_addr(&self) -> Result<[u8; 6]> {
+        let mut addr: rte_ether_addr = unsafe { mem::zeroed() };
+        check_ret("rte_eth_macaddr_get", unsafe {
+            rte_eth_macaddr_get(self.port_id(), &mut addr)
+        })
+        .map_err(|e| e.with_port(self.port_id()))?;
+        Ok(addr.addr_bytes)
+    }
+
+    /// 是否处于混杂模式
+    pub fn is_promiscuous(&self) -> bool {
+        unsafe { rte_eth_promiscuous_get(self.port_id()) == 1 }
+    }
+
+    /// 开启或关闭混杂模式
+    pub fn set_promiscuous(&mut self, enable: bool) -> Result<()> {
+        let port_id = self.port_id();
+        let ret = if enable {
+            check_ret("rte_eth_promiscuous_enable", unsafe {
+                rte_eth_promiscuous_enable(port_id)
+            })
+        } else {
+            check_ret("rte_eth_promiscuous_disable", unsafe {
+                rte_eth_promiscuous_disable(port_id)
+            })
+        };
+        ret.map(|_| ()).map_err(|e| e.with_port(port_id))
+    }
+
+    /// 当前 MTU
+    pub fn mtu(&self) -> Result<u16> {
+        let mut mtu = 0;
+        check_ret("rte_eth_dev_get_mtu", unsafe {
+            rte_eth_dev_get_mtu(self.port_id(), &mut mtu)
+        })
+        .map_err(|e| e.with_port(self.port_id()))?;
+        Ok(mtu)
+    }
+
+    /// 修改 MTU
+    pub fn set_mtu(&mut self, mtu: u16) -> Result<()> {
+        check_ret("rte_eth_dev_set_mtu", unsafe {
+            rte_eth_dev_set_mtu(self.port_id(), mtu)
+        })
+        .map_err(|e| e.with_port(self.port_id()))?;
+        Ok(())
+    }
+}
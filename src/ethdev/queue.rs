@@ -0,0 +1,140 @@
+//! 突发收发队列句柄
+
+use super::port::PortGuard;
+use crate::mbuf::MbufBatch;
+use crate::*;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// 不实现 `Sync` 的标记，保证队列句柄不会被多个线程同时访问
+type NotSync = PhantomData<Cell<()>>;
+
+/// 接收队列句柄
+#[derive(Debug)]
+pub struct RxQueue {
+    port_id: u16,
+    queue_id: u16,
+    _port: Option<Arc<PortGuard>>,
+    _not_sync: NotSync,
+}
+
+impl RxQueue {
+    /// 为已经完成设置的接收队列创建句柄
+    ///
+    /// # Safety
+    ///
+    /// 队列必须已通过 `rte_eth_rx_queue_setup` 设置，并且在句柄存活期间
+    /// 不能通过其他句柄或原始调用访问同一个队列。
+    pub unsafe fn new(port_id: u16, queue_id: u16) -> Self {
+        Self {
+            port_id,
+            queue_id,
+            _port: None,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 由 `PortBuilder::start` 创建，句柄存活期间端口不会被关闭
+    pub(crate) fn with_port(port: Arc<PortGuard>, queue_id: u16) -> Self {
+        Self {
+            port_id: port.port_id,
+            queue_id,
+            _port: Some(port),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 所属端口
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    /// 队列 ID
+    pub fn queue_id(&self) -> u16 {
+        self.queue_id
+    }
+
+    /// 接收数据包并追加到 `batch` 末尾，最多填满批次，返回收到的数量
+    pub fn recv(&mut self, batch: &mut MbufBatch) -> usize {
+        let free = batch.free_space();
+        if free == 0 {
+            return 0;
+        }
+        let n = unsafe {
+            rte_eth_rx_burst(self.port_id, self.queue_id, batch.spare_ptr(), free as u16)
+        } as usize;
+        unsafe {
+            batch.commit(n);
+        }
+        n
+    }
+}
+
+/// 发送队列句柄
+#[derive(Debug)]
+pub struct TxQueue {
+    port_id: u16,
+    queue_id: u16,
+    _port: Option<Arc<PortGuard>>,
+    _not_sync: NotSync,
+}
+
+impl TxQueue {
+    /// 为已经完成设置的发送队列创建句柄
+    ///
+    /// # Safety
+    ///
+    /// 队列必须已通过 `rte_eth_tx_queue_setup` 设置，并且在句柄存活期间
+    /// 不能通过其他句柄或原始调用访问同一个队列。
+    pub unsafe fn new(port_id: u16, queue_id: u16) -> Self {
+        Self {
+            port_id,
+            queue_id,
+            _port: None,
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 由 `PortBuilder::start` 创建，句柄存活期间端口不会被关闭
+    pub(crate) fn with_port(port: Arc<PortGuard>, queue_id: u16) -> Self {
+        Self {
+            port_id: port.port_id,
+            queue_id,
+            _port: Some(port),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// 所属端口
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    /// 队列 ID
+    pub fn queue_id(&self) -> u16 {
+        self.queue_id
+    }
+
+    /// 从 `batch` 开头发送数据包，返回发送的数量
+    ///
+    /// 已发送的数据包的所有权转移给驱动，未发送的数据包按原顺序留在批次中，
+    /// 可以重试或随批次释放。
+    pub fn send(&mut self, batch: &mut MbufBatch) -> usize {
+        if batch.is_empty() {
+            return 0;
+        }
+        let n = unsafe {
+            rte_eth_tx_burst(
+                self.port_id,
+                self.queue_id,
+                batch.as_raw_ptr(),
+                batch.len() as u16,
+            )
+        } as usize;
+        unsafe {
+            batch.forget_front(n);
+        }
+        n
+    }
+}
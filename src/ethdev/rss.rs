@@ -0,0 +1,373 @@
+//! RSS 哈希配置、重定向表和软件 Toeplitz 哈希
+
+use super::info::RssHash;
+use super::port::Port;
+use crate::error::{check_ret, DpdkError, Result};
+use crate::*;
+use std::mem;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ptr;
+
+/// 每个 `rte_eth_rss_reta_entry64` 容纳的重定向表项数
+const RETA_GROUP_SIZE: usize = constants::RTE_ETH_RETA_GROUP_SIZE as usize;
+
+/// RSS 哈希密钥和哈希类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RssConf {
+    /// 哈希密钥，长度为 `PortInfo::hash_key_size`；设置时为 `None` 表示保持当前密钥
+    pub key: Option<Vec<u8>>,
+    /// 参与哈希的报文类型和字段
+    pub hash: RssHash,
+}
+
+impl Port {
+    /// 当前的 RSS 哈希密钥和哈希类型（`rte_eth_dev_rss_hash_conf_get`）
+    pub fn rss_conf(&self) -> Result<RssConf> {
+        let port_id = self.port_id();
+        let mut key = vec![0u8; self.info()?.hash_key_size as usize];
+        let mut conf: rte_eth_rss_conf = unsafe { mem::zeroed() };
+        if !key.is_empty() {
+            conf.rss_key = key.as_mut_ptr();
+            conf.rss_key_len = key.len() as u8;
+        }
+        check_ret("rte_eth_dev_rss_hash_conf_get", unsafe {
+            rte_eth_dev_rss_hash_conf_get(port_id, &mut conf)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(RssConf {
+            key: (!key.is_empty()).then_some(key),
+            hash: RssHash::from_bits_retain(conf.rss_hf),
+        })
+    }
+
+    /// 修改 RSS 哈希密钥和哈希类型（`rte_eth_dev_rss_hash_update`）
+    ///
+    /// 端口需要以 `PortBuilder::rss` 开启 RSS。`hash` 为空时关闭哈希，所有数据包进入队列 0。
+    pub fn set_rss_conf(&mut self, conf: &RssConf) -> Result<()> {
+        let port_id = self.port_id();
+        let info = self.info()?;
+        let missing = conf.hash - info.flow_type_rss_offloads;
+        if !missing.is_empty() {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} ({}) 不支持 RSS 哈希类型 {:?}",
+                port_id, info.driver_name, missing
+            )));
+        }
+        let mut raw: rte_eth_rss_conf = unsafe { mem::zeroed() };
+        raw.rss_hf = conf.hash.bits();
+        // DPDK 不修改密钥，只是接口没有声明为 const，这里复制一份。
+        let mut key = conf.key.clone();
+        if let Some(key) = &mut key {
+            if key.len() != info.hash_key_size as usize {
+                return Err(DpdkError::invalid(format!(
+                    "端口 {} 的 RSS 密钥长度为 {} 字节，提供了 {} 字节",
+                    port_id,
+                    info.hash_key_size,
+                    key.len()
+                )));
+            }
+            raw.rss_key = key.as_mut_ptr();
+            raw.rss_key_len = key.len() as u8;
+        }
+        check_ret("rte_eth_dev_rss_hash_update", unsafe {
+            rte_eth_dev_rss_hash_update(port_id, &mut raw)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(())
+    }
+
+    /// 读取 RSS 重定向表（`rte_eth_dev_rss_reta_query`）
+    ///
+    /// 第 `i` 项是哈希值对表长取模为 `i` 的数据包进入的接收队列，表长为 `PortInfo::reta_size`。
+    pub fn reta(&self) -> Result<Vec<u16>> {
+        let port_id = self.port_id();
+        let size = self.info()?.reta_size as usize;
+        let mut groups = reta_groups(size);
+        check_ret("rte_eth_dev_rss_reta_query", unsafe {
+            rte_eth_dev_rss_reta_query(port_id, groups.as_mut_ptr(), size as u16)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(groups
+            .iter()
+            .flat_map(|group| group.reta)
+            .take(size)
+            .collect())
+    }
+
+    /// 设置 RSS 重定向表（`rte_eth_dev_rss_reta_update`）
+    ///
+    /// `reta` 的长度必须等于 `PortInfo::reta_size`，每一项都必须是有效的接收队列。
+    pub fn set_reta(&mut self, reta: &[u16]) -> Result<()> {
+        let port_id = self.port_id();
+        let size = self.info()?.reta_size as usize;
+        if reta.len() != size {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} 的重定向表有 {} 项，提供了 {} 项",
+                port_id,
+                size,
+                reta.len()
+            )));
+        }
+        if let Some(&queue) = reta.iter().find(|&&q| q >= self.nb_rx_queues()) {
+            return Err(DpdkError::invalid(format!(
+                "端口 {} 只有 {} 个接收队列，重定向表指向队列 {}",
+                port_id,
+                self.nb_rx_queues(),
+                queue
+            )));
+        }
+        let mut groups = reta_groups(size);
+        for (group, chunk) in groups.iter_mut().zip(reta.chunks(RETA_GROUP_SIZE)) {
+            group.reta[..chunk.len()].copy_from_slice(chunk);
+        }
+        check_ret("rte_eth_dev_rss_reta_update", unsafe {
+            rte_eth_dev_rss_reta_update(port_id, groups.as_mut_ptr(), size as u16)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(())
+    }
+}
+
+/// 覆盖 `size` 个表项的分组，掩码选中所有有效的表项
+fn reta_groups(size: usize) -> Vec<rte_eth_rss_reta_entry64> {
+    (0..size.div_ceil(RETA_GROUP_SIZE))
+        .map(|i| {
+            let n = (size - i * RETA_GROUP_SIZE).min(RETA_GROUP_SIZE);
+            let mut group: rte_eth_rss_reta_entry64 = unsafe { mem::zeroed() };
+            group.mask = if n == RETA_GROUP_SIZE {
+                u64::MAX
+            } else {
+                (1 << n) - 1
+            };
+            group
+        })
+        .collect()
+}
+
+/// RSS 哈希的输入：源地址、目的地址，以及可选的源端口和目的端口
+///
+/// 用于在软件中预测一条流被网卡分配到哪个接收队列：
+///
+/// ```ignore
+/// let conf = port.rss_conf()?;
+/// let reta = port.reta()?;
+/// let hash = RssTuple::ipv4(src, dst)
+///     .ports(sport, dport)
+///     .hash(conf.key.as_deref().unwrap())?;
+/// let queue = reta[hash as usize % reta.len()];
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RssTuple {
+    /// 按主机字节序排列的地址，IPv4 用前 2 个字，IPv6 用全部 8 个字
+    addrs: [u32; 8],
+    addr_words: usize,
+    ports: Option<(u16, u16)>,
+}
+
+impl RssTuple {
+    pub fn ipv4(src: Ipv4Addr, dst: Ipv4Addr) -> Self {
+        let mut addrs = [0; 8];
+        addrs[0] = u32::from(src);
+        addrs[1] = u32::from(dst);
+        RssTuple {
+            addrs,
+            addr_words: 2,
+            ports: None,
+        }
+    }
+
+    pub fn ipv6(src: Ipv6Addr, dst: Ipv6Addr) -> Self {
+        let mut addrs = [0; 8];
+        for (word, bytes) in addrs
+            .iter_mut()
+            .zip(src.octets().chunks(4).chain(dst.octets().chunks(4)))
+        {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        RssTuple {
+            addrs,
+            addr_words: 8,
+            ports: None,
+        }
+    }
+
+    /// 加入 TCP/UDP 端口，对应 `NONFRAG_IPV4_TCP` 等包含 L4 字段的哈希类型
+    pub fn ports(mut self, src_port: u16, dst_port: u16) -> Self {
+        self.ports = Some((src_port, dst_port));
+        self
+    }
+
+    /// 用 Toeplitz 算法计算哈希值（`rte_softrss`），与网卡使用同一密钥时结果一致
+    ///
+    /// 密钥至少要比输入多 4 字节：IPv4 带端口时 16 字节，IPv6 带端口时 40 字节。
+    pub fn hash(&self, key: &[u8]) -> Result<u32> {
+        let mut input = [0u32; 9];
+        input[..self.addr_words].copy_from_slice(&self.addrs[..self.addr_words]);
+        let mut len = self.addr_words;
+        if let Some((src_port, dst_port)) = self.ports {
+            input[len] = (src_port as u32) << 16 | dst_port as u32;
+            len += 1;
+        }
+        let min_key_len = (len + 1) * 4;
+        if key.len() < min_key_len {
+            return Err(DpdkError::invalid(format!(
+                "RSS 密钥只有 {} 字节，至少需要 {} 字节",
+                key.len(),
+                min_key_len
+            )));
+        }
+        // `rte_softrss` 按 32 位读取密钥，复制到对齐的缓冲区中。
+        let mut aligned = vec![0u32; key.len().div_ceil(4)];
+        unsafe {
+            ptr::copy_nonoverlapping(key.as_ptr(), aligned.as_mut_ptr() as *mut u8, key.len());
+            Ok(rte_softrss(
+                input.as_mut_ptr(),
+                len as u32,
+                aligned.as_ptr() as *const u8,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Microsoft RSS 规范中的默认密钥
+    const KEY: [u8; 40] = [
+        0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f,
+        0xb0, 0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30,
+        0xf2, 0x0c, 0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+    ];
+
+    /// Microsoft RSS 规范的验证数据：源地址、源端口、目的地址、目的端口、
+    /// 只有地址时的哈希值、包含端口时的哈希值
+    const IPV4: [(&str, u16, &str, u16, u32, u32); 5] = [
+        (
+            "66.9.149.187",
+            2794,
+            "161.142.100.80",
+            1766,
+            0x323e8fc2,
+            0x51ccc178,
+        ),
+        (
+            "199.92.111.2",
+            14230,
+            "65.69.140.83",
+            4739,
+            0xd718262a,
+            0xc626b0ea,
+        ),
+        (
+            "24.19.198.95",
+            12898,
+            "12.22.207.184",
+            38024,
+            0xd2d0a5de,
+            0x5c2b394a,
+        ),
+        (
+            "38.27.205.30",
+            48228,
+            "209.142.163.6",
+            2217,
+            0x82989176,
+            0xafc7327f,
+        ),
+        (
+            "153.39.163.191",
+            44251,
+            "202.188.127.2",
+            1303,
+            0x5d1809c5,
+            0x10e828a2,
+        ),
+    ];
+
+    const IPV6: [(&str, u16, &str, u16, u32, u32); 3] = [
+        (
+            "3ffe:2501:200:1fff::7",
+            2794,
+            "3ffe:2501:200:3::1",
+            1766,
+            0x2cc18cd5,
+            0x40207d3d,
+        ),
+        (
+            "3ffe:501:8::260:97ff:fe40:efab",
+            14230,
+            "ff02::1",
+            4739,
+            0x0f0c461c,
+            0xdde51bbf,
+        ),
+        (
+            "3ffe:1900:4545:3:200:f8ff:fe21:67cf",
+            44251,
+            "fe80::200:f8ff:fe21:67cf",
+            38024,
+            0x4b61e985,
+            0x02d1feef,
+        ),
+    ];
+
+    #[test]
+    fn ipv4_verification_vectors() {
+        for (src, sport, dst, dport, addrs, ports) in IPV4 {
+            let tuple = RssTuple::ipv4(src.parse().unwrap(), dst.parse().unwrap());
+            assert_eq!(tuple.hash(&KEY).unwrap(), addrs, "{} -> {}", src, dst);
+            assert_eq!(
+                tuple.ports(sport, dport).hash(&KEY).unwrap(),
+                ports,
+                "{}:{} -> {}:{}",
+                src,
+                sport,
+                dst,
+                dport
+            );
+        }
+    }
+
+    #[test]
+    fn ipv6_verification_vectors() {
+        for (src, sport, dst, dport, addrs, ports) in IPV6 {
+            let tuple = RssTuple::ipv6(src.parse().unwrap(), dst.parse().unwrap());
+            assert_eq!(tuple.hash(&KEY).unwrap(), addrs, "{} -> {}", src, dst);
+            assert_eq!(
+                tuple.ports(sport, dport).hash(&KEY).unwrap(),
+                ports,
+                "[{}]:{} -> [{}]:{}",
+                src,
+                sport,
+                dst,
+                dport
+            );
+        }
+    }
+
+    #[test]
+    fn short_key() {
+        let src = Ipv4Addr::new(66, 9, 149, 187);
+        let dst = Ipv4Addr::new(161, 142, 100, 80);
+        let tuple = RssTuple::ipv4(src, dst);
+        assert_eq!(tuple.hash(&KEY[..12]).unwrap(), 0x323e8fc2);
+        assert!(tuple.hash(&KEY[..11]).is_err());
+        assert!(tuple.ports(2794, 1766).hash(&KEY[..12]).is_err());
+
+        let v6 = RssTuple::ipv6(Ipv6Addr::LOCALHOST, Ipv6Addr::LOCALHOST).ports(1, 2);
+        assert!(v6.hash(&KEY[..36]).is_err());
+        assert!(v6.hash(&KEY).is_ok());
+    }
+
+    #[test]
+    fn unaligned_key() {
+        // 密钥不要求 4 字节对齐
+        let mut buf = [0u8; 41];
+        buf[1..].copy_from_slice(&KEY);
+        let src = Ipv4Addr::new(66, 9, 149, 187);
+        let dst = Ipv4Addr::new(161, 142, 100, 80);
+        let tuple = RssTuple::ipv4(src, dst).ports(2794, 1766);
+        assert_eq!(tuple.hash(&buf[1..]).unwrap(), 0x51ccc178);
+    }
+}
@@ -0,0 +1,230 @@
+//! 端口统计信息与扩展统计
+
+use super::port::Port;
+use crate::error::{check_ret, Result};
+use crate::*;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_uint;
+use std::ptr;
+use std::time::Duration;
+
+/// 单个队列的计数器
+///
+/// 发送队列没有错误计数，`errors` 恒为 0。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub packets: u64,
+    pub bytes: u64,
+    pub errors: u64,
+}
+
+impl QueueStats {
+    fn delta(&self, earlier: &QueueStats) -> QueueStats {
+        QueueStats {
+            packets: self.packets.saturating_sub(earlier.packets),
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+            errors: self.errors.saturating_sub(earlier.errors),
+        }
+    }
+}
+
+/// 端口基本统计信息（`rte_eth_stats_get`）
+///
+/// 每队列计数器只覆盖前 `RTE_ETHDEV_QUEUE_STAT_CNTRS` 个队列，
+/// 并且不是所有驱动都会填写。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortStats {
+    /// 成功接收的数据包数
+    pub rx_packets: u64,
+    /// 成功发送的数据包数
+    pub tx_packets: u64,
+    /// 成功接收的字节数
+    pub rx_bytes: u64,
+    /// 成功发送的字节数
+    pub tx_bytes: u64,
+    /// 因接收队列已满被硬件丢弃的数据包数
+    pub rx_missed: u64,
+    /// 接收错误的数据包数
+    pub rx_errors: u64,
+    /// 发送失败的数据包数
+    pub tx_errors: u64,
+    /// 因 mbuf 分配失败丢弃的数据包数
+    pub rx_nombuf: u64,
+    /// 每个接收队列的计数器
+    pub rx_queues: Vec<QueueStats>,
+    /// 每个发送队列的计数器
+    pub tx_queues: Vec<QueueStats>,
+}
+
+impl PortStats {
+    fn from_raw(raw: &rte_eth_stats, nb_rx_queues: u16, nb_tx_queues: u16) -> Self {
+        let nb_rx = (nb_rx_queues as usize).min(raw.q_ipackets.len());
+        let nb_tx = (nb_tx_queues as usize).min(raw.q_opackets.len());
+        PortStats {
+            rx_packets: raw.ipackets,
+            tx_packets: raw.opackets,
+            rx_bytes: raw.ibytes,
+            tx_bytes: raw.obytes,
+            rx_missed: raw.imissed,
+            rx_errors: raw.ierrors,
+            tx_errors: raw.oerrors,
+            rx_nombuf: raw.rx_nombuf,
+            rx_queues: (0..nb_rx)
+                .map(|i| QueueStats {
+                    packets: raw.q_ipackets[i],
+                    bytes: raw.q_ibytes[i],
+                    errors: raw.q_errors[i],
+                })
+                .collect(),
+            tx_queues: (0..nb_tx)
+                .map(|i| QueueStats {
+                    packets: raw.q_opackets[i],
+                    bytes: raw.q_obytes[i],
+                    errors: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// 计算从 `earlier` 到当前快照之间的增量
+    ///
+    /// 期间统计被重置时相应计数器按 0 计算。
+    pub fn delta(&self, earlier: &PortStats) -> PortStats {
+        let queues = |now: &[QueueStats], before: &[QueueStats]| {
+            now.iter()
+                .enumerate()
+                .map(|(i, q)| q.delta(before.get(i).unwrap_or(&QueueStats::default())))
+                .collect()
+        };
+        PortStats {
+            rx_packets: self.rx_packets.saturating_sub(earlier.rx_packets),
+            tx_packets: self.tx_packets.saturating_sub(earlier.tx_packets),
+            rx_bytes: self.rx_bytes.saturating_sub(earlier.rx_bytes),
+            tx_bytes: self.tx_bytes.saturating_sub(earlier.tx_bytes),
+            rx_missed: self.rx_missed.saturating_sub(earlier.rx_missed),
+            rx_errors: self.rx_errors.saturating_sub(earlier.rx_errors),
+            tx_errors: self.tx_errors.saturating_sub(earlier.tx_errors),
+            rx_nombuf: self.rx_nombuf.saturating_sub(earlier.rx_nombuf),
+            rx_queues: queues(&self.rx_queues, &earlier.rx_queues),
+            tx_queues: queues(&self.tx_queues, &earlier.tx_queues),
+        }
+    }
+
+    /// 根据间隔 `elapsed` 的两次快照计算收发速率
+    pub fn rate(&self, earlier: &PortStats, elapsed: Duration) -> PortRate {
+        let delta = self.delta(earlier);
+        let secs = elapsed.as_secs_f64();
+        let per_sec = |v: u64| if secs > 0.0 { v as f64 / secs } else { 0.0 };
+        PortRate {
+            rx_pps: per_sec(delta.rx_packets),
+            tx_pps: per_sec(delta.tx_packets),
+            rx_bps: per_sec(delta.rx_bytes) * 8.0,
+            tx_bps: per_sec(delta.tx_bytes) * 8.0,
+            rx_dropped_pps: per_sec(delta.rx_missed + delta.rx_nombuf),
+        }
+    }
+}
+
+/// 两次统计快照之间的平均速率
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PortRate {
+    /// 每秒接收的数据包数
+    pub rx_pps: f64,
+    /// 每秒发送的数据包数
+    pub tx_pps: f64,
+    /// 每秒接收的比特数
+    pub rx_bps: f64,
+    /// 每秒发送的比特数
+    pub tx_bps: f64,
+    /// 每秒因队列满或 mbuf 不足丢弃的数据包数
+    pub rx_dropped_pps: f64,
+}
+
+/// 一项驱动定义的扩展统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xstat {
+    /// 统计项 ID，可用于按 ID 查询
+    pub id: u64,
+    /// 统计项名称，如 `rx_good_packets`
+    pub name: String,
+    pub value: u64,
+}
+
+impl Port {
+    /// 端口基本统计信息
+    pub fn stats(&self) -> Result<PortStats> {
+        let port_id = self.port_id();
+        let mut raw: rte_eth_stats = unsafe { mem::zeroed() };
+        check_ret("rte_eth_stats_get", unsafe {
+            rte_eth_stats_get(port_id, &mut raw)
+        })
+        .map_err(|e| e.with_port(port_id))?;
+        Ok(PortStats::from_raw(
+            &raw,
+            self.nb_rx_queues(),
+            self.nb_tx_queues(),
+        ))
+    }
+
+    /// 所有扩展统计的名称和当前值
+    pub fn xstats(&self) -> Result<Vec<Xstat>> {
+        let port_id = self.port_id();
+        // 统计项的数量可能在两次调用之间变化，数量变多时重新获取。
+        loop {
+            let n = check_ret("rte_eth_xstats_get_names", unsafe {
+                rte_eth_xstats_get_names(port_id, ptr::null_mut(), 0)
+            })
+            .map_err(|e| e.with_port(port_id))? as usize;
+
+            let mut names: Vec<rte_eth_xstat_name> = vec![unsafe { mem::zeroed() }; n];
+            let nb_names = check_ret("rte_eth_xstats_get_names", unsafe {
+                rte_eth_xstats_get_names(port_id, names.as_mut_ptr(), n as c_uint)
+            })
+            .map_err(|e| e.with_port(port_id))? as usize;
+            if nb_names > n {
+                continue;
+            }
+
+            let mut values: Vec<rte_eth_xstat> = vec![unsafe { mem::zeroed() }; n];
+            let nb_values = check_ret("rte_eth_xstats_get", unsafe {
+                rte_eth_xstats_get(port_id, values.as_mut_ptr(), n as c_uint)
+            })
+            .map_err(|e| e.with_port(port_id))? as usize;
+            if nb_values > n {
+                continue;
+            }
+
+            return Ok(values[..nb_values]
+                .iter()
+                .filter_map(|xstat| {
+                    let name = names[..nb_names].get(xstat.id as usize)?;
+                    let name = unsafe { CStr::from_ptr(name.name.as_ptr()) };
+                    Some(Xstat {
+                        id: xstat.id,
+                        name: name.to_string_lossy().into_owned(),
+                        value: xstat.value,
+                    })
+                })
+                .collect());
+        }
+    }
+
+    /// 清零基本统计信息
+    pub fn reset_stats(&mut self) -> Result<()> {
+        check_ret("rte_eth_stats_reset", unsafe {
+            rte_eth_stats_reset(self.port_id())
+        })
+        .map_err(|e| e.with_port(self.port_id()))?;
+        Ok(())
+    }
+
+    /// 清零扩展统计（同时清零基本统计信息）
+    pub fn reset_xstats(&mut self) -> Result<()> {
+        check_ret("rte_eth_xstats_reset", unsafe {
+            rte_eth_xstats_reset(self.port_id())
+        })
+        .map_err(|e| e.with_port(self.port_id()))?;
+        Ok(())
+    }
+}
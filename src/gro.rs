@@ -0,0 +1,341 @@
+//! 通用接收合并（GRO，`librte_gro`）
+//!
+//! `GroContext` 把同一条流的多个小数据包合并为一个大数据包，减少上层协议栈处理的包数。
+//! 轻量模式只在一个批次内合并，合并结果直接留在批次中；重量模式把数据包保存在上下文中，
+//! 跨批次合并，需要定期调用 `flush` 取出：
+//!
+//! ```ignore
+//! let mut gro = GroContext::builder(GroType::TCP_IPV4 | GroType::VXLAN_TCP_IPV4)
+//!     .mode(GroMode::Heavyweight)
+//!     .timeout(Duration::from_micros(100))
+//!     .build()?;
+//! loop {
+//!     rxq.recv(&mut rx_batch);
+//!     gro.merge(&mut rx_batch);
+//!     gro.flush(&mut rx_batch);
+//!     // 处理 rx_batch ...
+//! }
+//! ```
+//!
+//! 合并依赖 mbuf 中的 `packet_type` 和各层头部长度，`merge` 会在软件中重新解析每个数据包
+//! 并填写它们，调用者不需要预先设置。合并后的数据包由多个段组成，头部校验和已更新。
+
+use crate::error::{DpdkError, Result};
+use crate::mbuf::{Mbuf, MbufBatch, TxOffloadLens};
+use crate::packet::{Ipv4Hdr, PacketType, TcpHdr, Tunnel, UdpHdr};
+use crate::*;
+use bitflags::bitflags;
+use std::fmt;
+use std::os::raw::c_void;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+bitflags! {
+    /// 参与合并的数据包类型（`RTE_GRO_*`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct GroType: u64 {
+        /// TCP/IPv4
+        const TCP_IPV4 = constants::RTE_GRO_TCP_IPV4;
+        /// 外层 IPv4 的 VXLAN 隧道中的 TCP/IPv4
+        const VXLAN_TCP_IPV4 = constants::RTE_GRO_IPV4_VXLAN_TCP_IPV4;
+        /// 分片的 UDP/IPv4
+        const UDP_IPV4 = constants::RTE_GRO_UDP_IPV4;
+        /// 外层 IPv4 的 VXLAN 隧道中分片的 UDP/IPv4
+        const VXLAN_UDP_IPV4 = constants::RTE_GRO_IPV4_VXLAN_UDP_IPV4;
+    }
+}
+
+/// 合并模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GroMode {
+    /// 只合并同一个批次中的数据包（`rte_gro_reassemble_burst`）
+    #[default]
+    Lightweight,
+    /// 跨批次合并，数据包保存在上下文中直到被 `flush` 取出（`rte_gro_reassemble`）
+    Heavyweight,
+}
+
+/// GRO 上下文构建器
+#[derive(Debug, Clone)]
+pub struct GroContextBuilder {
+    types: GroType,
+    mode: GroMode,
+    max_flows: u16,
+    max_items_per_flow: u16,
+    timeout: Duration,
+    socket_id: i32,
+}
+
+impl GroContextBuilder {
+    /// 合并模式，默认为轻量模式
+    pub fn mode(mut self, mode: GroMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// 同时合并的流数量上限，默认 64
+    pub fn max_flows(mut self, flows: u16) -> Self {
+        self.max_flows = flows;
+        self
+    }
+
+    /// 每条流中等待合并的数据包数量上限，默认 4
+    pub fn max_items_per_flow(mut self, items: u16) -> Self {
+        self.max_items_per_flow = items;
+        self
+    }
+
+    /// 重量模式下数据包在上下文中等待合并的最长时间，超时后由 `flush` 取出，默认 100 微秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 重量模式下分配合并表的 NUMA socket，默认为当前 lcore 所在的 socket
+    pub fn socket_id(mut self, socket_id: i32) -> Self {
+        self.socket_id = socket_id;
+        self
+    }
+
+    /// 创建上下文，重量模式下调用 `rte_gro_ctx_create` 分配合并表
+    pub fn build(self) -> Result<GroContext> {
+        if self.types.is_empty() {
+            return Err(DpdkError::invalid("至少需要一种 GRO 类型"));
+        }
+        if self.max_flows == 0 || self.max_items_per_flow == 0 {
+            return Err(DpdkError::invalid("流数量和每条流的数据包数量必须大于 0"));
+        }
+        let socket_id = if self.socket_id < 0 {
+            unsafe { rte_socket_id() as i32 }
+        } else {
+            self.socket_id
+        };
+        let param = rte_gro_param {
+            gro_types: self.types.bits(),
+            max_flow_num: self.max_flows,
+            max_item_per_flow: self.max_items_per_flow,
+            socket_id: socket_id as u16,
+        };
+        let ctx = match self.mode {
+            GroMode::Lightweight => None,
+            GroMode::Heavyweight => {
+                let ctx = unsafe { rte_gro_ctx_create(&param) };
+                // `rte_gro_ctx_create` 失败时不设置 `rte_errno`。
+                let ctx = NonNull::new(ctx)
+                    .ok_or_else(|| DpdkError::new("rte_gro_ctx_create", libc::ENOMEM))?;
+                Some(ctx)
+            }
+        };
+        let hz = unsafe { rte_get_tsc_hz() };
+        let timeout_cycles = (hz as u128 * self.timeout.as_nanos() / 1_000_000_000) as u64;
+        Ok(GroContext {
+            types: self.types,
+            param,
+            ctx,
+            timeout: self.timeout,
+            timeout_cycles,
+        })
+    }
+}
+
+/// GRO 上下文
+///
+/// 上下文不是线程安全的，每个 lcore 应使用自己的上下文。
+pub struct GroContext {
+    types: GroType,
+    param: rte_gro_param,
+    ctx: Option<NonNull<c_void>>,
+    timeout: Duration,
+    timeout_cycles: u64,
+}
+
+unsafe impl Send for GroContext {}
+
+impl GroContext {
+    /// 创建构建器，`types` 为参与合并的数据包类型
+    pub fn builder(types: GroType) -> GroContextBuilder {
+        GroContextBuilder {
+            types,
+            mode: GroMode::default(),
+            max_flows: 64,
+            max_items_per_flow: 4,
+            timeout: Duration::from_micros(100),
+            socket_id: SOCKET_ID_ANY,
+        }
+    }
+
+    pub fn types(&self) -> GroType {
+        self.types
+    }
+
+    pub fn mode(&self) -> GroMode {
+        if self.ctx.is_some() {
+            GroMode::Heavyweight
+        } else {
+            GroMode::Lightweight
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// 合并批次中的数据包，返回合并后批次中的数据包数量
+    ///
+    /// 轻量模式下合并结果留在批次中；重量模式下可以合并的数据包被移入上下文，
+    /// 批次中只剩下不能合并的数据包。被合并的数据包成为其他数据包的段，不会被释放。
+    pub fn merge(&mut self, batch: &mut MbufBatch) -> usize {
+        let vxlan = self
+            .types
+            .intersects(GroType::VXLAN_TCP_IPV4 | GroType::VXLAN_UDP_IPV4);
+        for mbuf in batch.iter_mut() {
+            prepare(mbuf, vxlan);
+        }
+        let nb_pkts = batch.len() as u16;
+        let n = unsafe {
+            match self.ctx {
+                None => rte_gro_reassemble_burst(batch.as_raw_ptr(), nb_pkts, &self.param),
+                Some(ctx) => rte_gro_reassemble(batch.as_raw_ptr(), nb_pkts, ctx.as_ptr()),
+            }
+        };
+        // 剩余的数据包已经被移到数组开头，其余的指针属于合并表或其他数据包。
+        unsafe { batch.forget_tail(n as usize) };
+        n as usize
+    }
+
+    /// 重量模式下上下文中的数据包数量，轻量模式下总是 0
+    pub fn pending(&self) -> u64 {
+        match self.ctx {
+            Some(ctx) => unsafe { rte_gro_get_pkt_count(ctx.as_ptr()) },
+            None => 0,
+        }
+    }
+
+    /// 把等待超过超时时间的数据包追加到 `out`，返回追加的数量
+    ///
+    /// 轻量模式下上下文中没有数据包，总是返回 0。
+    pub fn flush(&mut self, out: &mut MbufBatch) -> usize {
+        self.flush_older_than(self.timeout_cycles, out)
+    }
+
+    /// 把上下文中的所有数据包追加到 `out`，`out` 容纳不下时剩余的留在上下文中
+    pub fn flush_all(&mut self, out: &mut MbufBatch) -> usize {
+        self.flush_older_than(0, out)
+    }
+
+    fn flush_older_than(&mut self, timeout_cycles: u64, out: &mut MbufBatch) -> usize {
+        let Some(ctx) = self.ctx else {
+            return 0;
+        };
+        let max = out.free_space().min(u16::MAX as usize) as u16;
+        let n = unsafe {
+            rte_gro_timeout_flush(
+                ctx.as_ptr(),
+                timeout_cycles,
+                self.types.bits(),
+                out.spare_ptr(),
+                max,
+            )
+        };
+        unsafe { out.commit(n as usize) };
+        n as usize
+    }
+}
+
+impl Drop for GroContext {
+    fn drop(&mut self) {
+        let Some(ctx) = self.ctx else {
+            return;
+        };
+        // `rte_gro_ctx_destroy` 只释放合并表，表中的数据包需要先取出释放。
+        let mut out = MbufBatch::new(constants::RTE_GRO_MAX_BURST_ITEM_NUM as usize);
+        while self.flush_all(&mut out) > 0 {
+            out.clear();
+        }
+        unsafe { rte_gro_ctx_destroy(ctx.as_ptr()) };
+    }
+}
+
+impl fmt::Debug for GroContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GroContext")
+            .field("types", &self.types)
+            .field("mode", &self.mode())
+            .field("max_flows", &self.param.max_flow_num)
+            .field("max_items_per_flow", &self.param.max_item_per_flow)
+            .field("timeout", &self.timeout)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+/// 填写 GRO 需要的 `packet_type` 和头部长度
+///
+/// `rte_net_get_ptype` 不识别 VXLAN，开启 VXLAN 合并时另外解析隧道和内层头部。
+fn prepare(mbuf: &mut Mbuf, vxlan: bool) {
+    let (mut ptype, lens) = mbuf.detect_ptype();
+    let mut tx = TxOffloadLens {
+        l2_len: lens.l2_len as u16,
+        l3_len: lens.l3_len,
+        l4_len: lens.l4_len as u16,
+        ..Default::default()
+    };
+    if vxlan {
+        if let Some((inner, inner_lens)) = vxlan_layout(mbuf) {
+            ptype = PacketType::from_bits(ptype.l2() | ptype.l3() | ptype.l4() | inner);
+            tx = inner_lens;
+        }
+    }
+    mbuf.set_packet_type(ptype);
+    // 长度来自第一个段内的头部，不会超出位域范围。
+    let _ = mbuf.set_tx_offload_lens(tx);
+}
+
+/// VXLAN 数据包的隧道和内层类型，以及 GRO 要求的头部长度
+///
+/// GRO 要求 `l2_len` 包括外层 UDP 头部、VXLAN 头部和内层以太网头部。
+fn vxlan_layout(mbuf: &Mbuf) -> Option<(u32, TxOffloadLens)> {
+    let layout = mbuf.tunnel().ok()??;
+    if !matches!(layout.tunnel, Tunnel::Vxlan { .. }) {
+        return None;
+    }
+    let eth = mbuf.inner_ether().ok()??;
+    if eth.ether_type() as u32 != constants::RTE_ETHER_TYPE_IPV4 {
+        return None;
+    }
+    let inner_l2_len = eth.header_len();
+    let inner_l2 = match inner_l2_len {
+        0..=14 => constants::RTE_PTYPE_INNER_L2_ETHER,
+        15..=18 => constants::RTE_PTYPE_INNER_L2_ETHER_VLAN,
+        _ => constants::RTE_PTYPE_INNER_L2_ETHER_QINQ,
+    };
+    // 内层数据包可以跨越多个段，只检查第一个段中的头部。
+    let ip = Ipv4Hdr::header_only(eth.into_payload()).ok()?;
+    let l3_len = ip.header_len();
+    let inner_l3 = if l3_len == Ipv4Hdr::<&[u8]>::MIN_LEN {
+        constants::RTE_PTYPE_INNER_L3_IPV4
+    } else {
+        constants::RTE_PTYPE_INNER_L3_IPV4_EXT
+    };
+    let fragment = ip.is_fragment();
+    let (inner_l4, l4_len) = match ip.next_proto_id() as u32 {
+        constants::IPPROTO_TCP if !fragment => (
+            constants::RTE_PTYPE_INNER_L4_TCP,
+            TcpHdr::new(ip.into_payload()).ok()?.header_len(),
+        ),
+        // UDP 合并的对象是 IP 分片，后续分片没有 UDP 头部。
+        constants::IPPROTO_UDP if fragment => (constants::RTE_PTYPE_INNER_L4_FRAG, 0),
+        constants::IPPROTO_UDP => (constants::RTE_PTYPE_INNER_L4_UDP, UdpHdr::<&[u8]>::LEN),
+        _ => return None,
+    };
+    let ptype = constants::RTE_PTYPE_TUNNEL_VXLAN | inner_l2 | inner_l3 | inner_l4;
+    let lens = TxOffloadLens {
+        l2_len: (layout.tunnel_len + inner_l2_len) as u16,
+        l3_len: l3_len as u16,
+        l4_len: l4_len as u16,
+        outer_l2_len: layout.outer_l2_len as u16,
+        outer_l3_len: layout.outer_l3_len as u16,
+        ..Default::default()
+    };
+    Some((ptype, lens))
+}
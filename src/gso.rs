@@ -0,0 +1,140 @@
+//! 通用分段卸载（GSO，`librte_gso`）
+//!
+//! `GsoContext` 在软件中把超过分段大小的 TCP/UDP 数据包切分为多个段，用于网卡不支持
+//! TSO 或隧道 TSO 的情况。段的负载通过间接 mbuf 引用原数据包，不复制数据：
+//!
+//! ```ignore
+//! let gso = GsoContext::new(pool.clone(), indirect_pool, GsoType::TCP_IPV4, 1514)?;
+//! for mbuf in big_packets {
+//!     gso.segment(mbuf, &mut tx_batch)?;
+//! }
+//! txq.send(&mut tx_batch);
+//! ```
+//!
+//! 需要分段的数据包必须设置 `RTE_MBUF_F_TX_TCP_SEG` 或 `RTE_MBUF_F_TX_UDP_SEG` 标志，
+//! 隧道数据包还要设置对应的 `RTE_MBUF_F_TX_TUNNEL_*` 和 `RTE_MBUF_F_TX_OUTER_IPV4` 标志，
+//! 以及各层头部长度。GSO 只更新段的 IP 总长度、IP 标识和 TCP 序号，不计算校验和，
+//! 数据包应同时请求网卡计算 IP 和 L4 校验和。
+
+use crate::error::{DpdkError, Result};
+use crate::mbuf::{Mbuf, MbufBatch, PktMbufPool};
+use crate::*;
+use bitflags::bitflags;
+
+bitflags! {
+    /// 在软件中分段的数据包类型，取值与对应的发送 offload 能力相同
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct GsoType: u64 {
+        /// TCP/IPv4（`RTE_ETH_TX_OFFLOAD_TCP_TSO`）
+        const TCP_IPV4 = constants::RTE_ETH_TX_OFFLOAD_TCP_TSO;
+        /// UDP/IPv4，按 IP 分片切分（`RTE_ETH_TX_OFFLOAD_UDP_TSO`）
+        const UDP_IPV4 = constants::RTE_ETH_TX_OFFLOAD_UDP_TSO;
+        /// VXLAN 隧道中的 TCP/IPv4 或 UDP/IPv4（`RTE_ETH_TX_OFFLOAD_VXLAN_TNL_TSO`）
+        const VXLAN = constants::RTE_ETH_TX_OFFLOAD_VXLAN_TNL_TSO;
+        /// GRE 隧道中的 TCP/IPv4（`RTE_ETH_TX_OFFLOAD_GRE_TNL_TSO`）
+        const GRE = constants::RTE_ETH_TX_OFFLOAD_GRE_TNL_TSO;
+    }
+}
+
+/// GSO 上下文
+///
+/// 段头部从 `direct` 池分配，负载通过从 `indirect` 池分配的间接 mbuf 引用原数据包。
+/// `indirect` 池的数据区大小可以为 0。
+#[derive(Debug, Clone)]
+pub struct GsoContext {
+    direct: PktMbufPool,
+    indirect: PktMbufPool,
+    types: GsoType,
+    gso_size: u16,
+    ipid_fixed: bool,
+}
+
+impl GsoContext {
+    /// 创建上下文，`gso_size` 是每个段的最大长度，包括以太网头部
+    ///
+    /// `gso_size` 小于 `RTE_GSO_SEG_SIZE_MIN`（只分段 UDP 时为 `RTE_GSO_UDP_SEG_SIZE_MIN`）
+    /// 时返回错误。
+    pub fn new(
+        direct: PktMbufPool,
+        indirect: PktMbufPool,
+        types: GsoType,
+        gso_size: u16,
+    ) -> Result<Self> {
+        if types.is_empty() {
+            return Err(DpdkError::invalid("至少需要一种 GSO 类型"));
+        }
+        let min = if types == GsoType::UDP_IPV4 {
+            constants::RTE_GSO_UDP_SEG_SIZE_MIN
+        } else {
+            constants::RTE_GSO_SEG_SIZE_MIN
+        };
+        if (gso_size as u64) < min {
+            return Err(DpdkError::invalid(format!(
+                "GSO 分段大小 {} 小于最小值 {}",
+                gso_size, min
+            )));
+        }
+        Ok(GsoContext {
+            direct,
+            indirect,
+            types,
+            gso_size,
+            ipid_fixed: false,
+        })
+    }
+
+    /// 所有段使用与原数据包相同的 IPv4 标识，默认每个段的标识依次加 1
+    pub fn ipid_fixed(mut self, fixed: bool) -> Self {
+        self.ipid_fixed = fixed;
+        self
+    }
+
+    pub fn types(&self) -> GsoType {
+        self.types
+    }
+
+    pub fn gso_size(&self) -> u16 {
+        self.gso_size
+    }
+
+    /// 切分一个数据包，结果追加到 `out`，返回追加的数据包数量
+    ///
+    /// 不需要分段或类型不在 `types` 中的数据包原样追加。`out` 容纳不下所有段或
+    /// mbuf 池耗尽时返回错误，数据包被释放，`out` 保持不变。
+    pub fn segment(&self, mbuf: Mbuf, out: &mut MbufBatch) -> Result<usize> {
+        if out.is_full() {
+            return Err(DpdkError::invalid("发送批次已满"));
+        }
+        let ctx = rte_gso_ctx {
+            direct_pool: self.direct.as_ptr(),
+            indirect_pool: self.indirect.as_ptr(),
+            flag: if self.ipid_fixed {
+                constants::RTE_GSO_FLAG_IPID_FIXED
+            } else {
+                0
+            },
+            gso_types: self.types.bits() as u32,
+            gso_size: self.gso_size,
+        };
+        let nb_out = out.free_space().min(u16::MAX as usize) as u16;
+        let pkt = mbuf.into_raw();
+        let ret = unsafe { rte_gso_segment(pkt, &ctx, out.spare_ptr(), nb_out) };
+        match ret {
+            0 => {
+                let _ = out.push(unsafe { Mbuf::from_raw(pkt) });
+                Ok(1)
+            }
+            // 段通过间接 mbuf 持有原数据区的引用。DPDK 20.11 起 `rte_gso_segment` 不再减少
+            // 原数据包的引用计数，由调用者释放，数据区在所有段发送后才真正归还。
+            n if n > 0 => {
+                unsafe { out.commit(n as usize) };
+                drop(unsafe { Mbuf::from_raw(pkt) });
+                Ok(n as usize)
+            }
+            err => {
+                drop(unsafe { Mbuf::from_raw(pkt) });
+                Err(DpdkError::new("rte_gso_segment", -err))
+            }
+        }
+    }
+}
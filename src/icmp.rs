@@ -0,0 +1,192 @@
+//! ICMP 回显应答与 IPv6 邻居发现
+//!
+//! `IcmpResponder` 应答发往本机 IPv4 地址的回显请求；`Icmpv6Responder` 应答 ICMPv6
+//! 回显请求，并实现邻居请求/通告（RFC 4861），为转发的 IPv6 数据包解析下一跳 MAC 地址。
+//! 两者与 `ArpResponder` 一样作为收发循环中的一个阶段使用：
+//!
+//! ```ignore
+//! let mut icmp = IcmpResponder::new(&port)?;
+//! icmp.add_addr(Ipv4Addr::new(192, 168, 1, 1));
+//! let mut icmp6 = Icmpv6Responder::new(&port, pool.clone())?;
+//! icmp6.add_addr("fe80::1".parse()?);
+//! icmp6.announce(&mut tx_batch)?;
+//! loop {
+//!     rxq.recv(&mut rx_batch);
+//!     icmp.process(&mut rx_batch, &mut tx_batch);
+//!     icmp6.process(&mut rx_batch, &mut tx_batch);
+//!     // 转发剩余的数据包 ...
+//!     icmp6.poll(&mut tx_batch);
+//!     txq.send(&mut tx_batch);
+//! }
+//! ```
+//!
+//! 邻居请求发往被请求节点组播地址，端口需要开启混杂模式才能收到。
+//! 报文只在第一个段内解析，多段数据包原样留在批次中。
+
+mod v6;
+
+pub use v6::{Icmpv6Responder, Icmpv6Stats};
+
+use crate::error::Result;
+use crate::ethdev::Port;
+use crate::mbuf::{Mbuf, MbufBatch, RxChecksum};
+use crate::packet::{IcmpHdr, Ipv4Hdr};
+use crate::*;
+use std::net::Ipv4Addr;
+
+/// 应答报文的 TTL
+const REPLY_TTL: u8 = 64;
+
+/// ICMP 处理计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IcmpStats {
+    pub echo_requests: u64,
+    pub echo_replies_sent: u64,
+    /// IP 头部或 ICMP 校验和错误的回显请求
+    pub invalid: u64,
+    /// 数据区被共享、不能原地改写为应答而被丢弃的回显请求
+    pub not_writable: u64,
+    /// 发送批次已满而被丢弃的应答
+    pub tx_dropped: u64,
+}
+
+/// 一个端口上的 ICMP 回显应答器
+pub struct IcmpResponder {
+    port_id: u16,
+    mac: [u8; 6],
+    addrs: Vec<Ipv4Addr>,
+    stats: IcmpStats,
+}
+
+impl IcmpResponder {
+    /// 为端口创建应答器，使用端口的 MAC 地址
+    pub fn new(port: &Port) -> Result<Self> {
+        Ok(Self::with_mac(port.port_id(), port.mac_addr()?))
+    }
+
+    /// 使用指定的 MAC 地址创建应答器
+    pub fn with_mac(port_id: u16, mac: [u8; 6]) -> Self {
+        IcmpResponder {
+            port_id,
+            mac,
+            addrs: Vec::new(),
+            stats: IcmpStats::default(),
+        }
+    }
+
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    pub fn mac(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    /// 添加本机地址
+    pub fn add_addr(&mut self, ip: Ipv4Addr) {
+        if !self.addrs.contains(&ip) {
+            self.addrs.push(ip);
+        }
+    }
+
+    /// 删除本机地址，返回地址是否存在
+    pub fn remove_addr(&mut self, ip: Ipv4Addr) -> bool {
+        let len = self.addrs.len();
+        self.addrs.retain(|addr| *addr != ip);
+        self.addrs.len() != len
+    }
+
+    pub fn addrs(&self) -> &[Ipv4Addr] {
+        &self.addrs
+    }
+
+    /// 地址是否属于本机
+    pub fn owns(&self, ip: Ipv4Addr) -> bool {
+        self.addrs.contains(&ip)
+    }
+
+    pub fn stats(&self) -> IcmpStats {
+        self.stats
+    }
+
+    /// 从 `rx` 中取出发往本机的回显请求，应答放入 `tx`
+    pub fn process(&mut self, rx: &mut MbufBatch, tx: &mut MbufBatch) {
+        rx.retain_map(|mbuf| self.handle(mbuf, tx));
+    }
+
+    /// 处理一个数据包，不是发往本机的回显请求时原样返回
+    ///
+    /// 回显请求被原地改写为应答放入 `tx`，校验和错误或数据区被共享的请求被释放。
+    pub fn handle(&mut self, mut mbuf: Mbuf, tx: &mut MbufBatch) -> Option<Mbuf> {
+        if !self.is_echo_request(&mbuf) {
+            return Some(mbuf);
+        }
+        self.stats.echo_requests += 1;
+        let rx_ip_checksum = mbuf.rx_ip_checksum();
+        let valid = mbuf
+            .ether()
+            .and_then(|eth| Ipv4Hdr::new(eth.into_payload()))
+            .map(|ip| {
+                // 网卡已经检查过 IP 头部校验和时直接使用其结果
+                let ip_valid = match rx_ip_checksum {
+                    RxChecksum::Good => true,
+                    RxChecksum::Bad => false,
+                    _ => ip.checksum_valid(),
+                };
+                ip_valid
+                    && IcmpHdr::new(ip.into_payload())
+                        .map(|icmp| icmp.checksum_valid())
+                        .unwrap_or(false)
+            })
+            .unwrap_or(false);
+        if !valid {
+            self.stats.invalid += 1;
+            return None;
+        }
+        if !mbuf.is_writable() {
+            self.stats.not_writable += 1;
+            return None;
+        }
+        let mac = self.mac;
+        if let Ok(mut eth) = mbuf.ether_mut() {
+            eth.swap_addrs();
+            eth.set_src_addr(mac);
+            if let Ok(mut ip) = Ipv4Hdr::new(eth.into_payload_mut()) {
+                ip.swap_addrs();
+                ip.set_ttl(REPLY_TTL);
+                ip.update_checksum();
+                if let Ok(mut icmp) = IcmpHdr::new(ip.into_payload_mut()) {
+                    icmp.set_icmp_type(constants::RTE_IP_ICMP_ECHO_REPLY as u8);
+                    icmp.update_checksum();
+                }
+            }
+        }
+        self.stats.echo_replies_sent += 1;
+        if tx.push(mbuf).is_err() {
+            self.stats.tx_dropped += 1;
+        }
+        None
+    }
+
+    /// 是否是发往本机地址、未分片的 ICMP 回显请求
+    fn is_echo_request(&self, mbuf: &Mbuf) -> bool {
+        let Ok(eth) = mbuf.ether() else {
+            return false;
+        };
+        if eth.ether_type() as u32 != constants::RTE_ETHER_TYPE_IPV4 {
+            return false;
+        }
+        let Ok(ip) = Ipv4Hdr::new(eth.into_payload()) else {
+            return false;
+        };
+        if ip.next_proto_id() as u32 != constants::IPPROTO_ICMP
+            || ip.is_fragment()
+            || !self.owns(ip.dst_addr())
+        {
+            return false;
+        }
+        IcmpHdr::new(ip.into_payload())
+            .map(|icmp| icmp.is_echo_request())
+            .unwrap_or(false)
+    }
+}
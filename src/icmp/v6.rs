@@ -0,0 +1,394 @@
+//! ICMPv6 回显应答与邻居发现
+
+use super::REPLY_TTL;
+use crate::arp::{NeighborTable, Resolution};
+use crate::error::Result;
+use crate::ethdev::Port;
+use crate::mbuf::{Mbuf, MbufBatch, PktMbufPool};
+use crate::packet::{
+    IcmpHdr, Ipv6Hdr, NdMsg, PacketBuilder, ICMPV6_ECHO_REPLY, ICMPV6_ECHO_REQUEST,
+    ICMPV6_NEIGHBOR_ADVERT, ICMPV6_NEIGHBOR_SOLICIT,
+};
+use crate::*;
+use std::net::Ipv6Addr;
+use std::time::Instant;
+
+/// 邻居发现报文的跳数限制必须为 255，保证报文来自本链路（RFC 4861 7.1）
+const ND_HOP_LIMIT: u8 = 255;
+
+const ALL_NODES: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+/// ICMPv6 处理计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Icmpv6Stats {
+    pub echo_requests: u64,
+    pub echo_replies_sent: u64,
+    pub solicits_received: u64,
+    pub adverts_received: u64,
+    pub solicits_sent: u64,
+    pub adverts_sent: u64,
+    /// 校验和错误或格式错误的报文
+    pub invalid: u64,
+    /// 等待解析时被丢弃的数据包：排队已满或解析失败
+    pub pending_dropped: u64,
+    /// 数据区被共享、不能原地改写而被丢弃的数据包：回显请求或解析完成的排队数据包
+    pub not_writable: u64,
+    /// 发送批次已满而被丢弃的数据包
+    pub tx_dropped: u64,
+    /// 分配 mbuf 失败的次数
+    pub alloc_failed: u64,
+}
+
+/// 解析出的 ICMPv6 报文
+enum Message {
+    EchoRequest,
+    Solicit {
+        target: Ipv6Addr,
+        ll_addr: Option<[u8; 6]>,
+    },
+    Advert {
+        target: Ipv6Addr,
+        ll_addr: Option<[u8; 6]>,
+    },
+    Invalid,
+}
+
+/// 一个端口上的 ICMPv6 应答器和 IPv6 邻居表
+pub struct Icmpv6Responder {
+    port_id: u16,
+    mac: [u8; 6],
+    addrs: Vec<Ipv6Addr>,
+    neighbors: NeighborTable<Ipv6Addr>,
+    pool: PktMbufPool,
+    stats: Icmpv6Stats,
+}
+
+impl Icmpv6Responder {
+    /// 为端口创建应答器，使用端口的 MAC 地址，邻居发现报文从 `pool` 分配
+    pub fn new(port: &Port, pool: PktMbufPool) -> Result<Self> {
+        Ok(Self::with_mac(port.port_id(), port.mac_addr()?, pool))
+    }
+
+    /// 使用指定的 MAC 地址创建应答器
+    pub fn with_mac(port_id: u16, mac: [u8; 6], pool: PktMbufPool) -> Self {
+        Icmpv6Responder {
+            port_id,
+            mac,
+            addrs: Vec::new(),
+            neighbors: NeighborTable::new(),
+            pool,
+            stats: Icmpv6Stats::default(),
+        }
+    }
+
+    /// 替换邻居表，用于调整老化和重发参数
+    pub fn neighbor_table(mut self, neighbors: NeighborTable<Ipv6Addr>) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+
+    pub fn port_id(&self) -> u16 {
+        self.port_id
+    }
+
+    pub fn mac(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    /// 添加本机地址，发出的邻居请求使用第一个地址作为源地址
+    pub fn add_addr(&mut self, ip: Ipv6Addr) {
+        if !self.addrs.contains(&ip) {
+            self.addrs.push(ip);
+        }
+    }
+
+    /// 删除本机地址，返回地址是否存在
+    pub fn remove_addr(&mut self, ip: Ipv6Addr) -> bool {
+        let len = self.addrs.len();
+        self.addrs.retain(|addr| *addr != ip);
+        self.addrs.len() != len
+    }
+
+    pub fn addrs(&self) -> &[Ipv6Addr] {
+        &self.addrs
+    }
+
+    /// 地址是否属于本机
+    pub fn owns(&self, ip: Ipv6Addr) -> bool {
+        self.addrs.contains(&ip)
+    }
+
+    pub fn neighbors(&self) -> &NeighborTable<Ipv6Addr> {
+        &self.neighbors
+    }
+
+    pub fn neighbors_mut(&mut self) -> &mut NeighborTable<Ipv6Addr> {
+        &mut self.neighbors
+    }
+
+    pub fn stats(&self) -> Icmpv6Stats {
+        self.stats
+    }
+
+    /// 从 `rx` 中取出并处理发往本机的回显请求和所有邻居发现报文，
+    /// 产生的应答和被释放的排队数据包放入 `tx`
+    pub fn process(&mut self, rx: &mut MbufBatch, tx: &mut MbufBatch) {
+        rx.retain_map(|mbuf| self.handle(mbuf, tx));
+    }
+
+    /// 处理一个数据包，不是发往本机的回显请求或邻居发现报文时原样返回
+    ///
+    /// 回显请求被原地改写为应答放入 `tx`。针对本机地址的邻居请求以邻居通告应答，
+    /// 并学习请求方的 MAC 地址；邻居通告更新邻居表，释放等待该地址的数据包。
+    pub fn handle(&mut self, mbuf: Mbuf, tx: &mut MbufBatch) -> Option<Mbuf> {
+        let Some((src, src_mac, msg)) = self.parse(&mbuf) else {
+            return Some(mbuf);
+        };
+        match msg {
+            Message::EchoRequest => {
+                self.stats.echo_requests += 1;
+                self.reply_echo(mbuf, tx);
+            }
+            Message::Solicit { target, ll_addr } => {
+                self.stats.solicits_received += 1;
+                self.on_solicit(src, src_mac, target, ll_addr, tx);
+            }
+            Message::Advert { target, ll_addr } => {
+                self.stats.adverts_received += 1;
+                // 只更新已有的表项，未请求过的通告不新建表项（RFC 4861 7.2.5）。
+                if let Some(mac) = ll_addr {
+                    if self.neighbors.contains(target) {
+                        self.learn(target, mac, tx);
+                    }
+                }
+            }
+            Message::Invalid => self.stats.invalid += 1,
+        }
+        None
+    }
+
+    /// 把数据包发往下一跳
+    ///
+    /// 数据包必须以以太网头部开头并已设置以太网类型。下一跳的 MAC 地址已知时填写
+    /// 以太网地址并放入 `tx`，否则排队并在需要时发送邻居请求，解析完成后由 `handle`
+    /// 放入发送批次。
+    pub fn send_to(&mut self, next_hop: Ipv6Addr, mbuf: Mbuf, tx: &mut MbufBatch) {
+        match self.neighbors.resolve(next_hop, mbuf, Instant::now()) {
+            Resolution::Resolved(mac, mbuf) => self.send_resolved(mbuf, mac, tx),
+            Resolution::Request => self.send_solicit(next_hop, tx),
+            Resolution::Queued => {}
+            Resolution::Dropped => self.stats.pending_dropped += 1,
+        }
+    }
+
+    /// 为所有本机地址向全节点组播地址发送非请求的邻居通告
+    pub fn announce(&mut self, tx: &mut MbufBatch) -> Result<()> {
+        for i in 0..self.addrs.len() {
+            let ip = self.addrs[i];
+            let mbuf = self.build_nd(multicast_mac(ALL_NODES), ip, ALL_NODES, |buf| {
+                NdMsg::init_advert(buf, ip, self.mac, false, true).map(|_| ())
+            })?;
+            self.stats.adverts_sent += 1;
+            self.push_tx(mbuf, tx);
+        }
+        Ok(())
+    }
+
+    /// 驱动邻居表的老化和请求重发，应在收发循环中定期调用
+    pub fn poll(&mut self, tx: &mut MbufBatch) {
+        let expired = self.neighbors.expire(Instant::now());
+        self.stats.pending_dropped += expired.dropped as u64;
+        for ip in expired.retransmit {
+            self.send_solicit(ip, tx);
+        }
+    }
+
+    /// 解析 ICMPv6 报文，不需要本应答器处理时返回 `None`
+    fn parse(&self, mbuf: &Mbuf) -> Option<(Ipv6Addr, [u8; 6], Message)> {
+        let eth = mbuf.ether().ok()?;
+        if eth.ether_type() as u32 != constants::RTE_ETHER_TYPE_IPV6 {
+            return None;
+        }
+        let src_mac = eth.src_addr();
+        let ip = Ipv6Hdr::new(eth.into_payload()).ok()?;
+        if ip.ext_headers().any(|ext| ext.is_fragment()) {
+            return None;
+        }
+        let (proto, offset) = ip.upper_layer().ok()?;
+        if proto as u32 != constants::IPPROTO_ICMPV6 {
+            return None;
+        }
+        let l4 = &ip.payload()[offset..];
+        let icmp = IcmpHdr::new(l4).ok()?;
+        let msg = match icmp.icmp_type() {
+            // 带扩展头部的回显请求不应答，避免把路由头部等原样带回。
+            ICMPV6_ECHO_REQUEST
+                if icmp.is_echo_request_v6() && offset == 0 && self.owns(ip.dst_addr()) =>
+            {
+                Message::EchoRequest
+            }
+            ICMPV6_NEIGHBOR_SOLICIT | ICMPV6_NEIGHBOR_ADVERT => match NdMsg::new(l4) {
+                Ok(nd) if ip.hop_limits() == ND_HOP_LIMIT => {
+                    let (target, ll_addr) = (nd.target(), nd.ll_addr());
+                    if nd.is_solicit() {
+                        Message::Solicit { target, ll_addr }
+                    } else {
+                        Message::Advert { target, ll_addr }
+                    }
+                }
+                _ => Message::Invalid,
+            },
+            _ => return None,
+        };
+        let msg = match ip.l4_checksum_valid() {
+            Ok(true) => msg,
+            _ => Message::Invalid,
+        };
+        Some((ip.src_addr(), src_mac, msg))
+    }
+
+    fn reply_echo(&mut self, mut mbuf: Mbuf, tx: &mut MbufBatch) {
+        if !mbuf.is_writable() {
+            self.stats.not_writable += 1;
+            return;
+        }
+        let mac = self.mac;
+        if let Ok(mut eth) = mbuf.ether_mut() {
+            eth.swap_addrs();
+            eth.set_src_addr(mac);
+            if let Ok(mut ip) = Ipv6Hdr::new(eth.into_payload_mut()) {
+                ip.swap_addrs();
+                ip.set_hop_limits(REPLY_TTL);
+                if let Ok(mut icmp) = IcmpHdr::new(ip.payload_mut()) {
+                    icmp.set_icmp_type(ICMPV6_ECHO_REPLY);
+                }
+                if ip.update_l4_checksum().is_err() {
+                    return;
+                }
+            }
+        }
+        self.stats.echo_replies_sent += 1;
+        self.push_tx(mbuf, tx);
+    }
+
+    /// 应答针对本机地址的邻居请求（RFC 4861 7.2.3）
+    fn on_solicit(
+        &mut self,
+        src: Ipv6Addr,
+        src_mac: [u8; 6],
+        target: Ipv6Addr,
+        ll_addr: Option<[u8; 6]>,
+        tx: &mut MbufBatch,
+    ) {
+        if !self.owns(target) {
+            return;
+        }
+        // 源地址为未指定地址的请求来自重复地址检测，应答发往全节点组播地址，不置 S 标志。
+        let (dst, dst_mac, solicited) = if src.is_unspecified() {
+            (ALL_NODES, multicast_mac(ALL_NODES), false)
+        } else {
+            if let Some(mac) = ll_addr {
+                self.learn(src, mac, tx);
+            }
+            (src, ll_addr.unwrap_or(src_mac), true)
+        };
+        let mac = self.mac;
+        let built = self.build_nd(dst_mac, target, dst, |buf| {
+            NdMsg::init_advert(buf, target, mac, solicited, true).map(|_| ())
+        });
+        match built {
+            Ok(mbuf) => {
+                self.stats.adverts_sent += 1;
+                self.push_tx(mbuf, tx);
+            }
+            Err(_) => self.stats.alloc_failed += 1,
+        }
+    }
+
+    /// 记录邻居的 MAC 地址，发送等待该地址的数据包
+    fn learn(&mut self, ip: Ipv6Addr, mac: [u8; 6], tx: &mut MbufBatch) {
+        let pending = self.neighbors.confirm(ip, mac, Instant::now());
+        for queued in pending {
+            self.send_resolved(queued, mac, tx);
+        }
+    }
+
+    /// 向目标的被请求节点组播地址发送邻居请求
+    fn send_solicit(&mut self, target: Ipv6Addr, tx: &mut MbufBatch) {
+        // 没有本机地址时无法发送带源链路层地址选项的请求，排队的数据包随表项老化释放。
+        let Some(&src) = self.addrs.first() else {
+            return;
+        };
+        let dst = solicited_node(target);
+        let mac = self.mac;
+        let built = self.build_nd(multicast_mac(dst), src, dst, |buf| {
+            NdMsg::init_solicit(buf, target, mac).map(|_| ())
+        });
+        match built {
+            Ok(mbuf) => {
+                self.stats.solicits_sent += 1;
+                self.push_tx(mbuf, tx);
+            }
+            Err(_) => self.stats.alloc_failed += 1,
+        }
+    }
+
+    fn send_resolved(&mut self, mut mbuf: Mbuf, dst_mac: [u8; 6], tx: &mut MbufBatch) {
+        if !mbuf.is_writable() {
+            self.stats.not_writable += 1;
+            return;
+        }
+        match mbuf.ether_mut() {
+            Ok(mut eth) => {
+                eth.set_dst_addr(dst_mac);
+                eth.set_src_addr(self.mac);
+            }
+            Err(_) => {
+                self.stats.pending_dropped += 1;
+                return;
+            }
+        }
+        self.push_tx(mbuf, tx);
+    }
+
+    fn push_tx(&mut self, mbuf: Mbuf, tx: &mut MbufBatch) {
+        if tx.push(mbuf).is_err() {
+            self.stats.tx_dropped += 1;
+        }
+    }
+
+    /// 构造邻居发现报文，`init` 在 ICMPv6 部分写入报文内容
+    fn build_nd(
+        &self,
+        dst_mac: [u8; 6],
+        src: Ipv6Addr,
+        dst: Ipv6Addr,
+        init: impl FnOnce(&mut [u8]) -> Result<()>,
+    ) -> Result<Mbuf> {
+        let body_len = NdMsg::<&[u8]>::LEN_WITH_LL_ADDR - IcmpHdr::<&[u8]>::LEN;
+        let mut mbuf = PacketBuilder::new(self.mac, dst_mac)
+            .ipv6(src, dst)
+            .ttl(ND_HOP_LIMIT)
+            .icmp(0, 0, 0, 0)
+            .payload(vec![0u8; body_len])
+            .build(&self.pool)?;
+        let mut ip = Ipv6Hdr::new(mbuf.ether_mut()?.into_payload_mut())?;
+        init(ip.payload_mut())?;
+        ip.update_l4_checksum()?;
+        Ok(mbuf)
+    }
+}
+
+/// 被请求节点组播地址 ff02::1:ffXX:XXXX
+fn solicited_node(ip: Ipv6Addr) -> Ipv6Addr {
+    let o = ip.octets();
+    Ipv6Addr::from([
+        0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xff, o[13], o[14], o[15],
+    ])
+}
+
+/// IPv6 组播地址对应的以太网地址 33:33:XX:XX:XX:XX
+fn multicast_mac(ip: Ipv6Addr) -> [u8; 6] {
+    let o = ip.octets();
+    [0x33, 0x33, o[12], o[13], o[14], o[15]]
+}
@@ -0,0 +1,162 @@
+//! IP 分片与重组（`librte_ip_frag`）
+//!
+//! `Fragmenter` 把超过 MTU 的 IPv4/IPv6 数据包切分为分片，`Reassembler` 把收到的分片
+//! 重组为完整的数据包。两者都处理以以太网头部开头的帧：分片时以太网头部被复制到每个分片，
+//! 重组时保留第一个分片的以太网头部。
+//!
+//! ```ignore
+//! let frag = Fragmenter::new(pool.clone(), indirect_pool, 1500);
+//! for mbuf in big_packets {
+//!     frag.fragment(mbuf, &mut tx_batch)?;
+//! }
+//!
+//! let mut reasm = Reassembler::builder().timeout(Duration::from_secs(2)).build()?;
+//! rxq.recv(&mut rx_batch);
+//! reasm.process(&mut rx_batch);
+//! ```
+
+mod reassembly;
+
+pub use reassembly::{Reassembler, ReassemblerBuilder, ReassemblyStats};
+
+use crate::error::{check_ret, DpdkError, Result};
+use crate::mbuf::{Mbuf, MbufBatch, PktMbufPool, TxOffloadLens};
+use crate::packet::{EtherHdr, Ipv6Hdr};
+use crate::*;
+
+/// 以太网头部加两层 VLAN 标签的最大长度
+const MAX_L2_LEN: usize = EtherHdr::<&[u8]>::LEN + 8;
+
+/// IPv4 头部中校验和字段的偏移
+const IPV4_CKSUM: usize = 10;
+
+/// IP 分片器
+///
+/// 分片头部从 `direct` 池分配，负载通过从 `indirect` 池分配的间接 mbuf 引用原数据包，
+/// 不复制数据。`indirect` 池的数据区大小可以为 0。
+#[derive(Debug, Clone)]
+pub struct Fragmenter {
+    direct: PktMbufPool,
+    indirect: PktMbufPool,
+    mtu: u16,
+}
+
+impl Fragmenter {
+    /// 创建分片器，`mtu` 是分片后 IP 数据包的最大长度，不含以太网头部
+    pub fn new(direct: PktMbufPool, indirect: PktMbufPool, mtu: u16) -> Self {
+        Fragmenter {
+            direct,
+            indirect,
+            mtu,
+        }
+    }
+
+    pub fn mtu(&self) -> u16 {
+        self.mtu
+    }
+
+    /// 按 MTU 切分一个以太网帧，结果追加到 `out`，返回追加的数据包数量
+    ///
+    /// 不超过 MTU 的数据包原样追加。分片的 IPv4 头部校验和由软件计算，
+    /// `l2_len`/`l3_len` 已设置；L4 校验和必须在分片之前计算好。
+    /// 设置了 DF 标志的 IPv4 数据包返回 `ENOTSUP`，`out` 容纳不下所有分片时返回 `EINVAL`。
+    /// 出错时数据包被释放，`out` 保持不变。
+    pub fn fragment(&self, mut mbuf: Mbuf, out: &mut MbufBatch) -> Result<usize> {
+        let (l2_len, ether_type) = {
+            let eth = mbuf.ether()?;
+            (eth.header_len(), eth.ether_type() as u32)
+        };
+        let ipv4 = match ether_type {
+            constants::RTE_ETHER_TYPE_IPV4 => true,
+            constants::RTE_ETHER_TYPE_IPV6 => false,
+            _ => {
+                return Err(DpdkError::invalid(format!(
+                    "以太网类型 {:#06x} 不是 IPv4 或 IPv6，不能分片",
+                    ether_type
+                )))
+            }
+        };
+        if mbuf.len() - l2_len <= self.mtu as usize {
+            out.push(mbuf)
+                .map_err(|_| DpdkError::invalid("发送批次已满"))?;
+            return Ok(1);
+        }
+
+        // DPDK 的分片接口要求数据包从 IP 头部开始，以太网头部之后再补回每个分片。
+        let mut l2 = [0u8; MAX_L2_LEN];
+        l2[..l2_len].copy_from_slice(&mbuf.data()[..l2_len]);
+        mbuf.adj(l2_len)?;
+        let nb_out = out.free_space().min(u16::MAX as usize) as u16;
+        let (op, ret) = unsafe {
+            if ipv4 {
+                (
+                    "rte_ipv4_fragment_packet",
+                    rte_ipv4_fragment_packet(
+                        mbuf.as_ptr(),
+                        out.spare_ptr(),
+                        nb_out,
+                        self.mtu,
+                        self.direct.as_ptr(),
+                        self.indirect.as_ptr(),
+                    ),
+                )
+            } else {
+                (
+                    "rte_ipv6_fragment_packet",
+                    rte_ipv6_fragment_packet(
+                        mbuf.as_ptr(),
+                        out.spare_ptr(),
+                        nb_out,
+                        self.mtu,
+                        self.direct.as_ptr(),
+                        self.indirect.as_ptr(),
+                    ),
+                )
+            }
+        };
+        let n = check_ret(op, ret)? as usize;
+        // 分片通过间接 mbuf 持有原数据区的引用，原数据包在函数返回时释放。
+        unsafe { out.commit(n) };
+
+        let start = out.len() - n;
+        for i in start..out.len() {
+            if let Err(e) = finish_fragment(&mut out[i], &l2[..l2_len], ipv4) {
+                while out.len() > start {
+                    out.pop();
+                }
+                return Err(e);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// 为分片补上以太网头部，设置头部长度并计算 IPv4 头部校验和
+fn finish_fragment(frag: &mut Mbuf, l2: &[u8], ipv4: bool) -> Result<()> {
+    frag.prepend(l2.len())?.copy_from_slice(l2);
+    let l3_len = if ipv4 {
+        let hdr = &mut frag.data_mut()[l2.len()..];
+        let l3_len = (hdr[0] & constants::RTE_IPV4_HDR_IHL_MASK as u8) as usize
+            * constants::RTE_IPV4_IHL_MULTIPLIER as usize;
+        update_ipv4_checksum(hdr);
+        l3_len
+    } else {
+        Ipv6Hdr::<&[u8]>::LEN + constants::RTE_IPV6_FRAG_HDR_SIZE as usize
+    };
+    frag.set_tx_offload_lens(TxOffloadLens {
+        l2_len: l2.len() as u16,
+        l3_len: l3_len as u16,
+        ..Default::default()
+    })
+}
+
+/// 重新计算从 `hdr` 开始的 IPv4 头部校验和
+///
+/// 分片和重组后的数据包由多个段组成，`Ipv4Hdr` 视图要求整个数据包位于第一个段内，
+/// 因此这里直接在头部字节上计算。
+fn update_ipv4_checksum(hdr: &mut [u8]) {
+    hdr[IPV4_CKSUM..IPV4_CKSUM + 2].fill(0);
+    let cksum = unsafe { rte_ipv4_cksum(hdr.as_ptr() as *const rte_ipv4_hdr) };
+    // `rte_ipv4_cksum` 的结果按内存中的字节顺序计算，原样写回即为网络字节序。
+    hdr[IPV4_CKSUM..IPV4_CKSUM + 2].copy_from_slice(&cksum.to_ne_bytes());
+}
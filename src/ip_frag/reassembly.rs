@@ -0,0 +1,272 @@
+//! IP 分片重组表
+
+use super::update_ipv4_checksum;
+use crate::error::{check_ptr, DpdkError, Result};
+use crate::mbuf::{Mbuf, MbufBatch};
+use crate::packet::{Ipv4Hdr, Ipv6Hdr};
+use crate::*;
+use std::fmt;
+use std::mem;
+use std::ptr::NonNull;
+use std::time::Duration;
+
+/// 释放 death row 时的预取距离，与 DPDK 示例一致
+const DEATH_ROW_PREFETCH: u32 = 3;
+
+/// 重组计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReassemblyStats {
+    /// 收到的分片
+    pub fragments: u64,
+    /// 重组完成的数据包
+    pub reassembled: u64,
+    /// 因超时、表满或分片无效而被释放的 mbuf
+    pub dropped: u64,
+}
+
+/// 重组器构建器
+#[derive(Debug, Clone)]
+pub struct ReassemblerBuilder {
+    buckets: u32,
+    bucket_entries: u32,
+    max_entries: u32,
+    timeout: Duration,
+    socket_id: i32,
+}
+
+impl ReassemblerBuilder {
+    /// 哈希桶数量，默认 4096
+    pub fn buckets(mut self, buckets: u32) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
+    /// 每个桶的表项数，必须是 2 的幂，默认 16
+    pub fn bucket_entries(mut self, entries: u32) -> Self {
+        self.bucket_entries = entries;
+        self
+    }
+
+    /// 同时重组的数据包数量上限，不能超过 `buckets * bucket_entries`，默认 4096
+    pub fn max_entries(mut self, entries: u32) -> Self {
+        self.max_entries = entries;
+        self
+    }
+
+    /// 从第一个分片到达起等待其余分片的时间，超时后已收到的分片被释放，默认 1 秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 分配内存的 NUMA socket，默认为 `SOCKET_ID_ANY`
+    pub fn socket_id(mut self, socket_id: i32) -> Self {
+        self.socket_id = socket_id;
+        self
+    }
+
+    /// 调用 `rte_ip_frag_table_create` 创建重组表
+    pub fn build(self) -> Result<Reassembler> {
+        if !self.bucket_entries.is_power_of_two() {
+            return Err(DpdkError::invalid(format!(
+                "每个桶的表项数 {} 不是 2 的幂",
+                self.bucket_entries
+            )));
+        }
+        let capacity = self.buckets as u64 * self.bucket_entries as u64;
+        if self.max_entries == 0 || self.max_entries as u64 > capacity {
+            return Err(DpdkError::invalid(format!(
+                "最大表项数 {} 必须在 1 到 {} 之间",
+                self.max_entries, capacity
+            )));
+        }
+        let hz = unsafe { rte_get_tsc_hz() };
+        let max_cycles = (hz as u128 * self.timeout.as_nanos() / 1_000_000_000) as u64;
+        let tbl = unsafe {
+            rte_ip_frag_table_create(
+                self.buckets,
+                self.bucket_entries,
+                self.max_entries,
+                max_cycles,
+                self.socket_id,
+            )
+        };
+        let tbl = check_ptr("rte_ip_frag_table_create", tbl)?;
+        Ok(Reassembler {
+            tbl,
+            death_row: Box::new(unsafe { mem::zeroed() }),
+            timeout: self.timeout,
+            stats: ReassemblyStats::default(),
+        })
+    }
+}
+
+/// IPv4/IPv6 分片重组器
+///
+/// 包装 `rte_ip_frag_tbl`。重组过程中被淘汰的 mbuf 先放入 death row，
+/// 每次调用结束前自动释放。重组表不是线程安全的，每个 lcore 应使用自己的重组器。
+///
+/// IPv6 只支持分片头部紧跟在固定头部之后的数据包，这是 `librte_ip_frag` 的限制。
+pub struct Reassembler {
+    tbl: NonNull<rte_ip_frag_tbl>,
+    death_row: Box<rte_ip_frag_death_row>,
+    timeout: Duration,
+    stats: ReassemblyStats,
+}
+
+unsafe impl Send for Reassembler {}
+
+impl Reassembler {
+    /// 创建构建器
+    pub fn builder() -> ReassemblerBuilder {
+        ReassemblerBuilder {
+            buckets: 4096,
+            bucket_entries: 16,
+            max_entries: 4096,
+            timeout: Duration::from_secs(1),
+            socket_id: SOCKET_ID_ANY,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn stats(&self) -> ReassemblyStats {
+        self.stats
+    }
+
+    /// 把批次中的分片替换为重组完成的数据包
+    ///
+    /// 非分片的数据包保持原来的顺序，重组完成的数据包追加到批次末尾。
+    pub fn process(&mut self, batch: &mut MbufBatch) {
+        let mut done = Vec::new();
+        for mbuf in batch.extract_if(|mbuf| fragment_info(mbuf).is_some()) {
+            if let Some(pkt) = self.reassemble(mbuf) {
+                done.push(pkt);
+            }
+        }
+        // 每个重组完成的数据包至少消耗了一个分片，批次一定放得下。
+        for pkt in done {
+            let _ = batch.push(pkt);
+        }
+    }
+
+    /// 处理一个以太网帧
+    ///
+    /// 不是分片时原样返回；是分片时由重组表接管，收齐所有分片后返回重组完成的数据包。
+    /// 重组后的数据包由多个段组成，IPv4 头部校验和已重新计算，`l2_len`/`l3_len` 已设置。
+    /// 数据区被共享或头部不在第一个段内的分片无法重组，原样返回。
+    pub fn reassemble(&mut self, mut mbuf: Mbuf) -> Option<Mbuf> {
+        let Some((ipv4, l2_len, l3_len)) = fragment_info(&mbuf) else {
+            return Some(mbuf);
+        };
+        let mut lens = mbuf.tx_offload_lens();
+        lens.l2_len = l2_len as u16;
+        lens.l3_len = l3_len as u16;
+        if mbuf.set_tx_offload_lens(lens).is_err() {
+            return Some(mbuf);
+        }
+        self.stats.fragments += 1;
+
+        let ip = mbuf.data_mut()[l2_len..].as_mut_ptr();
+        let raw = mbuf.into_raw();
+        let pkt = unsafe {
+            let tms = rte_rdtsc();
+            if ipv4 {
+                rte_ipv4_frag_reassemble_packet(
+                    self.tbl.as_ptr(),
+                    &mut *self.death_row,
+                    raw,
+                    tms,
+                    ip as *mut rte_ipv4_hdr,
+                )
+            } else {
+                rte_ipv6_frag_reassemble_packet(
+                    self.tbl.as_ptr(),
+                    &mut *self.death_row,
+                    raw,
+                    tms,
+                    ip as *mut rte_ipv6_hdr,
+                    ip.add(Ipv6Hdr::<&[u8]>::LEN) as *mut rte_ipv6_fragment_ext,
+                )
+            }
+        };
+        self.free_death_row();
+        if pkt.is_null() {
+            return None;
+        }
+
+        let mut pkt = unsafe { Mbuf::from_raw(pkt) };
+        if ipv4 {
+            // DPDK 把重组后的头部校验和清零，这里重新计算。
+            update_ipv4_checksum(&mut pkt.data_mut()[l2_len..l2_len + l3_len]);
+        }
+        self.stats.reassembled += 1;
+        Some(pkt)
+    }
+
+    /// 释放所有超时的重组表项，通常在收包较少时定期调用
+    ///
+    /// 超时的表项在下一个同一数据包的分片到达时也会被发现，但没有后续分片时
+    /// 只有这里才会释放它们。
+    pub fn expire(&mut self) {
+        unsafe {
+            rte_ip_frag_table_del_expired_entries(
+                self.tbl.as_ptr(),
+                &mut *self.death_row,
+                rte_rdtsc(),
+            );
+        }
+        self.free_death_row();
+    }
+
+    fn free_death_row(&mut self) {
+        // DPDK 不检查 death row 的容量，因此每次调用之后立即释放。
+        self.stats.dropped += self.death_row.cnt as u64;
+        unsafe { rte_ip_frag_free_death_row(&mut *self.death_row, DEATH_ROW_PREFETCH) };
+    }
+}
+
+impl Drop for Reassembler {
+    fn drop(&mut self) {
+        // 销毁重组表时一并释放表中尚未重组完成的分片。
+        unsafe {
+            rte_ip_frag_free_death_row(&mut *self.death_row, DEATH_ROW_PREFETCH);
+            rte_ip_frag_table_destroy(self.tbl.as_ptr());
+        }
+    }
+}
+
+impl fmt::Debug for Reassembler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reassembler")
+            .field("timeout", &self.timeout)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+/// 可以重组的分片：返回是否为 IPv4、L2 头部长度和 L3 头部长度
+///
+/// IPv6 的 L3 头部长度包括分片头部。
+fn fragment_info(mbuf: &Mbuf) -> Option<(bool, usize, usize)> {
+    if !mbuf.is_writable() {
+        return None;
+    }
+    let eth = mbuf.ether().ok()?;
+    let l2_len = eth.header_len();
+    match eth.ether_type() as u32 {
+        constants::RTE_ETHER_TYPE_IPV4 => {
+            let ip = Ipv4Hdr::new(eth.into_payload()).ok()?;
+            ip.is_fragment().then(|| (true, l2_len, ip.header_len()))
+        }
+        constants::RTE_ETHER_TYPE_IPV6 => {
+            let ip = Ipv6Hdr::new(eth.into_payload()).ok()?;
+            let frag_len = constants::RTE_IPV6_FRAG_HDR_SIZE as usize;
+            (ip.proto() as u32 == constants::IPPROTO_FRAGMENT && ip.payload().len() >= frag_len)
+                .then_some((false, l2_len, Ipv6Hdr::<&[u8]>::LEN + frag_len))
+        }
+        _ => None,
+    }
+}
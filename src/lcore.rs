@@ -0,0 +1,307 @@
+//! lcore 拓扑查询与任务启动
+//!
+//! 通过 EAL 的 lcore 迭代函数（`rte_get_next_lcore`）枚举已启用的 lcore，
+//! 并给出每个 lcore 所在的 NUMA socket、绑定的 CPU 集合以及角色。
+//! 任务通过 `rte_eal_remote_launch` 在 worker lcore 上执行，见 `Eal::launch_on`。
+
+use crate::error::{check_ret, DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::marker::PhantomData;
+use std::os::raw::{c_int, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// lcore 的角色（`rte_lcore_role_t`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LcoreRole {
+    /// 由 EAL 管理的普通 lcore
+    Rte,
+    /// 未启用
+    Off,
+    /// 服务 lcore
+    Service,
+    /// 通过 `rte_thread_register` 注册的非 EAL 线程
+    NonEal,
+}
+
+impl LcoreRole {
+    fn from_raw(role: rte_lcore_role_t) -> Self {
+        if role == rte_lcore_role_t_ROLE_RTE {
+            LcoreRole::Rte
+        } else if role == rte_lcore_role_t_ROLE_SERVICE {
+            LcoreRole::Service
+        } else if role == rte_lcore_role_t_ROLE_NON_EAL {
+            LcoreRole::NonEal
+        } else {
+            LcoreRole::Off
+        }
+    }
+}
+
+/// 单个 lcore 的拓扑信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LcoreInfo {
+    /// lcore ID
+    pub id: u32,
+    /// 所在的 NUMA socket
+    pub socket_id: u32,
+    /// 绑定的 CPU 列表
+    pub cpuset: Vec<usize>,
+    /// lcore 角色
+    pub role: LcoreRole,
+    /// 是否为主 lcore
+    pub is_main: bool,
+}
+
+/// 已启用 lcore 的查询接口
+///
+/// 只能通过 `Eal::lcores` 获得，从而保证 EAL 已经初始化。
+#[derive(Debug, Clone, Copy)]
+pub struct Lcores {
+    _private: (),
+}
+
+impl Lcores {
+    pub(crate) fn new() -> Self {
+        Self { _private: () }
+    }
+
+    /// 主 lcore 的 ID
+    pub fn main(&self) -> u32 {
+        unsafe { rte_get_main_lcore() }
+    }
+
+    /// 已启用的 lcore 数量（包含主 lcore）
+    pub fn count(&self) -> u32 {
+        unsafe { rte_lcore_count() }
+    }
+
+    /// 当前线程所在的 lcore，非 EAL 线程返回 `None`
+    pub fn current(&self) -> Option<u32> {
+        let id = unsafe { rte_lcore_id() };
+        if id < RTE_MAX_LCORE {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// 判断 lcore 是否已启用
+    pub fn is_enabled(&self, lcore_id: u32) -> bool {
+        lcore_id < RTE_MAX_LCORE && unsafe { rte_lcore_is_enabled(lcore_id) } != 0
+    }
+
+    /// 遍历所有已启用的 lcore（包含主 lcore）
+    pub fn iter(&self) -> LcoreIter {
+        LcoreIter::new(false)
+    }
+
+    /// 遍历所有 worker lcore（不包含主 lcore）
+    pub fn workers(&self) -> LcoreIter {
+        LcoreIter::new(true)
+    }
+
+    /// 遍历位于指定 NUMA socket 上的 worker lcore
+    pub fn workers_on_socket(&self, socket_id: u32) -> impl Iterator<Item = u32> {
+        self.workers()
+            .filter(move |&id| unsafe { rte_lcore_to_socket_id(id) } == socket_id)
+    }
+
+    /// 查询 lcore 的拓扑信息，未启用的 lcore 返回 `None`
+    pub fn info(&self, lcore_id: u32) -> Option<LcoreInfo> {
+        if !self.is_enabled(lcore_id) {
+            return None;
+        }
+        let (socket_id, cpuset, role) = unsafe {
+            (
+                rte_lcore_to_socket_id(lcore_id),
+                rte_lcore_cpuset(lcore_id),
+                rte_eal_lcore_role(lcore_id),
+            )
+        };
+        Some(LcoreInfo {
+            id: lcore_id,
+            socket_id,
+            cpuset: cpuset_to_vec(&cpuset),
+            role: LcoreRole::from_raw(role),
+            is_main: lcore_id == self.main(),
+        })
+    }
+
+    /// 所有已启用 lcore 的拓扑信息
+    pub fn infos(&self) -> Vec<LcoreInfo> {
+        self.iter().filter_map(|id| self.info(id)).collect()
+    }
+}
+
+/// 已启用 lcore 的迭代器，按 ID 升序产生 lcore ID
+#[derive(Debug, Clone)]
+pub struct LcoreIter {
+    next: c_uint,
+    skip_main: bool,
+}
+
+impl LcoreIter {
+    fn new(skip_main: bool) -> Self {
+        // `rte_get_next_lcore` 会先对传入值加一，从 -1 开始即可得到第一个 lcore。
+        Self {
+            next: c_uint::MAX,
+            skip_main,
+        }
+    }
+}
+
+impl Iterator for LcoreIter {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let id = unsafe { rte_get_next_lcore(self.next, self.skip_main as c_int, 0) };
+        if id >= RTE_MAX_LCORE {
+            self.next = RTE_MAX_LCORE;
+            return None;
+        }
+        self.next = id;
+        Some(id)
+    }
+}
+
+fn cpuset_to_vec(cpuset: &rte_cpuset_t) -> Vec<usize> {
+    let word_bits = std::mem::size_of_val(&cpuset.__bits[0]) * 8;
+    cpuset
+        .__bits
+        .iter()
+        .enumerate()
+        .flat_map(|(word_index, &word)| {
+            (0..word_bits)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| word_index * word_bits + bit)
+        })
+        .collect()
+}
+
+/// 在 worker lcore 上执行的闭包及其结果
+struct Packet<F, T> {
+    f: Mutex<Option<F>>,
+    result: Mutex<Option<thread::Result<T>>>,
+}
+
+/// `rte_eal_remote_launch` 的入口函数
+///
+/// 闭包中的 panic 在此处被捕获并保存，不会跨越 FFI 边界。
+unsafe extern "C" fn trampoline<F, T>(arg: *mut c_void) -> c_int
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let packet = Arc::from_raw(arg as *const Packet<F, T>);
+    let f = packet.f.lock().unwrap().take();
+    if let Some(f) = f {
+        let result = panic::catch_unwind(AssertUnwindSafe(f));
+        *packet.result.lock().unwrap() = Some(result);
+    }
+    0
+}
+
+/// 在指定的 worker lcore 上启动闭包
+pub(crate) fn launch<F, T>(eal: &Eal, lcore_id: u32, f: F) -> Result<LcoreJoinHandle<'_, T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let lcores = eal.lcores();
+    if !lcores.is_enabled(lcore_id) {
+        return Err(DpdkError::invalid(format!("lcore {} 未启用", lcore_id)));
+    }
+    if lcore_id == lcores.main() {
+        return Err(DpdkError::invalid(format!(
+            "lcore {} 是主 lcore，不能作为 worker 启动",
+            lcore_id
+        )));
+    }
+
+    let packet = Arc::new(Packet {
+        f: Mutex::new(Some(f)),
+        result: Mutex::new(None),
+    });
+    let arg = Arc::into_raw(packet.clone()) as *mut c_void;
+    let ret = unsafe { rte_eal_remote_launch(Some(trampoline::<F, T>), arg, lcore_id) };
+    if let Err(e) = check_ret("rte_eal_remote_launch", ret) {
+        // 启动失败时入口函数不会执行，需要在此回收传出的引用。
+        drop(unsafe { Arc::from_raw(arg as *const Packet<F, T>) });
+        return Err(e);
+    }
+
+    Ok(LcoreJoinHandle {
+        lcore_id,
+        result: Some(packet as Arc<dyn ResultSlot<T>>),
+        _eal: PhantomData,
+    })
+}
+
+/// 对闭包类型擦除后的结果槽
+trait ResultSlot<T>: Send + Sync {
+    fn take(&self) -> Option<thread::Result<T>>;
+    fn is_set(&self) -> bool;
+}
+
+impl<F: Send, T: Send> ResultSlot<T> for Packet<F, T> {
+    fn take(&self) -> Option<thread::Result<T>> {
+        self.result.lock().unwrap().take()
+    }
+
+    fn is_set(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+}
+
+/// worker lcore 上运行的任务句柄
+///
+/// 与 `std::thread::JoinHandle` 类似，`join` 返回闭包的结果，闭包 panic 时返回 `Err`。
+/// `rte_eal_wait_lcore` 只能在主 lcore 上调用，因此句柄不能跨线程传递；
+/// 句柄被丢弃时会等待任务结束，使该 lcore 可以被再次启动。
+pub struct LcoreJoinHandle<'eal, T> {
+    lcore_id: u32,
+    result: Option<Arc<dyn ResultSlot<T>>>,
+    _eal: PhantomData<&'eal Eal>,
+}
+
+impl<T> LcoreJoinHandle<'_, T> {
+    /// 任务所在的 lcore
+    pub fn lcore_id(&self) -> u32 {
+        self.lcore_id
+    }
+
+    /// 任务是否已经结束
+    pub fn is_finished(&self) -> bool {
+        self.result.as_ref().is_none_or(|result| result.is_set())
+    }
+
+    /// 等待任务结束并取回结果
+    pub fn join(mut self) -> thread::Result<T> {
+        self.wait().expect("lcore 任务没有产生结果")
+    }
+
+    fn wait(&mut self) -> Option<thread::Result<T>> {
+        let result = self.result.take()?;
+        unsafe {
+            rte_eal_wait_lcore(self.lcore_id);
+        }
+        result.take()
+    }
+}
+
+impl<T> Drop for LcoreJoinHandle<'_, T> {
+    fn drop(&mut self) {
+        let _ = self.wait();
+    }
+}
+
+impl<T> fmt::Debug for LcoreJoinHandle<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LcoreJoinHandle")
+            .field("lcore_id", &self.lcore_id)
+            .finish()
+    }
+}
@@ -0,0 +1,467 @@
+//! 数据包缓冲区
+//!
+//! `PktMbufPool` 包装 `rte_pktmbuf_pool_create` 创建的 mbuf 内存池，
+//! `Mbuf` 是从池中分配的一个数据包，拥有其所有权，被丢弃时通过 `rte_pktmbuf_free` 归还。
+//! 数据访问和长度调整都在 Rust 侧做边界检查，越界时返回错误而不是破坏 mbuf。
+//!
+//! 巨帧和 scatter 接收会产生由多个段链接而成的 mbuf，`segment` 子模块提供
+//! 按段遍历、链接和线性化，`cursor` 子模块提供跨段的无拷贝头部解析。
+//! `offload` 子模块提供 `ol_flags` 和发送 offload 使用的头部长度。
+
+use crate::error::{check_ptr, check_ret, DpdkError, Result};
+use crate::*;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::mem;
+use std::os::raw::c_uint;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::sync::Arc;
+
+mod batch;
+mod cursor;
+mod offload;
+mod rx;
+mod segment;
+
+pub use batch::MbufBatch;
+pub use cursor::MbufCursor;
+pub use offload::{TxOffloadLens, TxOffloadRequest};
+pub use rx::RxChecksum;
+pub use segment::{Segments, SegmentsMut};
+
+/// mbuf 内存池构建器
+#[derive(Debug, Clone)]
+pub struct PktMbufPoolBuilder {
+    name: String,
+    count: u32,
+    cache_size: u32,
+    priv_size: u16,
+    data_room_size: u16,
+    socket_id: i32,
+}
+
+impl PktMbufPoolBuilder {
+    /// 池中 mbuf 的数量，默认 8191
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// 每个 lcore 的本地缓存大小，默认 250，不能超过 `RTE_MEMPOOL_CACHE_MAX_SIZE`
+    pub fn cache_size(mut self, cache_size: u32) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    /// 每个 mbuf 的私有数据区大小，必须是 `RTE_MBUF_PRIV_ALIGN` 的倍数
+    pub fn priv_size(mut self, priv_size: u16) -> Self {
+        self.priv_size = priv_size;
+        self
+    }
+
+    /// 每个 mbuf 的数据区大小（包含头部预留空间），默认 `RTE_MBUF_DEFAULT_BUF_SIZE`
+    pub fn data_room_size(mut self, data_room_size: u16) -> Self {
+        self.data_room_size = data_room_size;
+        self
+    }
+
+    /// 分配内存的 NUMA socket，默认为 `SOCKET_ID_ANY`
+    pub fn socket_id(mut self, socket_id: i32) -> Self {
+        self.socket_id = socket_id;
+        self
+    }
+
+    /// 调用 `rte_pktmbuf_pool_create` 创建内存池
+    pub fn build(self) -> Result<PktMbufPool> {
+        if self.cache_size > constants::RTE_MEMPOOL_CACHE_MAX_SIZE {
+            return Err(DpdkError::invalid(format!(
+                "缓存大小 {} 超过 RTE_MEMPOOL_CACHE_MAX_SIZE",
+                self.cache_size
+            )));
+        }
+        if !(self.priv_size as u32).is_multiple_of(constants::RTE_MBUF_PRIV_ALIGN) {
+            return Err(DpdkError::invalid(format!(
+                "私有数据区大小 {} 不是 RTE_MBUF_PRIV_ALIGN 的倍数",
+                self.priv_size
+            )));
+        }
+
+        let name = CString::new(self.name.as_str())?;
+        let mp = unsafe {
+            rte_pktmbuf_pool_create(
+                name.as_ptr(),
+                self.count,
+                self.cache_size,
+                self.priv_size,
+                self.data_room_size,
+                self.socket_id,
+            )
+        };
+        let raw = check_ptr("rte_pktmbuf_pool_create", mp)?;
+        Ok(PktMbufPool {
+            inner: Arc::new(RawPool(raw)),
+        })
+    }
+}
+
+/// 持有 `rte_mempool` 指针，最后一个 `PktMbufPool` 句柄被丢弃时释放内存池
+struct RawPool(NonNull<rte_mempool>);
+
+unsafe impl Send for RawPool {}
+unsafe impl Sync for RawPool {}
+
+impl Drop for RawPool {
+    fn drop(&mut self) {
+        // `Mbuf` 不持有内存池的引用，仍有 mbuf 未归还时释放内存池会导致悬垂指针，
+        // 此时宁可泄漏内存池。
+        unsafe {
+            if rte_mempool_in_use_count(self.0.as_ptr()) == 0 {
+                rte_mempool_free(self.0.as_ptr());
+            }
+        }
+    }
+}
+
+/// 存放数据包缓冲区的内存池
+///
+/// 句柄可以廉价地克隆并在线程之间共享，所有句柄都被丢弃后内存池才会释放。
+/// 释放时若仍有 mbuf 未归还（例如仍在网卡队列中），内存池会被泄漏而不是释放。
+#[derive(Clone)]
+pub struct PktMbufPool {
+    inner: Arc<RawPool>,
+}
+
+impl PktMbufPool {
+    /// 创建构建器
+    pub fn builder(name: impl Into<String>) -> PktMbufPoolBuilder {
+        PktMbufPoolBuilder {
+            name: name.into(),
+            count: 8191,
+            cache_size: 250,
+            priv_size: 0,
+            data_room_size: RTE_MBUF_DEFAULT_BUF_SIZE as u16,
+            socket_id: SOCKET_ID_ANY,
+        }
+    }
+
+    /// 分配一个 mbuf
+    pub fn alloc(&self) -> Result<Mbuf> {
+        let m = unsafe { rte_pktmbuf_alloc(self.as_ptr()) };
+        // `rte_pktmbuf_alloc` 失败时不设置 `rte_errno`，唯一的原因是池已耗尽。
+        NonNull::new(m)
+            .map(|raw| Mbuf { raw })
+            .ok_or_else(|| DpdkError::new("rte_pktmbuf_alloc", libc::ENOENT))
+    }
+
+    /// 一次分配 `n` 个 mbuf，数量不足时不分配任何 mbuf
+    pub fn alloc_bulk(&self, n: usize) -> Result<Vec<Mbuf>> {
+        let mut mbufs: Vec<*mut rte_mbuf> = vec![ptr::null_mut(); n];
+        check_ret("rte_pktmbuf_alloc_bulk", unsafe {
+            rte_pktmbuf_alloc_bulk(self.as_ptr(), mbufs.as_mut_ptr(), n as c_uint)
+        })?;
+        Ok(mbufs
+            .into_iter()
+            .map(|m| Mbuf {
+                raw: unsafe { NonNull::new_unchecked(m) },
+            })
+            .collect())
+    }
+
+    /// 池中可用的 mbuf 数量
+    pub fn avail_count(&self) -> u32 {
+        unsafe { rte_mempool_avail_count(self.as_ptr()) }
+    }
+
+    /// 已被分配的 mbuf 数量
+    pub fn in_use_count(&self) -> u32 {
+        unsafe { rte_mempool_in_use_count(self.as_ptr()) }
+    }
+
+    /// 池中 mbuf 的总数
+    pub fn capacity(&self) -> u32 {
+        unsafe { self.inner.0.as_ref().size }
+    }
+
+    /// 每个 mbuf 的数据区大小
+    pub fn data_room_size(&self) -> u16 {
+        unsafe { rte_pktmbuf_data_room_size(self.as_ptr()) }
+    }
+
+    /// 每个 mbuf 的私有数据区大小
+    pub fn priv_size(&self) -> u16 {
+        unsafe { rte_pktmbuf_priv_size(self.as_ptr()) }
+    }
+
+    /// 内存池名称
+    pub fn name(&self) -> String {
+        let name = unsafe { CStr::from_ptr(self.inner.0.as_ref().name.as_ptr()) };
+        name.to_string_lossy().into_owned()
+    }
+
+    /// 底层的 `rte_mempool` 指针
+    pub fn as_ptr(&self) -> *mut rte_mempool {
+        self.inner.0.as_ptr()
+    }
+}
+
+impl fmt::Debug for PktMbufPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PktMbufPool")
+            .field("name", &self.name())
+            .field("avail", &self.avail_count())
+            .field("in_use", &self.in_use_count())
+            .finish()
+    }
+}
+
+/// 拥有所有权的数据包缓冲区
+///
+/// 被丢弃时调用 `rte_pktmbuf_free` 归还到所属的内存池。
+/// 间接 mbuf（`try_clone`/`attach` 得到）与原 mbuf 共享数据区，此时数据区是只读的。
+#[repr(transparent)]
+pub struct Mbuf {
+    raw: NonNull<rte_mbuf>,
+}
+
+unsafe impl Send for Mbuf {}
+unsafe impl Sync for Mbuf {}
+
+impl Mbuf {
+    /// 接管一个原始 mbuf 指针的所有权
+    ///
+    /// # Safety
+    ///
+    /// `raw` 必须是非空的、有效的 mbuf，并且之后不能再通过其他途径释放。
+    pub unsafe fn from_raw(raw: *mut rte_mbuf) -> Self {
+        debug_assert!(!raw.is_null());
+        Mbuf {
+            raw: NonNull::new_unchecked(raw),
+        }
+    }
+
+    /// 交出所有权，返回原始 mbuf 指针，调用者负责释放
+    pub fn into_raw(self) -> *mut rte_mbuf {
+        let raw = self.raw.as_ptr();
+        mem::forget(self);
+        raw
+    }
+
+    /// 底层的 `rte_mbuf` 指针
+    pub fn as_ptr(&self) -> *mut rte_mbuf {
+        self.raw.as_ptr()
+    }
+
+    fn raw(&self) -> &rte_mbuf {
+        unsafe { self.raw.as_ref() }
+    }
+
+    /// 数据包总长度（`pkt_len`）
+    pub fn len(&self) -> usize {
+        self.raw().pkt_len as usize
+    }
+
+    /// 数据包是否为空
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 第一个段中的数据长度（`data_len`）
+    pub fn data_len(&self) -> usize {
+        self.raw().data_len as usize
+    }
+
+    /// 第一个段的数据，多段数据包见 `segments` 和 `cursor`
+    pub fn data(&self) -> &[u8] {
+        seg_data(self.raw())
+    }
+
+    /// 第一个段的可写数据
+    ///
+    /// # Panics
+    ///
+    /// 数据区与其他 mbuf 共享时（见 `is_writable`）会 panic。
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        assert!(self.is_writable(), "mbuf 的数据区被共享，不能修改");
+        unsafe { seg_data_mut(self.raw.as_ptr()) }
+    }
+
+    /// 数据区是否只属于当前 mbuf，可以安全修改
+    pub fn is_writable(&self) -> bool {
+        is_seg_writable(self.raw())
+    }
+
+    /// 第一个段的头部预留空间
+    pub fn headroom(&self) -> usize {
+        unsafe { rte_pktmbuf_headroom(self.as_ptr()) as usize }
+    }
+
+    /// 最后一个段的尾部剩余空间
+    pub fn tailroom(&self) -> usize {
+        unsafe { rte_pktmbuf_tailroom(rte_pktmbuf_lastseg(self.as_ptr())) as usize }
+    }
+
+    /// 在数据末尾追加 `len` 字节，返回新增的区域（内容未初始化为零）
+    pub fn append(&mut self, len: usize) -> Result<&mut [u8]> {
+        let last = unsafe { &*rte_pktmbuf_lastseg(self.as_ptr()) };
+        if !is_seg_writable(last) {
+            return Err(DpdkError::invalid("mbuf 的数据区被共享，不能追加数据"));
+        }
+        if len > self.tailroom() {
+            return Err(DpdkError::invalid(format!(
+                "追加 {} 字节超过尾部剩余空间 {}",
+                len,
+                self.tailroom()
+            )));
+        }
+        let data = unsafe { rte_pktmbuf_append(self.as_ptr(), len as u16) };
+        let data = check_ptr("rte_pktmbuf_append", data)?;
+        Ok(unsafe { slice::from_raw_parts_mut(data.as_ptr() as *mut u8, len) })
+    }
+
+    /// 在数据前面插入 `len` 字节，返回新增的区域（内容未初始化为零）
+    pub fn prepend(&mut self, len: usize) -> Result<&mut [u8]> {
+        if !self.is_writable() {
+            return Err(DpdkError::invalid("mbuf 的数据区被共享，不能插入数据"));
+        }
+        if len > self.headroom() {
+            return Err(DpdkError::invalid(format!(
+                "插入 {} 字节超过头部预留空间 {}",
+                len,
+                self.headroom()
+            )));
+        }
+        let data = unsafe { rte_pktmbuf_prepend(self.as_ptr(), len as u16) };
+        let data = check_ptr("rte_pktmbuf_prepend", data)?;
+        Ok(unsafe { slice::from_raw_parts_mut(data.as_ptr() as *mut u8, len) })
+    }
+
+    /// 从数据开头移除 `len` 字节，只能在第一个段内移除
+    pub fn adj(&mut self, len: usize) -> Result<()> {
+        if len > self.data_len() {
+            return Err(DpdkError::invalid(format!(
+                "移除 {} 字节超过第一个段的数据长度 {}",
+                len,
+                self.data_len()
+            )));
+        }
+        let data = unsafe { rte_pktmbuf_adj(self.as_ptr(), len as u16) };
+        check_ptr("rte_pktmbuf_adj", data)?;
+        Ok(())
+    }
+
+    /// 从数据末尾移除 `len` 字节，只能在最后一个段内移除
+    pub fn trim(&mut self, len: usize) -> Result<()> {
+        let last_len = unsafe { (*rte_pktmbuf_lastseg(self.as_ptr())).data_len as usize };
+        if len > last_len {
+            return Err(DpdkError::invalid(format!(
+                "移除 {} 字节超过最后一个段的数据长度 {}",
+                len, last_len
+            )));
+        }
+        check_ret("rte_pktmbuf_trim", unsafe {
+            rte_pktmbuf_trim(self.as_ptr(), len as u16)
+        })?;
+        Ok(())
+    }
+
+    /// 引用计数
+    pub fn refcnt(&self) -> u16 {
+        unsafe { rte_mbuf_refcnt_read(self.as_ptr()) }
+    }
+
+    /// 是否为间接 mbuf（数据区属于另一个 mbuf）
+    pub fn is_indirect(&self) -> bool {
+        self.raw().ol_flags & constants::RTE_MBUF_F_INDIRECT != 0
+    }
+
+    /// 接收该数据包的端口
+    pub fn port(&self) -> u16 {
+        self.raw().port
+    }
+
+    /// 从 `pool` 分配间接 mbuf，与当前数据包共享数据区（`rte_pktmbuf_clone`）
+    ///
+    /// 共享期间两个 mbuf 的数据区都是只读的。
+    pub fn try_clone(&self, pool: &PktMbufPool) -> Result<Mbuf> {
+        let m = unsafe { rte_pktmbuf_clone(self.as_ptr(), pool.as_ptr()) };
+        NonNull::new(m)
+            .map(|raw| Mbuf { raw })
+            .ok_or_else(|| DpdkError::new("rte_pktmbuf_clone", libc::ENOENT))
+    }
+
+    /// 让当前 mbuf 成为 `direct` 的间接 mbuf，共享其数据区（`rte_pktmbuf_attach`）
+    ///
+    /// 当前 mbuf 必须是未被共享的单段直接 mbuf，原有数据会被丢弃。
+    pub fn attach(&mut self, direct: &Mbuf) -> Result<()> {
+        let m = self.raw();
+        if m.ol_flags & (constants::RTE_MBUF_F_INDIRECT | constants::RTE_MBUF_F_EXTERNAL) != 0 {
+            return Err(DpdkError::invalid("只有直接 mbuf 可以附加到其他 mbuf"));
+        }
+        if self.refcnt() != 1 || m.nb_segs != 1 {
+            return Err(DpdkError::invalid("被附加的 mbuf 必须是未共享的单段 mbuf"));
+        }
+        unsafe {
+            rte_pktmbuf_attach(self.as_ptr(), direct.as_ptr());
+        }
+        Ok(())
+    }
+
+    /// 解除与直接 mbuf 的共享，恢复自己的空数据区；直接 mbuf 调用时无效果
+    pub fn detach(&mut self) {
+        if self.is_indirect() {
+            unsafe {
+                rte_pktmbuf_detach(self.as_ptr());
+            }
+        }
+    }
+}
+
+/// 段中的数据
+fn seg_data(m: &rte_mbuf) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(
+            (m.buf_addr as *const u8).add(m.data_off as usize),
+            m.data_len as usize,
+        )
+    }
+}
+
+/// 段中的可写数据
+///
+/// # Safety
+///
+/// `m` 必须是有效的段，调用者保证返回的切片存活期间没有其他访问。
+unsafe fn seg_data_mut<'a>(m: *mut rte_mbuf) -> &'a mut [u8] {
+    slice::from_raw_parts_mut(
+        ((*m).buf_addr as *mut u8).add((*m).data_off as usize),
+        (*m).data_len as usize,
+    )
+}
+
+/// 段的数据区是否只被该段引用
+fn is_seg_writable(m: &rte_mbuf) -> bool {
+    let shared_flags = constants::RTE_MBUF_F_INDIRECT | constants::RTE_MBUF_F_EXTERNAL;
+    m.ol_flags & shared_flags == 0 && unsafe { rte_mbuf_refcnt_read(m) } == 1
+}
+
+impl fmt::Debug for Mbuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.raw();
+        f.debug_struct("Mbuf")
+            .field("pkt_len", &m.pkt_len)
+            .field("data_len", &m.data_len)
+            .field("nb_segs", &m.nb_segs)
+            .field("port", &m.port)
+            .field("ol_flags", &format_args!("{:#x}", m.ol_flags))
+            .finish()
+    }
+}
+
+impl Drop for Mbuf {
+    fn drop(&mut self) {
+        unsafe {
+            rte_pktmbuf_free(self.raw.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! 固定容量的 mbuf 批次，用于突发收发
+
+use super::Mbuf;
+use crate::*;
+use std::fmt;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::vec;
+
+/// 固定容量的 mbuf 批次
+///
+/// `RxQueue::recv` 把收到的数据包追加到批次末尾，`TxQueue::send` 从批次开头取出
+/// 已发送的数据包，未发送的数据包留在批次中，因此不会因为忘记释放而泄漏。
+/// 批次被丢弃时释放其中剩余的所有数据包。
+pub struct MbufBatch {
+    // `Mbuf` 与 `*mut rte_mbuf` 布局相同，可以直接作为突发收发的指针数组。
+    mbufs: Vec<Mbuf>,
+    capacity: usize,
+}
+
+impl MbufBatch {
+    /// 创建容量为 `capacity` 的空批次
+    ///
+    /// # Panics
+    ///
+    /// `capacity` 超过 `u16::MAX` 时会 panic。
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity <= u16::MAX as usize,
+            "批次容量 {} 超过 u16::MAX",
+            capacity
+        );
+        Self {
+            mbufs: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// 批次容量，创建后不会改变
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 剩余可容纳的数据包数量
+    pub fn free_space(&self) -> usize {
+        self.capacity() - self.mbufs.len()
+    }
+
+    /// 批次是否已满
+    pub fn is_full(&self) -> bool {
+        self.free_space() == 0
+    }
+
+    /// 在末尾加入一个数据包，批次已满时原样返回
+    pub fn push(&mut self, mbuf: Mbuf) -> std::result::Result<(), Mbuf> {
+        if self.is_full() {
+            return Err(mbuf);
+        }
+        self.mbufs.push(mbuf);
+        Ok(())
+    }
+
+    /// 取出最后一个数据包
+    pub fn pop(&mut self) -> Option<Mbuf> {
+        self.mbufs.pop()
+    }
+
+    /// 取出指定位置的数据包，之后的数据包依次前移
+    ///
+    /// # Panics
+    ///
+    /// `index` 越界时会 panic。
+    pub fn remove(&mut self, index: usize) -> Mbuf {
+        self.mbufs.remove(index)
+    }
+
+    /// 只保留满足条件的数据包，其余的被释放
+    pub fn retain(&mut self, f: impl FnMut(&Mbuf) -> bool) {
+        self.mbufs.retain(f);
+    }
+
+    /// 按顺序取出满足条件的数据包，其余的数据包保持原有顺序留在批次中
+    ///
+    /// 只遍历一次批次，适合从收到的批次中分拣出某一类数据包。
+    /// 返回的迭代器被提前丢弃时，尚未检查的数据包留在批次中。
+    pub fn extract_if<F>(&mut self, f: F) -> vec::ExtractIf<'_, Mbuf, F>
+    where
+        F: FnMut(&mut Mbuf) -> bool,
+    {
+        self.mbufs.extract_if(.., f)
+    }
+
+    /// 按顺序把每个数据包交给 `f`，只保留 `f` 返回的数据包
+    ///
+    /// 只遍历一次批次，保留的数据包保持原有顺序。`f` 不返回的数据包由它接管，
+    /// 适合把处理阶段的 `handle`（不处理的数据包原样返回）应用到整个批次。
+    /// `f` panic 时，尚未交给它的数据包留在批次中。
+    pub fn retain_map(&mut self, mut f: impl FnMut(Mbuf) -> Option<Mbuf>) {
+        // 遍历期间 `[kept, read)` 是已经移出的空位，由 `Compact` 在结束或 panic 时
+        // 把尚未处理的数据包前移并恢复长度。
+        struct Compact<'a> {
+            mbufs: &'a mut Vec<Mbuf>,
+            len: usize,
+            read: usize,
+            kept: usize,
+        }
+
+        impl Drop for Compact<'_> {
+            fn drop(&mut self) {
+                let rest = self.len - self.read;
+                unsafe {
+                    let base = self.mbufs.as_mut_ptr();
+                    ptr::copy(base.add(self.read), base.add(self.kept), rest);
+                    self.mbufs.set_len(self.kept + rest);
+                }
+            }
+        }
+
+        let len = self.mbufs.len();
+        // 先把长度置零，遍历期间 `Vec` 不会释放已经移出的数据包。
+        unsafe { self.mbufs.set_len(0) };
+        let mut compact = Compact {
+            mbufs: &mut self.mbufs,
+            len,
+            read: 0,
+            kept: 0,
+        };
+        while compact.read < compact.len {
+            let base = compact.mbufs.as_mut_ptr();
+            let mbuf = unsafe { ptr::read(base.add(compact.read)) };
+            compact.read += 1;
+            if let Some(mbuf) = f(mbuf) {
+                unsafe { ptr::write(base.add(compact.kept), mbuf) };
+                compact.kept += 1;
+            }
+        }
+    }
+
+    /// 释放批次中的所有数据包
+    pub fn clear(&mut self) {
+        self.mbufs.clear();
+    }
+
+    /// 按顺序取出所有数据包
+    pub fn drain(&mut self) -> vec::Drain<'_, Mbuf> {
+        self.mbufs.drain(..)
+    }
+
+    /// 空闲区域的起始指针，供 `rte_eth_rx_burst` 写入
+    pub(crate) fn spare_ptr(&mut self) -> *mut *mut rte_mbuf {
+        let len = self.mbufs.len();
+        unsafe { self.mbufs.as_mut_ptr().add(len) as *mut *mut rte_mbuf }
+    }
+
+    /// 把 `rte_eth_rx_burst` 写入空闲区域的 `n` 个 mbuf 计入批次
+    ///
+    /// # Safety
+    ///
+    /// `n` 不能超过 `free_space()`，且空闲区域的前 `n` 项必须是有效的 mbuf。
+    pub(crate) unsafe fn commit(&mut self, n: usize) {
+        debug_assert!(n <= self.free_space());
+        let len = self.mbufs.len();
+        self.mbufs.set_len(len + n);
+    }
+
+    /// 批次开头的指针，供 `rte_eth_tx_burst` 读取
+    pub(crate) fn as_raw_ptr(&mut self) -> *mut *mut rte_mbuf {
+        self.mbufs.as_mut_ptr() as *mut *mut rte_mbuf
+    }
+
+    /// 移除开头 `n` 个所有权已经转移给驱动的 mbuf，不释放它们
+    ///
+    /// # Safety
+    ///
+    /// 开头的 `n` 个 mbuf 必须已经交给 `rte_eth_tx_burst` 等接口。
+    pub(crate) unsafe fn forget_front(&mut self, n: usize) {
+        self.mbufs.drain(..n).for_each(mem::forget);
+    }
+
+    /// 把批次截断为前 `len` 个 mbuf，不释放之后的 mbuf
+    ///
+    /// # Safety
+    ///
+    /// `len` 不能超过当前长度，之后的 mbuf 的所有权必须已经转移，例如被 GRO 合并到其他数据包中。
+    pub(crate) unsafe fn forget_tail(&mut self, len: usize) {
+        debug_assert!(len <= self.mbufs.len());
+        self.mbufs.set_len(len);
+    }
+}
+
+impl Deref for MbufBatch {
+    type Target = [Mbuf];
+
+    fn deref(&self) -> &[Mbuf] {
+        &self.mbufs
+    }
+}
+
+impl DerefMut for MbufBatch {
+    fn deref_mut(&mut self) -> &mut [Mbuf] {
+        &mut self.mbufs
+    }
+}
+
+impl<'a> IntoIterator for &'a MbufBatch {
+    type Item = &'a Mbuf;
+    type IntoIter = std::slice::Iter<'a, Mbuf>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.mbufs.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut MbufBatch {
+    type Item = &'a mut Mbuf;
+    type IntoIter = std::slice::IterMut<'a, Mbuf>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.mbufs.iter_mut()
+    }
+}
+
+impl fmt::Debug for MbufBatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MbufBatch")
+            .field("len", &self.mbufs.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
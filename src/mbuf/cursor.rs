@@ -0,0 +1,169 @@
+//! 跨段读取数据包的游标
+
+use super::{seg_data, Mbuf};
+use crate::*;
+use std::fmt;
+
+impl Mbuf {
+    /// 创建位于数据包开头的游标
+    pub fn cursor(&self) -> MbufCursor<'_> {
+        let mut cursor = MbufCursor {
+            seg: Some(self.raw()),
+            seg_off: 0,
+            pos: 0,
+            len: self.len(),
+        };
+        cursor.skip_empty();
+        cursor
+    }
+}
+
+/// 按顺序读取数据包内容的游标
+///
+/// 请求的数据位于同一个段内时直接返回段中的切片，不复制数据；
+/// 只有跨越段边界时才复制到调用者提供的缓冲区中。
+/// 读取越界时返回 `None` 且游标位置不变。
+#[derive(Clone)]
+pub struct MbufCursor<'a> {
+    seg: Option<&'a rte_mbuf>,
+    seg_off: usize,
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> MbufCursor<'a> {
+    /// 当前位置相对数据包开头的偏移
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// 剩余可读的字节数
+    pub fn remaining(&self) -> usize {
+        self.len.saturating_sub(self.pos)
+    }
+
+    /// 当前段中剩余的数据
+    pub fn chunk(&self) -> &'a [u8] {
+        match self.seg {
+            Some(seg) => &seg_data(seg)[self.seg_off..],
+            None => &[],
+        }
+    }
+
+    /// 跳过 `n` 字节
+    pub fn skip(&mut self, n: usize) -> Option<()> {
+        if n > self.remaining() {
+            return None;
+        }
+        let start = self.clone();
+        let mut left = n;
+        while left > 0 {
+            let step = left.min(self.chunk().len());
+            if step == 0 {
+                // 段链比 `pkt_len` 短，数据包不一致。
+                *self = start;
+                return None;
+            }
+            self.advance(step);
+            left -= step;
+        }
+        Some(())
+    }
+
+    /// 读取 `len` 字节
+    ///
+    /// 数据位于当前段内时返回段中的切片，否则复制到 `scratch` 并返回其前 `len` 字节。
+    ///
+    /// # Panics
+    ///
+    /// 需要复制且 `scratch` 小于 `len` 时会 panic。
+    pub fn read<'s>(&mut self, len: usize, scratch: &'s mut [u8]) -> Option<&'s [u8]>
+    where
+        'a: 's,
+    {
+        if len > self.remaining() {
+            return None;
+        }
+        let chunk = self.chunk();
+        if chunk.len() >= len {
+            self.advance(len);
+            return Some(&chunk[..len]);
+        }
+        let buf = &mut scratch[..len];
+        self.read_into(buf)?;
+        Some(buf)
+    }
+
+    /// 读取 `buf.len()` 字节并复制到 `buf` 中
+    pub fn read_into(&mut self, buf: &mut [u8]) -> Option<()> {
+        if buf.len() > self.remaining() {
+            return None;
+        }
+        let start = self.clone();
+        let mut filled = 0;
+        while filled < buf.len() {
+            let chunk = self.chunk();
+            let step = chunk.len().min(buf.len() - filled);
+            if step == 0 {
+                *self = start;
+                return None;
+            }
+            buf[filled..filled + step].copy_from_slice(&chunk[..step]);
+            self.advance(step);
+            filled += step;
+        }
+        Some(())
+    }
+
+    /// 读取一个字节
+    pub fn read_u8(&mut self) -> Option<u8> {
+        let mut buf = [0u8; 1];
+        self.read_into(&mut buf)?;
+        Some(buf[0])
+    }
+
+    /// 按网络字节序读取 `u16`
+    pub fn read_u16(&mut self) -> Option<u16> {
+        let mut buf = [0u8; 2];
+        self.read_into(&mut buf)?;
+        Some(u16::from_be_bytes(buf))
+    }
+
+    /// 按网络字节序读取 `u32`
+    pub fn read_u32(&mut self) -> Option<u32> {
+        let mut buf = [0u8; 4];
+        self.read_into(&mut buf)?;
+        Some(u32::from_be_bytes(buf))
+    }
+
+    /// 在当前段内前进 `n` 字节，`n` 不能超过当前段的剩余长度
+    fn advance(&mut self, n: usize) {
+        self.seg_off += n;
+        self.pos += n;
+        self.skip_empty();
+    }
+
+    /// 当前段已读完时移动到下一个非空段
+    fn skip_empty(&mut self) {
+        while let Some(seg) = self.seg {
+            if self.seg_off < seg.data_len as usize {
+                break;
+            }
+            let next = unsafe { seg.next.as_ref() };
+            if next.is_none() {
+                break;
+            }
+            self.seg = next;
+            self.seg_off = 0;
+        }
+    }
+}
+
+impl fmt::Debug for MbufCursor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MbufCursor")
+            .field("position", &self.pos)
+            .field("remaining", &self.remaining())
+            .finish()
+    }
+}
@@ -0,0 +1,341 @@
+//! 发送 offload 使用的 `ol_flags` 与头部长度
+
+use super::Mbuf;
+use crate::error::{check_ret, DpdkError, Result};
+use crate::ethdev::TxOffload;
+use crate::packet::{Ipv4Hdr, Ipv6Hdr, TcpHdr, UdpHdr};
+use crate::*;
+
+// `tx_offload` 位域中各字段的偏移和位数，与 `rte_mbuf_tx_offload` 一致
+const L2_LEN: (u32, u32) = (0, 7);
+const L3_LEN: (u32, u32) = (7, 9);
+const L4_LEN: (u32, u32) = (16, 8);
+const TSO_SEGSZ: (u32, u32) = (24, 16);
+const OUTER_L3_LEN: (u32, u32) = (40, 9);
+const OUTER_L2_LEN: (u32, u32) = (49, 7);
+
+/// `request_tx_offload` 负责设置的标志，其余标志（VLAN 插入、隧道等）保持不变
+const TX_CKSUM_FLAGS: u64 = constants::RTE_MBUF_F_TX_IPV4
+    | constants::RTE_MBUF_F_TX_IPV6
+    | constants::RTE_MBUF_F_TX_IP_CKSUM
+    | constants::RTE_MBUF_F_TX_L4_MASK
+    | constants::RTE_MBUF_F_TX_TCP_SEG;
+
+// L4 校验和字段相对 L4 头部的偏移
+const TCP_CKSUM: usize = 16;
+const UDP_CKSUM: usize = 6;
+
+/// mbuf 中 `tx_offload` 记录的各层头部长度
+///
+/// 隧道数据包中 `l2_len` 包括外层 L4 头部、隧道头部和内层 L2 头部，
+/// `l3_len`/`l4_len` 指内层头部，外层头部由 `outer_l2_len`/`outer_l3_len` 描述。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TxOffloadLens {
+    pub l2_len: u16,
+    pub l3_len: u16,
+    pub l4_len: u16,
+    /// TSO 分段大小
+    pub tso_segsz: u16,
+    pub outer_l2_len: u16,
+    pub outer_l3_len: u16,
+}
+
+impl TxOffloadLens {
+    fn from_raw(raw: u64) -> Self {
+        let field = |(ofs, bits): (u32, u32)| ((raw >> ofs) & ((1 << bits) - 1)) as u16;
+        TxOffloadLens {
+            l2_len: field(L2_LEN),
+            l3_len: field(L3_LEN),
+            l4_len: field(L4_LEN),
+            tso_segsz: field(TSO_SEGSZ),
+            outer_l2_len: field(OUTER_L2_LEN),
+            outer_l3_len: field(OUTER_L3_LEN),
+        }
+    }
+
+    /// 检查每个长度是否放得进对应的位域
+    fn check(&self) -> Result<()> {
+        let fields = [
+            ("l2_len", self.l2_len, L2_LEN),
+            ("l3_len", self.l3_len, L3_LEN),
+            ("l4_len", self.l4_len, L4_LEN),
+            ("outer_l2_len", self.outer_l2_len, OUTER_L2_LEN),
+            ("outer_l3_len", self.outer_l3_len, OUTER_L3_LEN),
+        ];
+        for (name, value, (_, bits)) in fields {
+            if value as u64 >= 1 << bits {
+                return Err(DpdkError::invalid(format!(
+                    "{} 为 {}，超出 {} 位的范围",
+                    name, value, bits
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 希望在发送时完成的校验和与分段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TxOffloadRequest {
+    /// IPv4 头部校验和，IPv6 没有头部校验和
+    pub ip_checksum: bool,
+    /// TCP 或 UDP 校验和
+    pub l4_checksum: bool,
+    /// TSO 分段后每段 TCP 负载的最大长度，0 表示不分段
+    pub tso_segsz: u16,
+}
+
+/// 第一个段中 IP 头部和 L4 头部的位置
+struct Headers {
+    ipv4: bool,
+    l2_len: usize,
+    l3_len: usize,
+    /// 是否是 IPv4 分片或带 IPv6 分片头部
+    fragment: bool,
+    /// 上层协议号，分片不包含完整的 L4 数据，为 `None`
+    proto: Option<u32>,
+    l4_len: usize,
+    /// 是否有 IPv6 扩展头部
+    ext_headers: bool,
+}
+
+impl Headers {
+    /// 只要求头部位于第一个段内，IP 总长度/负载长度可以超出第一个段
+    fn parse(mbuf: &Mbuf) -> Result<Self> {
+        let eth = mbuf.ether()?;
+        let l2_len = eth.header_len();
+        let ether_type = eth.ether_type() as u32;
+        let (ipv4, l3_len, fragment, proto, l4, ext_headers) = match ether_type {
+            constants::RTE_ETHER_TYPE_IPV4 => {
+                let ip = Ipv4Hdr::header_only(eth.into_payload())?;
+                let fragment = ip.is_fragment();
+                let proto = ip.next_proto_id() as u32;
+                (
+                    true,
+                    ip.header_len(),
+                    fragment,
+                    proto,
+                    ip.into_payload(),
+                    false,
+                )
+            }
+            constants::RTE_ETHER_TYPE_IPV6 => {
+                let ip = Ipv6Hdr::header_only(eth.into_payload())?;
+                let fragment = ip.ext_headers().any(|ext| ext.is_fragment());
+                let (proto, offset) = ip.upper_layer()?;
+                let l4 = &ip.into_payload()[offset..];
+                (
+                    false,
+                    Ipv6Hdr::<&[u8]>::LEN + offset,
+                    fragment,
+                    proto as u32,
+                    l4,
+                    offset != 0,
+                )
+            }
+            _ => {
+                return Err(DpdkError::invalid(format!(
+                    "以太网类型 {:#06x} 不是 IPv4 或 IPv6",
+                    ether_type
+                )))
+            }
+        };
+        let proto = (!fragment).then_some(proto);
+        let l4_len = match proto {
+            Some(constants::IPPROTO_TCP) => TcpHdr::new(l4)?.header_len(),
+            Some(constants::IPPROTO_UDP) => {
+                UdpHdr::header_only(l4)?;
+                UdpHdr::<&[u8]>::LEN
+            }
+            _ => 0,
+        };
+        Ok(Headers {
+            ipv4,
+            l2_len,
+            l3_len,
+            fragment,
+            proto,
+            l4_len,
+            ext_headers,
+        })
+    }
+}
+
+impl Mbuf {
+    /// offload 标志（`ol_flags`），包括 `RTE_MBUF_F_RX_*` 和 `RTE_MBUF_F_TX_*`
+    pub fn ol_flags(&self) -> u64 {
+        self.raw().ol_flags
+    }
+
+    /// 设置 offload 标志
+    pub fn set_ol_flags(&mut self, ol_flags: u64) {
+        unsafe { (*self.as_ptr()).ol_flags = ol_flags }
+    }
+
+    /// 发送 offload 使用的头部长度
+    pub fn tx_offload_lens(&self) -> TxOffloadLens {
+        TxOffloadLens::from_raw(unsafe { self.raw().__bindgen_anon_4.tx_offload })
+    }
+
+    /// 设置发送 offload 使用的头部长度，长度超出位域范围时返回错误
+    pub fn set_tx_offload_lens(&mut self, lens: TxOffloadLens) -> Result<()> {
+        lens.check()?;
+        unsafe {
+            (*self.as_ptr()).__bindgen_anon_4.tx_offload = rte_mbuf_tx_offload(
+                lens.l2_len as u64,
+                lens.l3_len as u64,
+                lens.l4_len as u64,
+                lens.tso_segsz as u64,
+                lens.outer_l3_len as u64,
+                lens.outer_l2_len as u64,
+                0,
+            );
+        }
+        Ok(())
+    }
+
+    /// 请求网卡计算校验和或进行 TSO 分段，端口没有开启相应能力时由软件完成
+    ///
+    /// 解析第一个段中的以太网、IP 和 TCP/UDP 头部，设置 `ol_flags`、各层头部长度和
+    /// `tso_segsz`，并用 `rte_validate_tx_offload` 检查；之后交给网卡计算的 L4 校验和字段
+    /// 预先写入伪头部校验和（`rte_ipv4_phdr_cksum`/`rte_ipv6_phdr_cksum`）。
+    /// `enabled` 是端口开启的发送 offload，通常为 `Port::tx_offloads()`。
+    /// 返回错误时 `ol_flags`、头部长度和数据包内容保持不变。
+    ///
+    /// 各层头部必须位于第一个段内，软件计算 L4 校验和还要求整个数据包位于第一个段内。
+    /// 带扩展头部的 IPv6 数据包的 L4 校验和总是由软件计算。分片不包含完整的 L4 数据，
+    /// 请求 L4 校验和或 TSO 时返回错误。TSO 没有软件回退，端口没有开启 `TCP_TSO` 时
+    /// 返回错误，可以改用 `GsoContext` 分段。隧道数据包的 offload 由 `TunnelEncap` 设置，
+    /// 这里返回错误。
+    pub fn request_tx_offload(&mut self, req: TxOffloadRequest, enabled: TxOffload) -> Result<()> {
+        if self.ol_flags() & constants::RTE_MBUF_F_TX_TUNNEL_MASK != 0 {
+            return Err(DpdkError::invalid(
+                "隧道数据包的发送 offload 由 TunnelEncap 设置",
+            ));
+        }
+        if !self.is_writable() {
+            return Err(DpdkError::invalid("数据区被共享，不能写入校验和"));
+        }
+        let hdrs = Headers::parse(self)?;
+        if hdrs.fragment && (req.l4_checksum || req.tso_segsz != 0) {
+            return Err(DpdkError::invalid(
+                "分片不包含完整的 L4 数据，不能计算 L4 校验和或进行 TSO",
+            ));
+        }
+        let tcp = hdrs.proto == Some(constants::IPPROTO_TCP);
+        let udp = hdrs.proto == Some(constants::IPPROTO_UDP);
+        let tso = req.tso_segsz != 0;
+        if tso {
+            if !tcp || hdrs.ext_headers {
+                return Err(DpdkError::invalid(
+                    "TSO 只支持没有 IPv6 扩展头部、未分片的 TCP 数据包",
+                ));
+            }
+            // IPv4 TSO 要求网卡同时计算每个分段的 IP 头部校验和。
+            let mut needed = TxOffload::TCP_TSO;
+            if hdrs.ipv4 {
+                needed |= TxOffload::IPV4_CKSUM;
+            }
+            if !enabled.contains(needed) {
+                return Err(DpdkError::invalid(format!(
+                    "端口没有开启 {:?}，可以用 GsoContext 在软件中分段",
+                    needed - enabled
+                )));
+            }
+        }
+
+        let ip_hw =
+            hdrs.ipv4 && (req.ip_checksum || tso) && enabled.contains(TxOffload::IPV4_CKSUM);
+        // 伪头部校验和按 IP 头部中的协议号计算，有扩展头部时不能交给网卡。
+        let l4_flag = if tso {
+            constants::RTE_MBUF_F_TX_TCP_SEG
+        } else if !req.l4_checksum || hdrs.ext_headers {
+            0
+        } else if tcp && enabled.contains(TxOffload::TCP_CKSUM) {
+            constants::RTE_MBUF_F_TX_TCP_CKSUM
+        } else if udp && enabled.contains(TxOffload::UDP_CKSUM) {
+            constants::RTE_MBUF_F_TX_UDP_CKSUM
+        } else {
+            0
+        };
+        let mut ol_flags = self.ol_flags() & !TX_CKSUM_FLAGS;
+        if ip_hw {
+            ol_flags |= constants::RTE_MBUF_F_TX_IP_CKSUM;
+        }
+        ol_flags |= l4_flag;
+        if ol_flags & TX_CKSUM_FLAGS != 0 {
+            ol_flags |= if hdrs.ipv4 {
+                constants::RTE_MBUF_F_TX_IPV4
+            } else {
+                constants::RTE_MBUF_F_TX_IPV6
+            };
+        }
+
+        // 先写入标志和长度并检查，失败时恢复原来的值，数据包内容保持不变。
+        let old_flags = self.ol_flags();
+        let old_lens = unsafe { self.raw().__bindgen_anon_4.tx_offload };
+        let result = self
+            .set_tx_offload_lens(TxOffloadLens {
+                l2_len: hdrs.l2_len as u16,
+                l3_len: hdrs.l3_len as u16,
+                l4_len: hdrs.l4_len as u16,
+                tso_segsz: req.tso_segsz,
+                ..Default::default()
+            })
+            .and_then(|()| {
+                self.set_ol_flags(ol_flags);
+                check_ret("rte_validate_tx_offload", unsafe {
+                    rte_validate_tx_offload(self.as_ptr())
+                })?;
+                self.fill_tx_checksums(&hdrs, req, ol_flags, l4_flag, ip_hw)
+            });
+        if result.is_err() {
+            self.set_ol_flags(old_flags);
+            unsafe { (*self.as_ptr()).__bindgen_anon_4.tx_offload = old_lens };
+        }
+        result
+    }
+
+    /// 写入 `request_tx_offload` 选定的伪头部校验和或软件计算的校验和
+    ///
+    /// 只有软件计算 L4 校验和可能失败，此时数据包还没有被修改。
+    fn fill_tx_checksums(
+        &mut self,
+        hdrs: &Headers,
+        req: TxOffloadRequest,
+        ol_flags: u64,
+        l4_flag: u64,
+        ip_hw: bool,
+    ) -> Result<()> {
+        let tcp = hdrs.proto == Some(constants::IPPROTO_TCP);
+        let l3 = &mut self.data_mut()[hdrs.l2_len..];
+        if l4_flag != 0 {
+            let off = hdrs.l3_len + if tcp { TCP_CKSUM } else { UDP_CKSUM };
+            let ip = l3.as_ptr();
+            // 结果按内存中的字节顺序计算，原样写回即为网络字节序。
+            let cksum = unsafe {
+                if hdrs.ipv4 {
+                    rte_ipv4_phdr_cksum(ip as *const rte_ipv4_hdr, ol_flags)
+                } else {
+                    rte_ipv6_phdr_cksum(ip as *const rte_ipv6_hdr, ol_flags)
+                }
+            };
+            l3[off..off + 2].copy_from_slice(&cksum.to_ne_bytes());
+        } else if req.l4_checksum {
+            // 软件计算需要整个 L4 数据，多段数据包被 `Ipv4Hdr::new`/`Ipv6Hdr::new`
+            // 的长度检查拒绝。
+            if hdrs.ipv4 {
+                Ipv4Hdr::new(&mut *l3)?.update_l4_checksum()?;
+            } else {
+                Ipv6Hdr::new(&mut *l3)?.update_l4_checksum()?;
+            }
+        }
+        if ip_hw {
+            // 网卡计算 IPv4 头部校验和时要求该字段为 0。
+            Ipv4Hdr::header_only(&mut *l3)?.set_hdr_checksum(0);
+        } else if hdrs.ipv4 && req.ip_checksum {
+            Ipv4Hdr::header_only(&mut *l3)?.update_checksum();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! 接收时网卡写入 mbuf 的元数据：校验和检查结果、RSS 哈希、流标记和时间戳
+
+use super::Mbuf;
+use crate::packet::PacketType;
+use crate::*;
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::OnceLock;
+
+/// 时间戳动态字段和动态标志的名称（`RTE_MBUF_DYNFIELD_TIMESTAMP_NAME`、
+/// `RTE_MBUF_DYNFLAG_RX_TIMESTAMP_NAME`）
+const TIMESTAMP_FIELD: &[u8] = b"rte_dynfield_timestamp\0";
+const RX_TIMESTAMP_FLAG: &[u8] = b"rte_dynflag_rx_timestamp\0";
+
+/// 时间戳字段的偏移和标志位，只缓存查找成功的结果
+static RX_TIMESTAMP: OnceLock<(usize, u64)> = OnceLock::new();
+
+/// 网卡对一层校验和的检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RxChecksum {
+    /// 网卡没有检查，可能不支持或没有开启校验和 offload
+    Unknown,
+    /// 校验和错误
+    Bad,
+    /// 校验和正确
+    Good,
+    /// 数据包中的校验和无效（例如被截断），但数据本身是完整的
+    None,
+}
+
+impl RxChecksum {
+    /// 按 `mask` 下的 UNKNOWN/BAD/GOOD/NONE 取值解码
+    fn decode(ol_flags: u64, mask: u64, bad: u64, good: u64) -> Self {
+        match ol_flags & mask {
+            0 => RxChecksum::Unknown,
+            v if v == bad => RxChecksum::Bad,
+            v if v == good => RxChecksum::Good,
+            _ => RxChecksum::None,
+        }
+    }
+
+    pub fn is_good(&self) -> bool {
+        *self == RxChecksum::Good
+    }
+
+    pub fn is_bad(&self) -> bool {
+        *self == RxChecksum::Bad
+    }
+}
+
+impl Mbuf {
+    /// IP 头部校验和的检查结果，隧道数据包中指内层 IP 头部
+    pub fn rx_ip_checksum(&self) -> RxChecksum {
+        RxChecksum::decode(
+            self.ol_flags(),
+            constants::RTE_MBUF_F_RX_IP_CKSUM_MASK,
+            constants::RTE_MBUF_F_RX_IP_CKSUM_BAD,
+            constants::RTE_MBUF_F_RX_IP_CKSUM_GOOD,
+        )
+    }
+
+    /// TCP/UDP/SCTP 校验和的检查结果，隧道数据包中指内层 L4 头部
+    pub fn rx_l4_checksum(&self) -> RxChecksum {
+        RxChecksum::decode(
+            self.ol_flags(),
+            constants::RTE_MBUF_F_RX_L4_CKSUM_MASK,
+            constants::RTE_MBUF_F_RX_L4_CKSUM_BAD,
+            constants::RTE_MBUF_F_RX_L4_CKSUM_GOOD,
+        )
+    }
+
+    /// 隧道外层 IP 头部校验和的检查结果
+    ///
+    /// DPDK 只定义了“错误”一个标志，因此结果只有 `Bad` 和 `Unknown` 两种。
+    pub fn rx_outer_ip_checksum(&self) -> RxChecksum {
+        if self.ol_flags() & constants::RTE_MBUF_F_RX_OUTER_IP_CKSUM_BAD != 0 {
+            RxChecksum::Bad
+        } else {
+            RxChecksum::Unknown
+        }
+    }
+
+    /// 隧道外层 UDP 校验和的检查结果
+    pub fn rx_outer_l4_checksum(&self) -> RxChecksum {
+        RxChecksum::decode(
+            self.ol_flags(),
+            constants::RTE_MBUF_F_RX_OUTER_L4_CKSUM_MASK,
+            constants::RTE_MBUF_F_RX_OUTER_L4_CKSUM_BAD,
+            constants::RTE_MBUF_F_RX_OUTER_L4_CKSUM_GOOD,
+        )
+    }
+
+    /// 网卡计算的 RSS 哈希值，端口没有开启 RSS 时为 `None`
+    pub fn rss_hash(&self) -> Option<u32> {
+        (self.ol_flags() & constants::RTE_MBUF_F_RX_RSS_HASH != 0)
+            .then(|| unsafe { self.raw().__bindgen_anon_3.hash.rss })
+    }
+
+    /// 匹配的流规则设置的标记（`RTE_FLOW_ACTION_TYPE_MARK` 或 flow director ID）
+    pub fn fdir_mark(&self) -> Option<u32> {
+        (self.ol_flags() & constants::RTE_MBUF_F_RX_FDIR_ID != 0)
+            .then(|| unsafe { self.raw().__bindgen_anon_3.hash.fdir.hi })
+    }
+
+    /// 网卡记录的接收时间戳，单位由驱动决定，通常为纳秒或网卡时钟周期
+    ///
+    /// 端口开启 `RxOffload::TIMESTAMP` 时驱动注册时间戳动态字段。找到该字段后缓存其位置，
+    /// 在此之前每次调用都重新查找；没有时间戳的数据包返回 `None`。
+    pub fn rx_timestamp(&self) -> Option<u64> {
+        let (offset, flag) = match RX_TIMESTAMP.get() {
+            Some(&field) => field,
+            None => {
+                let field = lookup_rx_timestamp()?;
+                let _ = RX_TIMESTAMP.set(field);
+                field
+            }
+        };
+        if self.ol_flags() & flag == 0 {
+            return None;
+        }
+        Some(unsafe { ptr::read((self.as_ptr() as *const u8).add(offset) as *const u64) })
+    }
+
+    /// 数据包类型，驱动没有识别出任何一层时用 `rte_net_get_ptype` 在软件中解析
+    ///
+    /// 软件解析不识别 VXLAN、GENEVE 等基于 UDP 端口的隧道，结果不写回 mbuf。
+    pub fn classify(&self) -> PacketType {
+        let ptype = self.packet_type();
+        if ptype.is_unknown() {
+            self.detect_ptype().0
+        } else {
+            ptype
+        }
+    }
+}
+
+fn lookup_rx_timestamp() -> Option<(usize, u64)> {
+    let offset = unsafe {
+        rte_mbuf_dynfield_lookup(TIMESTAMP_FIELD.as_ptr() as *const c_char, ptr::null_mut())
+    };
+    let bit = unsafe {
+        rte_mbuf_dynflag_lookup(RX_TIMESTAMP_FLAG.as_ptr() as *const c_char, ptr::null_mut())
+    };
+    if offset < 0 || bit < 0 {
+        return None;
+    }
+    Some((offset as usize, 1u64 << bit))
+}
@@ -0,0 +1,149 @@
+//! 多段 mbuf 的遍历、链接与线性化
+
+use super::{is_seg_writable, seg_data, seg_data_mut, Mbuf};
+use crate::error::{check_ret, DpdkError, Result};
+use crate::*;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+use std::ptr;
+
+impl Mbuf {
+    /// 段的数量（`nb_segs`）
+    pub fn nb_segs(&self) -> usize {
+        self.raw().nb_segs as usize
+    }
+
+    /// 数据是否全部位于第一个段中
+    pub fn is_contiguous(&self) -> bool {
+        self.raw().nb_segs == 1
+    }
+
+    /// 按顺序遍历每个段中的数据
+    pub fn segments(&self) -> Segments<'_> {
+        Segments {
+            next: Some(self.raw()),
+        }
+    }
+
+    /// 按顺序遍历每个段中的可写数据
+    ///
+    /// 遇到与其他 mbuf 共享数据区的段时会 panic。
+    pub fn segments_mut(&mut self) -> SegmentsMut<'_> {
+        SegmentsMut {
+            next: self.as_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 把 `tail` 链接到当前数据包的末尾（`rte_pktmbuf_chain`）
+    ///
+    /// 段数超过 `RTE_MBUF_MAX_NB_SEGS` 时返回错误，此时 `tail` 被释放。
+    pub fn chain(&mut self, tail: Mbuf) -> Result<()> {
+        let nb_segs = self.nb_segs() + tail.nb_segs();
+        if nb_segs > constants::RTE_MBUF_MAX_NB_SEGS as usize {
+            return Err(DpdkError::invalid(format!(
+                "链接后的段数 {} 超过 RTE_MBUF_MAX_NB_SEGS",
+                nb_segs
+            )));
+        }
+        check_ret("rte_pktmbuf_chain", unsafe {
+            rte_pktmbuf_chain(self.as_ptr(), tail.as_ptr())
+        })?;
+        // 成功后 `tail` 成为当前数据包的一部分，随头部一起释放。
+        tail.into_raw();
+        Ok(())
+    }
+
+    /// 把所有段的数据复制到第一个段中并释放其余段（`rte_pktmbuf_linearize`）
+    ///
+    /// 第一个段的尾部剩余空间必须能容纳其余段的数据。
+    pub fn linearize(&mut self) -> Result<()> {
+        if self.is_contiguous() {
+            return Ok(());
+        }
+        if !self.is_writable() {
+            return Err(DpdkError::invalid("mbuf 的数据区被共享，不能线性化"));
+        }
+        if unsafe { rte_pktmbuf_linearize(self.as_ptr()) } < 0 {
+            return Err(DpdkError::invalid(format!(
+                "第一个段的尾部剩余空间不足以容纳 {} 字节",
+                self.len() - self.data_len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 从数据包偏移 `offset` 处读取 `buf.len()` 字节，可以跨越多个段
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<()> {
+        let mut cursor = self.cursor();
+        cursor
+            .skip(offset)
+            .and_then(|_| cursor.read_into(buf))
+            .ok_or_else(|| {
+                DpdkError::invalid(format!(
+                    "读取范围 {}..{} 超过数据包长度 {}",
+                    offset,
+                    offset + buf.len(),
+                    self.len()
+                ))
+            })
+    }
+
+    /// 检查段链的一致性（`rte_mbuf_check`）
+    ///
+    /// 包括各段 `data_len` 之和等于 `pkt_len`、段数等于 `nb_segs`，
+    /// 以及每个段的数据都位于其缓冲区内。
+    pub fn validate(&self) -> Result<()> {
+        let mut reason: *const c_char = ptr::null();
+        if unsafe { rte_mbuf_check(self.as_ptr(), 1, &mut reason) } < 0 {
+            let reason = if reason.is_null() {
+                "未知原因".to_string()
+            } else {
+                unsafe { CStr::from_ptr(reason) }
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            return Err(DpdkError::invalid(format!("mbuf 不一致: {}", reason)));
+        }
+        Ok(())
+    }
+}
+
+/// 数据包各段数据的迭代器
+#[derive(Clone)]
+pub struct Segments<'a> {
+    next: Option<&'a rte_mbuf>,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let m = self.next?;
+        self.next = unsafe { m.next.as_ref() };
+        Some(seg_data(m))
+    }
+}
+
+/// 数据包各段可写数据的迭代器
+pub struct SegmentsMut<'a> {
+    next: *mut rte_mbuf,
+    _marker: PhantomData<&'a mut Mbuf>,
+}
+
+impl<'a> Iterator for SegmentsMut<'a> {
+    type Item = &'a mut [u8];
+
+    fn next(&mut self) -> Option<&'a mut [u8]> {
+        let m = self.next;
+        if m.is_null() {
+            return None;
+        }
+        unsafe {
+            assert!(is_seg_writable(&*m), "mbuf 段的数据区被共享，不能修改");
+            self.next = (*m).next;
+            Some(seg_data_mut(m))
+        }
+    }
+}
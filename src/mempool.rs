@@ -0,0 +1,387 @@
+//! 类型化的内存池
+//!
+//! `Mempool<T>` 包装 `rte_mempool_create` 创建的内存池，池中每个对象都是一个 `T`。
+//! 对象在创建内存池时由初始化闭包构造，之后在池和 `PoolBox<T>` 之间循环使用，
+//! 直到内存池被释放时才被析构。
+
+use crate::error::{check_ptr, check_ret, DpdkError, Result};
+use crate::*;
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::os::raw::{c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
+use std::thread;
+
+/// 对象初始化闭包，参数为对象在池中的序号
+type ObjInit<'a, T> = Box<dyn FnMut(u32) -> T + 'a>;
+
+/// 回调中捕获的 panic
+type PanicPayload = Box<dyn Any + Send>;
+
+/// 内存池构建器
+pub struct MempoolBuilder<'a, T> {
+    name: String,
+    count: u32,
+    cache_size: u32,
+    socket_id: i32,
+    flags: u32,
+    init: ObjInit<'a, T>,
+}
+
+impl<'a, T> MempoolBuilder<'a, T> {
+    /// 创建构建器，`init` 用于构造池中的每个对象
+    pub fn with_init(name: impl Into<String>, init: impl FnMut(u32) -> T + 'a) -> Self {
+        Self {
+            name: name.into(),
+            count: 1024,
+            cache_size: 0,
+            socket_id: SOCKET_ID_ANY,
+            flags: 0,
+            init: Box::new(init),
+        }
+    }
+
+    /// 池中对象的数量
+    pub fn count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// 每个 lcore 的本地缓存大小，不能超过 `RTE_MEMPOOL_CACHE_MAX_SIZE`
+    pub fn cache_size(mut self, cache_size: u32) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    /// 分配内存的 NUMA socket，默认为 `SOCKET_ID_ANY`
+    pub fn socket_id(mut self, socket_id: i32) -> Self {
+        self.socket_id = socket_id;
+        self
+    }
+
+    /// 不在内存通道和 rank 之间分散对象（`RTE_MEMPOOL_F_NO_SPREAD`）
+    pub fn no_spread(mut self, enable: bool) -> Self {
+        self.set_flag(constants::RTE_MEMPOOL_F_NO_SPREAD, enable);
+        self
+    }
+
+    /// 对象不按缓存行对齐（`RTE_MEMPOOL_F_NO_CACHE_ALIGN`）
+    pub fn no_cache_align(mut self, enable: bool) -> Self {
+        self.set_flag(constants::RTE_MEMPOOL_F_NO_CACHE_ALIGN, enable);
+        self
+    }
+
+    /// 对象不要求 IOVA 连续（`RTE_MEMPOOL_F_NO_IOVA_CONTIG`）
+    pub fn no_iova_contig(mut self, enable: bool) -> Self {
+        self.set_flag(constants::RTE_MEMPOOL_F_NO_IOVA_CONTIG, enable);
+        self
+    }
+
+    /// 替换对象初始化闭包
+    pub fn init(mut self, init: impl FnMut(u32) -> T + 'a) -> Self {
+        self.init = Box::new(init);
+        self
+    }
+
+    fn set_flag(&mut self, flag: u32, enable: bool) {
+        if enable {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    /// 调用 `rte_mempool_create` 创建内存池
+    ///
+    /// 不支持 `RTE_MEMPOOL_F_SP_PUT`/`RTE_MEMPOOL_F_SC_GET`，
+    /// 因为 `Mempool<T>` 允许多个线程同时取放对象。
+    ///
+    /// 初始化闭包 panic 时，已构造的对象被析构，内存池被释放，然后在此处重新抛出 panic。
+    pub fn build(self) -> Result<Mempool<T>> {
+        // 对象默认按缓存行对齐，否则只保证 8 字节对齐。
+        let max_align = if self.flags & constants::RTE_MEMPOOL_F_NO_CACHE_ALIGN != 0 {
+            mem::size_of::<u64>()
+        } else {
+            RTE_CACHE_LINE_SIZE as usize
+        };
+        if mem::align_of::<T>() > max_align {
+            return Err(DpdkError::invalid(format!(
+                "对象对齐要求 {} 超过内存池支持的 {} 字节",
+                mem::align_of::<T>(),
+                max_align
+            )));
+        }
+        if self.cache_size > constants::RTE_MEMPOOL_CACHE_MAX_SIZE {
+            return Err(DpdkError::invalid(format!(
+                "缓存大小 {} 超过 RTE_MEMPOOL_CACHE_MAX_SIZE",
+                self.cache_size
+            )));
+        }
+
+        let name = CString::new(self.name.as_str())?;
+        let elt_size = mem::size_of::<T>().max(1) as c_uint;
+        let mut state = InitState {
+            init: self.init,
+            initialized: 0,
+            panic: None,
+        };
+        let mp = unsafe {
+            rte_mempool_create(
+                name.as_ptr(),
+                self.count,
+                elt_size,
+                self.cache_size,
+                0,
+                None,
+                ptr::null_mut(),
+                Some(obj_init::<T>),
+                &mut state as *mut InitState<'a, T> as *mut c_void,
+                self.socket_id,
+                self.flags,
+            )
+        };
+        let raw = check_ptr("rte_mempool_create", mp)?;
+        if let Some(payload) = state.panic {
+            unsafe {
+                drop_objects::<T>(raw.as_ptr(), state.initialized);
+                rte_mempool_free(raw.as_ptr());
+            }
+            panic::resume_unwind(payload);
+        }
+        Ok(Mempool {
+            raw,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T> fmt::Debug for MempoolBuilder<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MempoolBuilder")
+            .field("name", &self.name)
+            .field("count", &self.count)
+            .field("cache_size", &self.cache_size)
+            .field("socket_id", &self.socket_id)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+/// 对象构造回调的状态
+struct InitState<'a, T> {
+    init: ObjInit<'a, T>,
+    /// 已构造的对象数量，回调按序号从 0 开始依次调用
+    initialized: u32,
+    panic: Option<PanicPayload>,
+}
+
+/// `rte_mempool_create` 的对象构造回调
+///
+/// 闭包中的 panic 在此处被捕获并保存，之后的对象不再构造。
+unsafe extern "C" fn obj_init<T>(
+    _mp: *mut rte_mempool,
+    opaque: *mut c_void,
+    obj: *mut c_void,
+    obj_idx: c_uint,
+) {
+    let state = &mut *(opaque as *mut InitState<'_, T>);
+    if state.panic.is_some() {
+        return;
+    }
+    match panic::catch_unwind(AssertUnwindSafe(|| (state.init)(obj_idx))) {
+        Ok(value) => {
+            ptr::write(obj as *mut T, value);
+            state.initialized += 1;
+        }
+        Err(payload) => state.panic = Some(payload),
+    }
+}
+
+/// 对象析构回调的状态
+struct DropState {
+    /// 只析构序号小于该值的对象
+    limit: u32,
+    panic: Option<PanicPayload>,
+}
+
+/// 析构池中对象的回调，`T::drop` 中的 panic 被捕获，其余对象照常析构
+unsafe extern "C" fn obj_drop<T>(
+    _mp: *mut rte_mempool,
+    opaque: *mut c_void,
+    obj: *mut c_void,
+    obj_idx: c_uint,
+) {
+    let state = &mut *(opaque as *mut DropState);
+    if obj_idx >= state.limit {
+        return;
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(|| ptr::drop_in_place(obj as *mut T)));
+    if let Err(payload) = result {
+        state.panic.get_or_insert(payload);
+    }
+}
+
+/// 析构序号小于 `limit` 的对象，返回第一个 `T::drop` 中的 panic
+unsafe fn drop_objects<T>(mp: *mut rte_mempool, limit: u32) -> Option<PanicPayload> {
+    if !mem::needs_drop::<T>() {
+        return None;
+    }
+    let mut state = DropState { limit, panic: None };
+    rte_mempool_obj_iter(
+        mp,
+        Some(obj_drop::<T>),
+        &mut state as *mut DropState as *mut c_void,
+    );
+    state.panic
+}
+
+/// 存放 `T` 类型对象的内存池
+///
+/// 默认的多生产者/多消费者模式下，取放操作是线程安全的。
+/// 被丢弃时析构所有对象并调用 `rte_mempool_free`，之后重新抛出 `T::drop` 中的第一个 panic
+/// （当前线程已在展开 panic 时除外）。
+pub struct Mempool<T> {
+    raw: NonNull<rte_mempool>,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for Mempool<T> {}
+unsafe impl<T: Send> Sync for Mempool<T> {}
+
+impl<T: Default> Mempool<T> {
+    /// 创建构建器，对象使用 `T::default()` 初始化
+    pub fn builder<'a>(name: impl Into<String>) -> MempoolBuilder<'a, T> {
+        MempoolBuilder::with_init(name, |_| T::default())
+    }
+}
+
+impl<T> Mempool<T> {
+    /// 从池中取出一个对象
+    pub fn get(&self) -> Result<PoolBox<'_, T>> {
+        let mut obj: *mut c_void = ptr::null_mut();
+        check_ret("rte_mempool_get", unsafe {
+            rte_mempool_get(self.raw.as_ptr(), &mut obj)
+        })?;
+        Ok(PoolBox {
+            obj: unsafe { NonNull::new_unchecked(obj as *mut T) },
+            pool: self,
+        })
+    }
+
+    /// 从池中一次取出 `n` 个对象，对象不足时不取出任何对象
+    pub fn get_bulk(&self, n: usize) -> Result<Vec<PoolBox<'_, T>>> {
+        let mut objs: Vec<*mut c_void> = vec![ptr::null_mut(); n];
+        check_ret("rte_mempool_get_bulk", unsafe {
+            rte_mempool_get_bulk(self.raw.as_ptr(), objs.as_mut_ptr(), n as c_uint)
+        })?;
+        Ok(objs
+            .into_iter()
+            .map(|obj| PoolBox {
+                obj: unsafe { NonNull::new_unchecked(obj as *mut T) },
+                pool: self,
+            })
+            .collect())
+    }
+
+    /// 池中可用的对象数量
+    pub fn avail_count(&self) -> u32 {
+        unsafe { rte_mempool_avail_count(self.raw.as_ptr()) }
+    }
+
+    /// 已被取出的对象数量
+    pub fn in_use_count(&self) -> u32 {
+        unsafe { rte_mempool_in_use_count(self.raw.as_ptr()) }
+    }
+
+    /// 池中对象的总数
+    pub fn capacity(&self) -> u32 {
+        unsafe { self.raw.as_ref().size }
+    }
+
+    /// 内存池名称
+    pub fn name(&self) -> String {
+        let name = unsafe { CStr::from_ptr(self.raw.as_ref().name.as_ptr()) };
+        name.to_string_lossy().into_owned()
+    }
+
+    /// 底层的 `rte_mempool` 指针
+    pub fn as_ptr(&self) -> *mut rte_mempool {
+        self.raw.as_ptr()
+    }
+}
+
+impl<T> fmt::Debug for Mempool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mempool")
+            .field("name", &self.name())
+            .field("avail", &self.avail_count())
+            .field("in_use", &self.in_use_count())
+            .finish()
+    }
+}
+
+impl<T> Drop for Mempool<T> {
+    fn drop(&mut self) {
+        // `PoolBox` 借用了内存池，此时所有对象都已归还。
+        let panic = unsafe {
+            let panic = drop_objects::<T>(self.raw.as_ptr(), u32::MAX);
+            rte_mempool_free(self.raw.as_ptr());
+            panic
+        };
+        // 已经在展开另一个 panic 时再次 panic 会终止进程，此时只能丢弃对象析构中的 panic。
+        if let Some(payload) = panic {
+            if !thread::panicking() {
+                panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+/// 从 `Mempool<T>` 中取出的对象，被丢弃时归还到内存池
+pub struct PoolBox<'pool, T> {
+    obj: NonNull<T>,
+    pool: &'pool Mempool<T>,
+}
+
+unsafe impl<T: Send> Send for PoolBox<'_, T> {}
+unsafe impl<T: Sync> Sync for PoolBox<'_, T> {}
+
+impl<T> PoolBox<'_, T> {
+    /// 对象的地址
+    pub fn as_ptr(this: &Self) -> *mut T {
+        this.obj.as_ptr()
+    }
+}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.obj.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.obj.as_mut() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe {
+            rte_mempool_put(self.pool.raw.as_ptr(), self.obj.as_ptr() as *mut c_void);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! 协议头部视图
+//!
+//! `EtherHdr`、`Ipv4Hdr`、`Ipv6Hdr`、`UdpHdr`、`TcpHdr`、`IcmpHdr` 直接包装从头部起始位置开始的字节切片，
+//! 不复制数据。创建视图时检查长度以及头部中的长度字段，之后的访问不会越界。
+//! 所有 getter 返回主机字节序的值，setter 接受主机字节序的值，
+//! 与网络字节序之间的转换由视图完成。
+//!
+//! 视图对缓冲区类型是泛型的：`&[u8]` 上的视图只读，`&mut [u8]` 上的视图可以修改。
+//! `into_payload`/`into_payload_mut` 消耗当前视图并返回其负载，用于逐层解析：
+//!
+//! ```ignore
+//! let eth = mbuf.ether_mut()?;
+//! if eth.ether_type() == RTE_ETHER_TYPE_IPV4 as u16 {
+//!     let mut ip = Ipv4Hdr::new(eth.into_payload_mut())?;
+//!     ip.set_ttl(ip.ttl() - 1);
+//!     ip.update_checksum();
+//! }
+//! ```
+//!
+//! VXLAN、GENEVE、GRE 隧道由 `Mbuf::tunnel` 识别，`TunnelEncap` 和 `Mbuf::decap`
+//! 负责封装和解封装；`Mbuf::detect_ptype` 在软件中识别数据包类型。
+
+mod arp;
+mod builder;
+mod ether;
+mod icmp;
+mod ipv4;
+mod ipv6;
+mod ptype;
+mod tcp;
+mod tunnel;
+mod udp;
+mod vlan;
+
+pub use arp::ArpHdr;
+pub use builder::{ChecksumMode, PacketBuilder};
+pub use ether::EtherHdr;
+pub use icmp::{
+    IcmpHdr, NdMsg, ICMPV6_ECHO_REPLY, ICMPV6_ECHO_REQUEST, ICMPV6_NEIGHBOR_ADVERT,
+    ICMPV6_NEIGHBOR_SOLICIT,
+};
+pub use ipv4::Ipv4Hdr;
+pub use ipv6::{Ipv6ExtHdr, Ipv6ExtHeaders, Ipv6Hdr};
+pub use ptype::{HdrLens, L2Type, L3Type, L4Type, PacketType, TunnelType};
+pub use tcp::{TcpFlags, TcpHdr};
+pub use tunnel::{GeneveHdr, GreHdr, Tunnel, TunnelEncap, TunnelLayout, VxlanHdr};
+pub use udp::UdpHdr;
+pub use vlan::VlanTag;
+
+use crate::error::{DpdkError, Result};
+use crate::mbuf::Mbuf;
+
+impl Mbuf {
+    /// 第一个段开头的以太网头部
+    ///
+    /// 视图只覆盖第一个段，多段数据包需要先 `linearize`。
+    pub fn ether(&self) -> Result<EtherHdr<&[u8]>> {
+        EtherHdr::new(self.data())
+    }
+
+    /// 第一个段开头的可写以太网头部
+    ///
+    /// # Panics
+    ///
+    /// 数据区与其他 mbuf 共享时会 panic，见 `data_mut`。
+    pub fn ether_mut(&mut self) -> Result<EtherHdr<&mut [u8]>> {
+        EtherHdr::new(self.data_mut())
+    }
+}
+
+/// 检查缓冲区是否能容纳 `need` 字节的头部
+fn check_len(what: &str, len: usize, need: usize) -> Result<()> {
+    if len < need {
+        return Err(DpdkError::invalid(format!(
+            "{}头部需要 {} 字节，缓冲区只有 {} 字节",
+            what, need, len
+        )));
+    }
+    Ok(())
+}
+
+fn get_u16(buf: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes([buf[off], buf[off + 1]])
+}
+
+fn set_u16(buf: &mut [u8], off: usize, value: u16) {
+    buf[off..off + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn get_u32(buf: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+fn set_u32(buf: &mut [u8], off: usize, value: u32) {
+    buf[off..off + 4].copy_from_slice(&value.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_big_endian() {
+        let mut buf = [0u8; 6];
+        set_u16(&mut buf, 1, 0x1234);
+        assert_eq!(buf, [0, 0x12, 0x34, 0, 0, 0]);
+        assert_eq!(get_u16(&buf, 1), 0x1234);
+
+        set_u32(&mut buf, 2, 0xdead_beef);
+        assert_eq!(buf, [0, 0x12, 0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(get_u32(&buf, 2), 0xdead_beef);
+        assert_eq!(get_u32(&buf, 0), 0x0012_dead);
+    }
+
+    #[test]
+    fn check_len_reports_shortfall() {
+        assert!(check_len("UDP ", 8, 8).is_ok());
+        assert!(check_len("UDP ", 7, 8).is_err());
+    }
+}
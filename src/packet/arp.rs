@@ -0,0 +1,155 @@
+//! ARP 头部（`rte_arp_hdr`）
+
+use super::ether::MacFmt;
+use super::{check_len, get_u16, set_u16};
+use crate::error::{DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::net::Ipv4Addr;
+
+const HRD: usize = 0;
+const PRO: usize = 2;
+const HLN: usize = 4;
+const PLN: usize = 5;
+const OPCODE: usize = 6;
+const SHA: usize = 8;
+const SIP: usize = 14;
+const THA: usize = 18;
+const TIP: usize = 24;
+
+const HDR_LEN: usize = 28;
+
+/// 以太网上 IPv4 ARP 头部视图
+///
+/// 创建时检查硬件类型、协议类型和地址长度，其他类型的 ARP 报文返回错误。
+#[derive(Clone, Copy)]
+pub struct ArpHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> ArpHdr<T> {
+    /// 头部长度
+    pub const LEN: usize = HDR_LEN;
+
+    /// 在 ARP 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("ARP ", buf.as_ref().len(), HDR_LEN)?;
+        let b = buf.as_ref();
+        if get_u16(b, HRD) as u32 != constants::RTE_ARP_HRD_ETHER
+            || get_u16(b, PRO) as u32 != constants::RTE_ETHER_TYPE_IPV4
+            || b[HLN] != 6
+            || b[PLN] != 4
+        {
+            return Err(DpdkError::invalid("只支持以太网上的 IPv4 ARP"));
+        }
+        Ok(Self { buf })
+    }
+
+    /// 操作码，如 `RTE_ARP_OP_REQUEST`
+    pub fn opcode(&self) -> u16 {
+        get_u16(self.buf.as_ref(), OPCODE)
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.opcode() as u32 == constants::RTE_ARP_OP_REQUEST
+    }
+
+    pub fn is_reply(&self) -> bool {
+        self.opcode() as u32 == constants::RTE_ARP_OP_REPLY
+    }
+
+    pub fn sender_mac(&self) -> [u8; 6] {
+        self.mac(SHA)
+    }
+
+    pub fn sender_ip(&self) -> Ipv4Addr {
+        self.ip(SIP)
+    }
+
+    pub fn target_mac(&self) -> [u8; 6] {
+        self.mac(THA)
+    }
+
+    pub fn target_ip(&self) -> Ipv4Addr {
+        self.ip(TIP)
+    }
+
+    /// 是否是免费 ARP（发送方和目标 IP 相同）
+    pub fn is_gratuitous(&self) -> bool {
+        self.sender_ip() == self.target_ip()
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    fn mac(&self, off: usize) -> [u8; 6] {
+        let mut mac = [0u8; 6];
+        mac.copy_from_slice(&self.buf.as_ref()[off..off + 6]);
+        mac
+    }
+
+    fn ip(&self, off: usize) -> Ipv4Addr {
+        let b = self.buf.as_ref();
+        Ipv4Addr::new(b[off], b[off + 1], b[off + 2], b[off + 3])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> ArpHdr<T> {
+    /// 在缓冲区上写入以太网 IPv4 ARP 的固定字段后创建视图，操作码和地址需要另外设置
+    pub fn init(mut buf: T) -> Result<Self> {
+        check_len("ARP ", buf.as_ref().len(), HDR_LEN)?;
+        let b = buf.as_mut();
+        set_u16(b, HRD, constants::RTE_ARP_HRD_ETHER as u16);
+        set_u16(b, PRO, constants::RTE_ETHER_TYPE_IPV4 as u16);
+        b[HLN] = 6;
+        b[PLN] = 4;
+        b[OPCODE..HDR_LEN].fill(0);
+        Ok(Self { buf })
+    }
+
+    pub fn set_opcode(&mut self, opcode: u16) {
+        set_u16(self.buf.as_mut(), OPCODE, opcode);
+    }
+
+    pub fn set_sender_mac(&mut self, mac: [u8; 6]) {
+        self.buf.as_mut()[SHA..SHA + 6].copy_from_slice(&mac);
+    }
+
+    pub fn set_sender_ip(&mut self, ip: Ipv4Addr) {
+        self.buf.as_mut()[SIP..SIP + 4].copy_from_slice(&ip.octets());
+    }
+
+    pub fn set_target_mac(&mut self, mac: [u8; 6]) {
+        self.buf.as_mut()[THA..THA + 6].copy_from_slice(&mac);
+    }
+
+    pub fn set_target_ip(&mut self, ip: Ipv4Addr) {
+        self.buf.as_mut()[TIP..TIP + 4].copy_from_slice(&ip.octets());
+    }
+
+    /// 把请求原地改写为应答：原发送方成为目标，`mac` 作为发送方 MAC，原目标 IP 作为发送方 IP
+    pub fn make_reply(&mut self, mac: [u8; 6]) {
+        let sender_mac = self.sender_mac();
+        let sender_ip = self.sender_ip();
+        let target_ip = self.target_ip();
+        self.set_opcode(constants::RTE_ARP_OP_REPLY as u16);
+        self.set_target_mac(sender_mac);
+        self.set_target_ip(sender_ip);
+        self.set_sender_mac(mac);
+        self.set_sender_ip(target_ip);
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for ArpHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArpHdr")
+            .field("opcode", &self.opcode())
+            .field("sender_mac", &MacFmt(self.sender_mac()))
+            .field("sender_ip", &self.sender_ip())
+            .field("target_mac", &MacFmt(self.target_mac()))
+            .field("target_ip", &self.target_ip())
+            .finish()
+    }
+}
@@ -0,0 +1,614 @@
+//! 数据包构建器
+
+use super::{icmp, ipv6, set_u16, set_u32, tcp, udp, Ipv4Hdr, TcpFlags};
+use crate::error::{DpdkError, Result};
+use crate::ethdev::TxOffload;
+use crate::mbuf::{Mbuf, PktMbufPool};
+use crate::*;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::os::raw::c_void;
+
+/// 校验和的计算方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumMode {
+    /// 由软件计算所有校验和
+    Software,
+    /// 在 mbuf 上标记 IP、TCP、UDP 校验和 offload，由网卡计算
+    ///
+    /// 参数是端口开启的发送 offload（`Port::tx_offloads`），其中没有开启的层由软件计算。
+    /// ICMP 校验和没有硬件 offload，总是由软件计算。
+    Offload(TxOffload),
+    /// 校验和字段留空，由调用者之后处理，例如用 `Mbuf::request_tx_offload` 按端口能力选择
+    Deferred,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum L3 {
+    Ipv4 { src: Ipv4Addr, dst: Ipv4Addr },
+    Ipv6 { src: Ipv6Addr, dst: Ipv6Addr },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum L4 {
+    Udp {
+        src_port: u16,
+        dst_port: u16,
+    },
+    Tcp {
+        src_port: u16,
+        dst_port: u16,
+        flags: TcpFlags,
+    },
+    Icmp {
+        icmp_type: u8,
+        code: u8,
+        ident: u16,
+        seq: u16,
+    },
+}
+
+/// 按层组合以太网帧并写入 mbuf
+///
+/// 依次指定以太网头部、可选的 VLAN 标签、IPv4 或 IPv6 头部、UDP、TCP 或 ICMP 头部
+/// 以及负载。以太网类型、协议号、各层长度和校验和都自动填写。
+/// 构建器可以重复使用，每次 `build` 生成一个新的数据包。
+///
+/// ```ignore
+/// let pkt = PacketBuilder::new(src_mac, dst_mac)
+///     .ipv4(Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 2))
+///     .udp(1024, 4789)
+///     .payload(b"hello".to_vec())
+///     .build(&pool)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PacketBuilder {
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    ether_type: u16,
+    vlan_tci: Option<u16>,
+    l3: Option<L3>,
+    ttl: u8,
+    tos: u8,
+    packet_id: u16,
+    dont_fragment: bool,
+    flow_label: u32,
+    l4: Option<L4>,
+    tcp_seq: u32,
+    tcp_ack: u32,
+    tcp_window: u16,
+    payload: Vec<u8>,
+    checksum: ChecksumMode,
+}
+
+impl PacketBuilder {
+    /// 以源、目的 MAC 地址开始构建
+    pub fn new(src_mac: [u8; 6], dst_mac: [u8; 6]) -> Self {
+        Self {
+            src_mac,
+            dst_mac,
+            ether_type: 0,
+            vlan_tci: None,
+            l3: None,
+            ttl: 64,
+            tos: 0,
+            packet_id: 0,
+            dont_fragment: false,
+            flow_label: 0,
+            l4: None,
+            tcp_seq: 0,
+            tcp_ack: 0,
+            tcp_window: u16::MAX,
+            payload: Vec::new(),
+            checksum: ChecksumMode::Software,
+        }
+    }
+
+    /// 以太网类型，只在没有 IP 层时使用
+    pub fn ether_type(mut self, ether_type: u16) -> Self {
+        self.ether_type = ether_type;
+        self
+    }
+
+    /// 插入 802.1Q VLAN 标签
+    pub fn vlan(mut self, tci: u16) -> Self {
+        self.vlan_tci = Some(tci);
+        self
+    }
+
+    /// IPv4 头部
+    pub fn ipv4(mut self, src: Ipv4Addr, dst: Ipv4Addr) -> Self {
+        self.l3 = Some(L3::Ipv4 { src, dst });
+        self
+    }
+
+    /// IPv6 头部
+    pub fn ipv6(mut self, src: Ipv6Addr, dst: Ipv6Addr) -> Self {
+        self.l3 = Some(L3::Ipv6 { src, dst });
+        self
+    }
+
+    /// IPv4 TTL 或 IPv6 hop limit，默认为 64
+    pub fn ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// IPv4 服务类型或 IPv6 traffic class
+    pub fn tos(mut self, tos: u8) -> Self {
+        self.tos = tos;
+        self
+    }
+
+    /// IPv4 标识
+    pub fn packet_id(mut self, id: u16) -> Self {
+        self.packet_id = id;
+        self
+    }
+
+    /// IPv4 DF 标志
+    pub fn dont_fragment(mut self, df: bool) -> Self {
+        self.dont_fragment = df;
+        self
+    }
+
+    /// IPv6 流标签，只使用低 20 位
+    pub fn flow_label(mut self, label: u32) -> Self {
+        self.flow_label = label;
+        self
+    }
+
+    /// UDP 头部
+    pub fn udp(mut self, src_port: u16, dst_port: u16) -> Self {
+        self.l4 = Some(L4::Udp { src_port, dst_port });
+        self
+    }
+
+    /// TCP 头部，不带选项
+    pub fn tcp(mut self, src_port: u16, dst_port: u16, flags: TcpFlags) -> Self {
+        self.l4 = Some(L4::Tcp {
+            src_port,
+            dst_port,
+            flags,
+        });
+        self
+    }
+
+    /// TCP 序列号和确认号
+    pub fn tcp_seq(mut self, seq: u32, ack: u32) -> Self {
+        self.tcp_seq = seq;
+        self.tcp_ack = ack;
+        self
+    }
+
+    /// TCP 接收窗口，默认为 65535
+    pub fn tcp_window(mut self, window: u16) -> Self {
+        self.tcp_window = window;
+        self
+    }
+
+    /// ICMP 头部，IPv6 上为 ICMPv6
+    ///
+    /// `ident` 和 `seq` 填写在头部的后 4 字节，用于 echo 等消息。
+    pub fn icmp(mut self, icmp_type: u8, code: u8, ident: u16, seq: u16) -> Self {
+        self.l4 = Some(L4::Icmp {
+            icmp_type,
+            code,
+            ident,
+            seq,
+        });
+        self
+    }
+
+    /// 负载
+    pub fn payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    /// 校验和的计算方式，默认为 `ChecksumMode::Software`
+    pub fn checksum(mut self, mode: ChecksumMode) -> Self {
+        self.checksum = mode;
+        self
+    }
+
+    /// L2 头部长度（含 VLAN 标签）
+    fn l2_len(&self) -> usize {
+        let vlan = if self.vlan_tci.is_some() {
+            constants::RTE_VLAN_HLEN as usize
+        } else {
+            0
+        };
+        constants::RTE_ETHER_HDR_LEN as usize + vlan
+    }
+
+    fn l3_len(&self) -> usize {
+        match self.l3 {
+            Some(L3::Ipv4 { .. }) => Ipv4Hdr::<&[u8]>::MIN_LEN,
+            Some(L3::Ipv6 { .. }) => ipv6::HDR_LEN,
+            None => 0,
+        }
+    }
+
+    fn l4_len(&self) -> usize {
+        match self.l4 {
+            Some(L4::Udp { .. }) => udp::HDR_LEN,
+            Some(L4::Tcp { .. }) => tcp::HDR_MIN_LEN,
+            Some(L4::Icmp { .. }) => icmp::HDR_LEN,
+            None => 0,
+        }
+    }
+
+    /// 整个以太网帧的长度
+    pub fn frame_len(&self) -> usize {
+        self.l2_len() + self.l3_len() + self.l4_len() + self.payload.len()
+    }
+
+    /// 上层协议号
+    fn proto(&self) -> u8 {
+        let proto = match (self.l3, self.l4) {
+            (_, Some(L4::Udp { .. })) => constants::IPPROTO_UDP,
+            (_, Some(L4::Tcp { .. })) => constants::IPPROTO_TCP,
+            (Some(L3::Ipv6 { .. }), Some(L4::Icmp { .. })) => constants::IPPROTO_ICMPV6,
+            (_, Some(L4::Icmp { .. })) => constants::IPPROTO_ICMP,
+            // 没有已知的上层协议
+            (_, None) => constants::IPPROTO_NONE,
+        };
+        proto as u8
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.l4.is_some() && self.l3.is_none() {
+            return Err(DpdkError::invalid("UDP、TCP 和 ICMP 头部需要 IP 层"));
+        }
+        let l3_payload = self.l4_len() + self.payload.len();
+        let limit = match self.l3 {
+            Some(L3::Ipv4 { .. }) => u16::MAX as usize - self.l3_len(),
+            _ => u16::MAX as usize,
+        };
+        if l3_payload > limit {
+            return Err(DpdkError::invalid(format!(
+                "IP 负载 {} 字节，超过最大值 {}",
+                l3_payload, limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// 从内存池分配 mbuf 并写入数据包
+    pub fn build(&self, pool: &PktMbufPool) -> Result<Mbuf> {
+        let mut mbuf = pool.alloc()?;
+        self.write(&mut mbuf)?;
+        Ok(mbuf)
+    }
+
+    /// 把数据包写入空的 mbuf
+    ///
+    /// 数据包写入第一个段，长度不能超过其尾部剩余空间。
+    pub fn write(&self, mbuf: &mut Mbuf) -> Result<()> {
+        self.validate()?;
+        if !mbuf.is_empty() {
+            return Err(DpdkError::invalid("数据包只能写入空的 mbuf"));
+        }
+        let data = mbuf.append(self.frame_len())?;
+        let ol_flags = self.write_frame(data)?;
+        if ol_flags != 0 {
+            let m = mbuf.as_ptr();
+            unsafe {
+                (*m).ol_flags |= ol_flags;
+                (*m).__bindgen_anon_4.tx_offload = rte_mbuf_tx_offload(
+                    self.l2_len() as u64,
+                    self.l3_len() as u64,
+                    self.l4_len() as u64,
+                    0,
+                    0,
+                    0,
+                    0,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// 把数据包写入长度为 `frame_len` 的缓冲区，返回需要设置的 offload 标志
+    fn write_frame(&self, data: &mut [u8]) -> Result<u64> {
+        let l2_len = self.l2_len();
+        let l3_len = self.l3_len();
+        let l4_len = self.l4_len();
+        let (l2, rest) = data.split_at_mut(l2_len);
+        let (l3, rest) = rest.split_at_mut(l3_len);
+        let (l4, payload) = rest.split_at_mut(l4_len);
+
+        self.write_l2(l2);
+        self.write_l3(l3, (l4_len + payload.len()) as u16);
+        self.write_l4(l4, (l4_len + payload.len()) as u16);
+        payload.copy_from_slice(&self.payload);
+
+        self.fill_checksums(&mut data[l2_len..])
+    }
+
+    fn write_l2(&self, l2: &mut [u8]) {
+        l2[0..6].copy_from_slice(&self.dst_mac);
+        l2[6..12].copy_from_slice(&self.src_mac);
+        let ether_type = match self.l3 {
+            Some(L3::Ipv4 { .. }) => constants::RTE_ETHER_TYPE_IPV4 as u16,
+            Some(L3::Ipv6 { .. }) => constants::RTE_ETHER_TYPE_IPV6 as u16,
+            None => self.ether_type,
+        };
+        match self.vlan_tci {
+            Some(tci) => {
+                set_u16(l2, 12, constants::RTE_ETHER_TYPE_VLAN as u16);
+                set_u16(l2, 14, tci);
+                set_u16(l2, 16, ether_type);
+            }
+            None => set_u16(l2, 12, ether_type),
+        }
+    }
+
+    fn write_l3(&self, l3: &mut [u8], l3_payload_len: u16) {
+        match self.l3 {
+            Some(L3::Ipv4 { src, dst }) => {
+                l3[0] = constants::RTE_IPV4_VHL_DEF as u8;
+                l3[1] = self.tos;
+                set_u16(l3, 2, l3.len() as u16 + l3_payload_len);
+                set_u16(l3, 4, self.packet_id);
+                let frag = if self.dont_fragment {
+                    constants::RTE_IPV4_HDR_DF_FLAG as u16
+                } else {
+                    0
+                };
+                set_u16(l3, 6, frag);
+                l3[8] = self.ttl;
+                l3[9] = self.proto();
+                set_u16(l3, 10, 0);
+                l3[12..16].copy_from_slice(&src.octets());
+                l3[16..20].copy_from_slice(&dst.octets());
+            }
+            Some(L3::Ipv6 { src, dst }) => {
+                let vtc_flow =
+                    (6 << 28) | ((self.tos as u32) << 20) | (self.flow_label & 0x000f_ffff);
+                set_u32(l3, 0, vtc_flow);
+                set_u16(l3, 4, l3_payload_len);
+                l3[6] = self.proto();
+                l3[7] = self.ttl;
+                l3[8..24].copy_from_slice(&src.octets());
+                l3[24..40].copy_from_slice(&dst.octets());
+            }
+            None => {}
+        }
+    }
+
+    fn write_l4(&self, l4: &mut [u8], l4_total_len: u16) {
+        match self.l4 {
+            Some(L4::Udp { src_port, dst_port }) => {
+                set_u16(l4, 0, src_port);
+                set_u16(l4, 2, dst_port);
+                set_u16(l4, 4, l4_total_len);
+                set_u16(l4, 6, 0);
+            }
+            Some(L4::Tcp {
+                src_port,
+                dst_port,
+                flags,
+            }) => {
+                set_u16(l4, 0, src_port);
+                set_u16(l4, 2, dst_port);
+                set_u32(l4, 4, self.tcp_seq);
+                set_u32(l4, 8, self.tcp_ack);
+                l4[12] = ((tcp::HDR_MIN_LEN / 4) as u8) << 4;
+                l4[13] = flags.bits();
+                set_u16(l4, 14, self.tcp_window);
+                set_u16(l4, 16, 0);
+                set_u16(l4, 18, 0);
+            }
+            Some(L4::Icmp {
+                icmp_type,
+                code,
+                ident,
+                seq,
+            }) => {
+                l4[0] = icmp_type;
+                l4[1] = code;
+                set_u16(l4, 2, 0);
+                set_u16(l4, 4, ident);
+                set_u16(l4, 6, seq);
+            }
+            None => {}
+        }
+    }
+
+    /// 计算或标记校验和，返回需要设置的 offload 标志
+    ///
+    /// `l3` 从 IP 头部开始，到数据包末尾结束。
+    fn fill_checksums(&self, l3: &mut [u8]) -> Result<u64> {
+        if self.checksum == ChecksumMode::Deferred {
+            return Ok(0);
+        }
+        let enabled = match self.checksum {
+            ChecksumMode::Offload(enabled) => enabled,
+            _ => TxOffload::empty(),
+        };
+        let l3_len = self.l3_len();
+        let mut ol_flags = 0;
+
+        // L4 校验和字段相对 L3 头部的偏移；ICMP 没有硬件 offload。
+        let (l4_cksum, l4_flag) = match self.l4 {
+            Some(L4::Udp { .. }) => (Some(l3_len + 6), constants::RTE_MBUF_F_TX_UDP_CKSUM),
+            Some(L4::Tcp { .. }) => (Some(l3_len + 16), constants::RTE_MBUF_F_TX_TCP_CKSUM),
+            Some(L4::Icmp { .. }) => (Some(l3_len + 2), 0),
+            None => (None, 0),
+        };
+        let l4_offload = match self.l4 {
+            Some(L4::Udp { .. }) => enabled.contains(TxOffload::UDP_CKSUM),
+            Some(L4::Tcp { .. }) => enabled.contains(TxOffload::TCP_CKSUM),
+            _ => false,
+        };
+
+        match self.l3 {
+            Some(L3::Ipv4 { .. }) => {
+                // 头部校验和字段在 `write_l3` 中已经清零，这正是硬件 offload 的要求。
+                if enabled.contains(TxOffload::IPV4_CKSUM) {
+                    ol_flags |= constants::RTE_MBUF_F_TX_IPV4 | constants::RTE_MBUF_F_TX_IP_CKSUM;
+                } else {
+                    Ipv4Hdr::new(&mut *l3)?.update_checksum();
+                    if l4_offload {
+                        ol_flags |= constants::RTE_MBUF_F_TX_IPV4;
+                    }
+                }
+            }
+            Some(L3::Ipv6 { .. }) => {
+                if l4_offload {
+                    ol_flags |= constants::RTE_MBUF_F_TX_IPV6;
+                }
+            }
+            None => return Ok(0),
+        }
+
+        let Some(off) = l4_cksum else {
+            return Ok(ol_flags);
+        };
+        let ip = l3.as_ptr();
+        let l4 = l3[l3_len..].as_ptr() as *const c_void;
+        // 以下函数的结果按内存中的字节顺序计算，原样写回即为网络字节序。
+        let cksum = if l4_offload {
+            // 硬件 offload 要求校验和字段预先填入伪头部校验和。
+            ol_flags |= l4_flag;
+            match self.l3 {
+                Some(L3::Ipv4 { .. }) => unsafe {
+                    rte_ipv4_phdr_cksum(ip as *const rte_ipv4_hdr, ol_flags)
+                },
+                _ => unsafe { rte_ipv6_phdr_cksum(ip as *const rte_ipv6_hdr, ol_flags) },
+            }
+        } else {
+            match (self.l3, self.l4) {
+                (Some(L3::Ipv4 { .. }), Some(L4::Icmp { .. })) => {
+                    let len = l3.len() - l3_len;
+                    !unsafe { rte_raw_cksum(l4, len) }
+                }
+                (Some(L3::Ipv4 { .. }), _) => unsafe {
+                    rte_ipv4_udptcp_cksum(ip as *const rte_ipv4_hdr, l4)
+                },
+                // ICMPv6 与 UDP、TCP 一样使用伪头部校验和。
+                _ => unsafe { rte_ipv6_udptcp_cksum(ip as *const rte_ipv6_hdr, l4) },
+            }
+        };
+        l3[off..off + 2].copy_from_slice(&cksum.to_ne_bytes());
+        Ok(ol_flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const DST_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+    fn frame(builder: &PacketBuilder) -> (Vec<u8>, u64) {
+        let mut data = vec![0u8; builder.frame_len()];
+        let ol_flags = builder.write_frame(&mut data).unwrap();
+        (data, ol_flags)
+    }
+
+    fn vlan_udp() -> PacketBuilder {
+        PacketBuilder::new(SRC_MAC, DST_MAC)
+            .vlan(100)
+            .ipv4(Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 2))
+            .packet_id(0x1234)
+            .dont_fragment(true)
+            .udp(1024, 4789)
+            .payload(b"hello".to_vec())
+    }
+
+    #[test]
+    fn vlan_ipv4_udp() {
+        let (data, ol_flags) = frame(&vlan_udp());
+        #[rustfmt::skip]
+        let expected = [
+            // 以太网头部和 VLAN 100
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x81, 0x00, 0x00, 0x64, 0x08, 0x00,
+            // IPv4
+            0x45, 0x00, 0x00, 0x21, 0x12, 0x34, 0x40, 0x00, 0x40, 0x11, 0xa5, 0x44,
+            0xc0, 0xa8, 0x01, 0x01, 0xc0, 0xa8, 0x01, 0x02,
+            // UDP
+            0x04, 0x00, 0x12, 0xb5, 0x00, 0x0d, 0x21, 0xf9,
+            b'h', b'e', b'l', b'l', b'o',
+        ];
+        assert_eq!(data, expected);
+        assert_eq!(ol_flags, 0);
+    }
+
+    #[test]
+    fn ipv6_tcp() {
+        let builder = PacketBuilder::new(SRC_MAC, DST_MAC)
+            .ipv6(
+                "2001:db8::1".parse().unwrap(),
+                "2001:db8::2".parse().unwrap(),
+            )
+            .flow_label(0x12345)
+            .tcp(443, 50000, TcpFlags::SYN | TcpFlags::ACK)
+            .tcp_seq(1, 2)
+            .payload(b"hi".to_vec());
+        let (data, ol_flags) = frame(&builder);
+        #[rustfmt::skip]
+        let expected = [
+            // 以太网头部
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x86, 0xdd,
+            // IPv6
+            0x60, 0x01, 0x23, 0x45, 0x00, 0x16, 0x06, 0x40,
+            0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+            // TCP
+            0x01, 0xbb, 0xc3, 0x50, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+            0x50, 0x12, 0xff, 0xff, 0x26, 0xe4, 0x00, 0x00,
+            b'h', b'i',
+        ];
+        assert_eq!(data, expected);
+        assert_eq!(ol_flags, 0);
+    }
+
+    #[test]
+    fn offload_leaves_pseudo_header_checksum() {
+        let builder = vlan_udp().checksum(ChecksumMode::Offload(
+            TxOffload::IPV4_CKSUM | TxOffload::UDP_CKSUM,
+        ));
+        let (data, ol_flags) = frame(&builder);
+        assert_eq!(
+            ol_flags,
+            constants::RTE_MBUF_F_TX_IPV4
+                | constants::RTE_MBUF_F_TX_IP_CKSUM
+                | constants::RTE_MBUF_F_TX_UDP_CKSUM
+        );
+        // IP 头部校验和留空，UDP 校验和字段为伪头部校验和
+        assert_eq!(&data[28..30], &[0, 0]);
+        assert_eq!(&data[44..46], &[0x83, 0x72]);
+
+        let (data, _) = frame(&vlan_udp().checksum(ChecksumMode::Deferred));
+        assert_eq!(&data[28..30], &[0, 0]);
+        assert_eq!(&data[44..46], &[0, 0]);
+    }
+
+    #[test]
+    fn length_limits() {
+        let v4 = PacketBuilder::new(SRC_MAC, DST_MAC)
+            .ipv4(Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST)
+            .udp(1, 2);
+        let max = u16::MAX as usize - 20 - 8;
+        assert!(v4.clone().payload(vec![0; max]).validate().is_ok());
+        assert!(v4.payload(vec![0; max + 1]).validate().is_err());
+
+        let v6 = PacketBuilder::new(SRC_MAC, DST_MAC)
+            .ipv6(Ipv6Addr::LOCALHOST, Ipv6Addr::LOCALHOST)
+            .udp(1, 2);
+        // IPv6 的 payload_len 不含固定头部
+        let max = u16::MAX as usize - 8;
+        assert!(v6.clone().payload(vec![0; max]).validate().is_ok());
+        assert!(v6.payload(vec![0; max + 1]).validate().is_err());
+
+        let no_l3 = PacketBuilder::new(SRC_MAC, DST_MAC).udp(1, 2);
+        assert!(no_l3.validate().is_err());
+    }
+}
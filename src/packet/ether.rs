@@ -0,0 +1,315 @@
+//! 以太网头部（`rte_ether_hdr`）
+
+use super::vlan::{is_vlan_tpid, VlanTag, MAX_VLAN_TAGS};
+use super::{check_len, get_u16, set_u16};
+use crate::error::Result;
+use crate::*;
+use std::fmt;
+
+const DST_ADDR: usize = 0;
+const SRC_ADDR: usize = 6;
+const ETHER_TYPE: usize = 12;
+const VLAN_HLEN: usize = constants::RTE_VLAN_HLEN as usize;
+
+/// 以太网头部视图
+///
+/// 最多识别两层 VLAN 标签（802.1Q 或 QinQ）。`ether_type`、`header_len` 和 `payload`
+/// 都跳过标签，返回被封装的协议；偏移 12 处的原始字段见 `outer_ether_type`。
+#[derive(Clone, Copy)]
+pub struct EtherHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> EtherHdr<T> {
+    /// 不含 VLAN 标签的头部长度
+    pub const LEN: usize = constants::RTE_ETHER_HDR_LEN as usize;
+
+    /// 在以太网头部起始位置的缓冲区上创建视图
+    ///
+    /// 缓冲区必须能容纳头部中出现的 VLAN 标签。
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("以太网", buf.as_ref().len(), Self::LEN)?;
+        let mut need = Self::LEN;
+        let mut off = ETHER_TYPE;
+        for _ in 0..MAX_VLAN_TAGS {
+            if !is_vlan_tpid(get_u16(buf.as_ref(), off)) {
+                break;
+            }
+            need += VLAN_HLEN;
+            check_len("VLAN ", buf.as_ref().len(), need)?;
+            off += VLAN_HLEN;
+        }
+        Ok(Self { buf })
+    }
+
+    /// 目的 MAC 地址
+    pub fn dst_addr(&self) -> [u8; 6] {
+        self.addr(DST_ADDR)
+    }
+
+    /// 源 MAC 地址
+    pub fn src_addr(&self) -> [u8; 6] {
+        self.addr(SRC_ADDR)
+    }
+
+    /// VLAN 标签之后的以太网类型，如 `RTE_ETHER_TYPE_IPV4`
+    pub fn ether_type(&self) -> u16 {
+        get_u16(self.buf.as_ref(), self.ether_type_offset())
+    }
+
+    /// 源 MAC 地址之后的第一个类型字段，有 VLAN 标签时为外层 TPID
+    pub fn outer_ether_type(&self) -> u16 {
+        get_u16(self.buf.as_ref(), ETHER_TYPE)
+    }
+
+    /// VLAN 标签的层数，最多为 2
+    pub fn vlan_count(&self) -> usize {
+        let buf = self.buf.as_ref();
+        let mut count = 0;
+        let mut off = ETHER_TYPE;
+        // 创建视图后类型字段可能被改写，只统计缓冲区内完整的标签
+        while count < MAX_VLAN_TAGS
+            && off + VLAN_HLEN + 2 <= buf.len()
+            && is_vlan_tpid(get_u16(buf, off))
+        {
+            count += 1;
+            off += VLAN_HLEN;
+        }
+        count
+    }
+
+    /// 外层 VLAN 标签，只有一层时即为该标签
+    pub fn vlan(&self) -> Option<VlanTag> {
+        self.vlan_at(0)
+    }
+
+    /// QinQ 的内层 VLAN 标签
+    pub fn inner_vlan(&self) -> Option<VlanTag> {
+        self.vlan_at(1)
+    }
+
+    /// 头部长度，含 VLAN 标签
+    pub fn header_len(&self) -> usize {
+        Self::LEN + self.vlan_count() * VLAN_HLEN
+    }
+
+    /// 头部和 VLAN 标签之后的数据
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[self.header_len()..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    fn addr(&self, off: usize) -> [u8; 6] {
+        let mut addr = [0u8; 6];
+        addr.copy_from_slice(&self.buf.as_ref()[off..off + 6]);
+        addr
+    }
+
+    fn ether_type_offset(&self) -> usize {
+        ETHER_TYPE + self.vlan_count() * VLAN_HLEN
+    }
+
+    fn vlan_at(&self, index: usize) -> Option<VlanTag> {
+        if index >= self.vlan_count() {
+            return None;
+        }
+        let off = ETHER_TYPE + index * VLAN_HLEN;
+        Some(VlanTag {
+            tpid: get_u16(self.buf.as_ref(), off),
+            tci: get_u16(self.buf.as_ref(), off + 2),
+        })
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> EtherHdr<T> {
+    pub fn set_dst_addr(&mut self, addr: [u8; 6]) {
+        self.buf.as_mut()[DST_ADDR..DST_ADDR + 6].copy_from_slice(&addr);
+    }
+
+    pub fn set_src_addr(&mut self, addr: [u8; 6]) {
+        self.buf.as_mut()[SRC_ADDR..SRC_ADDR + 6].copy_from_slice(&addr);
+    }
+
+    /// 设置 VLAN 标签之后的以太网类型
+    pub fn set_ether_type(&mut self, ether_type: u16) {
+        let off = self.ether_type_offset();
+        set_u16(self.buf.as_mut(), off, ether_type);
+    }
+
+    /// 修改已有 VLAN 标签的 TCI，不存在该层标签时不做修改并返回 `false`
+    ///
+    /// `index` 为 0 表示外层标签，为 1 表示 QinQ 的内层标签。
+    pub fn set_vlan_tci(&mut self, index: usize, tci: u16) -> bool {
+        if index >= self.vlan_count() {
+            return false;
+        }
+        let off = ETHER_TYPE + index * VLAN_HLEN + 2;
+        set_u16(self.buf.as_mut(), off, tci);
+        true
+    }
+
+    /// 交换源和目的 MAC 地址
+    pub fn swap_addrs(&mut self) {
+        let (dst, rest) = self.buf.as_mut().split_at_mut(SRC_ADDR);
+        dst.swap_with_slice(&mut rest[..6]);
+    }
+
+    /// 头部和 VLAN 标签之后的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let start = self.header_len();
+        &mut self.buf.as_mut()[start..]
+    }
+}
+
+impl<'a> EtherHdr<&'a [u8]> {
+    /// 消耗视图，返回头部之后的数据，用于解析下一层头部
+    pub fn into_payload(self) -> &'a [u8] {
+        let start = self.header_len();
+        &self.buf[start..]
+    }
+}
+
+impl<'a> EtherHdr<&'a mut [u8]> {
+    /// 消耗视图，返回头部之后的可写数据，用于解析下一层头部
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        let start = self.header_len();
+        &mut self.buf[start..]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for EtherHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EtherHdr")
+            .field("dst_addr", &MacFmt(self.dst_addr()))
+            .field("src_addr", &MacFmt(self.src_addr()))
+            .field("vlan", &self.vlan())
+            .field("inner_vlan", &self.inner_vlan())
+            .field("ether_type", &format_args!("{:#06x}", self.ether_type()))
+            .finish()
+    }
+}
+
+/// 按 `xx:xx:xx:xx:xx:xx` 格式输出 MAC 地址
+pub(super) struct MacFmt(pub(super) [u8; 6]);
+
+impl fmt::Debug for MacFmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let a = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a[0], a[1], a[2], a[3], a[4], a[5]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DST: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const SRC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+
+    /// 构造以太网头部，`tags` 为依次出现的 (TPID, TCI)，之后跟 4 字节负载
+    fn frame(tags: &[(u16, u16)], ether_type: u16) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&DST);
+        buf.extend_from_slice(&SRC);
+        for &(tpid, tci) in tags {
+            buf.extend_from_slice(&tpid.to_be_bytes());
+            buf.extend_from_slice(&tci.to_be_bytes());
+        }
+        buf.extend_from_slice(&ether_type.to_be_bytes());
+        buf.extend_from_slice(&[0xaa, 0xbb, 0xcc, 0xdd]);
+        buf
+    }
+
+    const VLAN: u16 = constants::RTE_ETHER_TYPE_VLAN as u16;
+    const QINQ: u16 = constants::RTE_ETHER_TYPE_QINQ as u16;
+    const IPV4: u16 = constants::RTE_ETHER_TYPE_IPV4 as u16;
+
+    #[test]
+    fn untagged() {
+        let buf = frame(&[], IPV4);
+        let eth = EtherHdr::new(&buf[..]).unwrap();
+        assert_eq!(eth.dst_addr(), DST);
+        assert_eq!(eth.src_addr(), SRC);
+        assert_eq!(eth.vlan_count(), 0);
+        assert_eq!(eth.vlan(), None);
+        assert_eq!(eth.header_len(), 14);
+        assert_eq!(eth.ether_type(), IPV4);
+        assert_eq!(eth.payload(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+    }
+
+    #[test]
+    fn single_vlan() {
+        let tci = VlanTag::tci_from_parts(5, true, 100);
+        let buf = frame(&[(VLAN, tci)], IPV4);
+        let eth = EtherHdr::new(&buf[..]).unwrap();
+        assert_eq!(eth.vlan_count(), 1);
+        assert_eq!(eth.header_len(), 18);
+        assert_eq!(eth.outer_ether_type(), VLAN);
+        assert_eq!(eth.ether_type(), IPV4);
+        let vlan = eth.vlan().unwrap();
+        assert_eq!(
+            (vlan.tpid, vlan.pcp(), vlan.dei(), vlan.vid()),
+            (VLAN, 5, true, 100)
+        );
+        assert_eq!(eth.inner_vlan(), None);
+        assert_eq!(eth.payload(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+    }
+
+    #[test]
+    fn qinq() {
+        let buf = frame(&[(QINQ, 10), (VLAN, 20)], IPV4);
+        let eth = EtherHdr::new(&buf[..]).unwrap();
+        assert_eq!(eth.vlan_count(), 2);
+        assert_eq!(eth.header_len(), 22);
+        assert_eq!(eth.vlan().map(|v| (v.tpid, v.vid())), Some((QINQ, 10)));
+        assert_eq!(
+            eth.inner_vlan().map(|v| (v.tpid, v.vid())),
+            Some((VLAN, 20))
+        );
+        assert_eq!(eth.ether_type(), IPV4);
+        assert_eq!(eth.payload(), &[0xaa, 0xbb, 0xcc, 0xdd]);
+    }
+
+    #[test]
+    fn third_tag_is_payload() {
+        // 最多识别两层标签，第三个 TPID 被当作以太网类型
+        let buf = frame(&[(QINQ, 10), (VLAN, 20), (VLAN, 30)], IPV4);
+        let eth = EtherHdr::new(&buf[..]).unwrap();
+        assert_eq!(eth.vlan_count(), 2);
+        assert_eq!(eth.ether_type(), VLAN);
+    }
+
+    #[test]
+    fn truncated() {
+        let buf = frame(&[], IPV4);
+        assert!(EtherHdr::new(&buf[..13]).is_err());
+        // 类型字段是 VLAN TPID，但标签不完整
+        let buf = frame(&[(VLAN, 1)], IPV4);
+        assert!(EtherHdr::new(&buf[..17]).is_err());
+        assert!(EtherHdr::new(&buf[..18]).is_ok());
+        let buf = frame(&[(QINQ, 1), (VLAN, 2)], IPV4);
+        assert!(EtherHdr::new(&buf[..21]).is_err());
+    }
+
+    #[test]
+    fn setters() {
+        let mut buf = frame(&[(QINQ, 10), (VLAN, 20)], IPV4);
+        let mut eth = EtherHdr::new(&mut buf[..]).unwrap();
+        assert!(eth.set_vlan_tci(1, 0x2345));
+        assert!(!eth.set_vlan_tci(2, 1));
+        eth.set_ether_type(constants::RTE_ETHER_TYPE_IPV6 as u16);
+        eth.swap_addrs();
+        assert_eq!(eth.dst_addr(), SRC);
+        assert_eq!(eth.src_addr(), DST);
+        assert_eq!(&buf[18..22], &[0x23, 0x45, 0x86, 0xdd]);
+        assert_eq!(&buf[14..16], &[0, 10]);
+    }
+}
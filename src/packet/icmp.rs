@@ -0,0 +1,334 @@
+//! ICMP、ICMPv6 头部与 IPv6 邻居发现报文
+
+use super::ether::MacFmt;
+use super::{check_len, get_u16, set_u16};
+use crate::error::{DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::os::raw::c_void;
+
+const TYPE: usize = 0;
+const CODE: usize = 1;
+const CKSUM: usize = 2;
+const IDENT: usize = 4;
+const SEQ_NB: usize = 6;
+
+pub(super) const HDR_LEN: usize = 8;
+
+/// ICMPv6 类型（RFC 4443、RFC 4861），DPDK 22.11 中没有定义
+pub const ICMPV6_ECHO_REQUEST: u8 = 128;
+pub const ICMPV6_ECHO_REPLY: u8 = 129;
+pub const ICMPV6_NEIGHBOR_SOLICIT: u8 = 135;
+pub const ICMPV6_NEIGHBOR_ADVERT: u8 = 136;
+
+/// 邻居发现报文的固定部分：类型、代码、校验和、标志和目标地址
+const ND_FLAGS: usize = 4;
+const ND_TARGET: usize = 8;
+const ND_HDR_LEN: usize = 24;
+
+/// 源/目标链路层地址选项，以太网上为 8 字节
+const ND_OPT_SOURCE_LL_ADDR: u8 = 1;
+const ND_OPT_TARGET_LL_ADDR: u8 = 2;
+const ND_OPT_LL_ADDR_LEN: usize = 8;
+
+const ND_FLAG_ROUTER: u8 = 0x80;
+const ND_FLAG_SOLICITED: u8 = 0x40;
+const ND_FLAG_OVERRIDE: u8 = 0x20;
+
+/// ICMP 头部视图，IPv4 与 IPv6 共用
+///
+/// 视图覆盖整个 ICMP 报文，通常由 `Ipv4Hdr::into_payload` 或 IPv6 上层数据创建。
+/// `ident` 和 `seq_nb` 只对回显报文有意义。
+#[derive(Clone, Copy)]
+pub struct IcmpHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> IcmpHdr<T> {
+    /// 头部长度
+    pub const LEN: usize = HDR_LEN;
+
+    /// 在 ICMP 报文起始位置的缓冲区上创建视图，缓冲区应恰好包含整个报文
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("ICMP ", buf.as_ref().len(), HDR_LEN)?;
+        Ok(Self { buf })
+    }
+
+    pub fn icmp_type(&self) -> u8 {
+        self.buf.as_ref()[TYPE]
+    }
+
+    pub fn code(&self) -> u8 {
+        self.buf.as_ref()[CODE]
+    }
+
+    pub fn checksum(&self) -> u16 {
+        get_u16(self.buf.as_ref(), CKSUM)
+    }
+
+    pub fn ident(&self) -> u16 {
+        get_u16(self.buf.as_ref(), IDENT)
+    }
+
+    pub fn seq_nb(&self) -> u16 {
+        get_u16(self.buf.as_ref(), SEQ_NB)
+    }
+
+    /// 是否是 ICMPv4 回显请求
+    pub fn is_echo_request(&self) -> bool {
+        self.icmp_type() as u32 == constants::RTE_IP_ICMP_ECHO_REQUEST && self.code() == 0
+    }
+
+    /// 是否是 ICMPv6 回显请求
+    pub fn is_echo_request_v6(&self) -> bool {
+        self.icmp_type() == ICMPV6_ECHO_REQUEST && self.code() == 0
+    }
+
+    /// ICMPv4 校验和是否正确，ICMPv6 的校验和见 `Ipv6Hdr::l4_checksum_valid`
+    pub fn checksum_valid(&self) -> bool {
+        let b = self.buf.as_ref();
+        unsafe { rte_raw_cksum(b.as_ptr() as *const c_void, b.len()) == 0xffff }
+    }
+
+    /// 头部之后的数据
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[HDR_LEN..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> IcmpHdr<T> {
+    pub fn set_icmp_type(&mut self, icmp_type: u8) {
+        self.buf.as_mut()[TYPE] = icmp_type;
+    }
+
+    pub fn set_code(&mut self, code: u8) {
+        self.buf.as_mut()[CODE] = code;
+    }
+
+    pub fn set_checksum(&mut self, cksum: u16) {
+        set_u16(self.buf.as_mut(), CKSUM, cksum);
+    }
+
+    pub fn set_ident(&mut self, ident: u16) {
+        set_u16(self.buf.as_mut(), IDENT, ident);
+    }
+
+    pub fn set_seq_nb(&mut self, seq: u16) {
+        set_u16(self.buf.as_mut(), SEQ_NB, seq);
+    }
+
+    /// 重新计算 ICMPv4 校验和，覆盖整个缓冲区
+    ///
+    /// ICMPv6 校验和包含伪头部，使用 `Ipv6Hdr::update_l4_checksum`。
+    pub fn update_checksum(&mut self) {
+        self.set_checksum(0);
+        let b = self.buf.as_mut();
+        let cksum = !unsafe { rte_raw_cksum(b.as_ptr() as *const c_void, b.len()) };
+        // 反码和按内存中的字节顺序计算，原样写回即为网络字节序。
+        b[CKSUM..CKSUM + 2].copy_from_slice(&cksum.to_ne_bytes());
+    }
+
+    /// 头部之后的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[HDR_LEN..]
+    }
+}
+
+impl<'a> IcmpHdr<&'a [u8]> {
+    /// 消耗视图，返回负载
+    pub fn into_payload(self) -> &'a [u8] {
+        &self.buf[HDR_LEN..]
+    }
+}
+
+impl<'a> IcmpHdr<&'a mut [u8]> {
+    /// 消耗视图，返回可写的负载
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        &mut self.buf[HDR_LEN..]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for IcmpHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IcmpHdr")
+            .field("icmp_type", &self.icmp_type())
+            .field("code", &self.code())
+            .field("checksum", &format_args!("{:#06x}", self.checksum()))
+            .field("ident", &self.ident())
+            .field("seq_nb", &self.seq_nb())
+            .finish()
+    }
+}
+
+/// IPv6 邻居请求或邻居通告报文视图（RFC 4861）
+///
+/// 视图覆盖整个 ICMPv6 报文。创建时检查类型、代码和固定部分的长度；
+/// 选项按需解析，格式错误的选项被忽略。
+#[derive(Clone, Copy)]
+pub struct NdMsg<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> NdMsg<T> {
+    /// 固定部分的长度
+    pub const LEN: usize = ND_HDR_LEN;
+
+    /// 带一个链路层地址选项时的报文长度
+    pub const LEN_WITH_LL_ADDR: usize = ND_HDR_LEN + ND_OPT_LL_ADDR_LEN;
+
+    /// 在 ICMPv6 报文起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("邻居发现", buf.as_ref().len(), ND_HDR_LEN)?;
+        let b = buf.as_ref();
+        if !matches!(b[TYPE], ICMPV6_NEIGHBOR_SOLICIT | ICMPV6_NEIGHBOR_ADVERT) || b[CODE] != 0 {
+            return Err(DpdkError::invalid(format!(
+                "ICMPv6 类型 {} 代码 {} 不是邻居请求或邻居通告",
+                b[TYPE], b[CODE]
+            )));
+        }
+        Ok(Self { buf })
+    }
+
+    pub fn is_solicit(&self) -> bool {
+        self.buf.as_ref()[TYPE] == ICMPV6_NEIGHBOR_SOLICIT
+    }
+
+    pub fn is_advert(&self) -> bool {
+        self.buf.as_ref()[TYPE] == ICMPV6_NEIGHBOR_ADVERT
+    }
+
+    /// 目标地址
+    pub fn target(&self) -> Ipv6Addr {
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&self.buf.as_ref()[ND_TARGET..ND_TARGET + 16]);
+        Ipv6Addr::from(octets)
+    }
+
+    /// 通告的 R 标志：发送方是路由器
+    pub fn router(&self) -> bool {
+        self.is_advert() && self.buf.as_ref()[ND_FLAGS] & ND_FLAG_ROUTER != 0
+    }
+
+    /// 通告的 S 标志：通告是对请求的应答
+    pub fn solicited(&self) -> bool {
+        self.is_advert() && self.buf.as_ref()[ND_FLAGS] & ND_FLAG_SOLICITED != 0
+    }
+
+    /// 通告的 O 标志：通告应覆盖已有的邻居表项
+    pub fn override_flag(&self) -> bool {
+        self.is_advert() && self.buf.as_ref()[ND_FLAGS] & ND_FLAG_OVERRIDE != 0
+    }
+
+    /// 链路层地址选项中的 MAC 地址
+    ///
+    /// 邻居请求取源链路层地址选项，邻居通告取目标链路层地址选项。
+    pub fn ll_addr(&self) -> Option<[u8; 6]> {
+        let want = if self.is_solicit() {
+            ND_OPT_SOURCE_LL_ADDR
+        } else {
+            ND_OPT_TARGET_LL_ADDR
+        };
+        let mut opts = &self.buf.as_ref()[ND_HDR_LEN..];
+        while opts.len() >= 2 {
+            // 选项长度以 8 字节为单位，长度为 0 的选项无效（RFC 4861 4.6）。
+            let len = opts[1] as usize * 8;
+            if len == 0 || len > opts.len() {
+                return None;
+            }
+            if opts[0] == want && len >= ND_OPT_LL_ADDR_LEN {
+                let mut mac = [0u8; 6];
+                mac.copy_from_slice(&opts[2..8]);
+                return Some(mac);
+            }
+            opts = &opts[len..];
+        }
+        None
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> NdMsg<T> {
+    /// 写入带源链路层地址选项的邻居请求，缓冲区至少需要 `LEN_WITH_LL_ADDR` 字节
+    ///
+    /// 校验和字段为 0，需要在 IPv6 头部写好之后用 `Ipv6Hdr::update_l4_checksum` 计算。
+    pub fn init_solicit(buf: T, target: Ipv6Addr, mac: [u8; 6]) -> Result<Self> {
+        Self::init(
+            buf,
+            ICMPV6_NEIGHBOR_SOLICIT,
+            0,
+            target,
+            ND_OPT_SOURCE_LL_ADDR,
+            mac,
+        )
+    }
+
+    /// 写入带目标链路层地址选项的邻居通告，缓冲区至少需要 `LEN_WITH_LL_ADDR` 字节
+    ///
+    /// 校验和的计算同 `init_solicit`。
+    pub fn init_advert(
+        buf: T,
+        target: Ipv6Addr,
+        mac: [u8; 6],
+        solicited: bool,
+        override_flag: bool,
+    ) -> Result<Self> {
+        let mut flags = 0;
+        if solicited {
+            flags |= ND_FLAG_SOLICITED;
+        }
+        if override_flag {
+            flags |= ND_FLAG_OVERRIDE;
+        }
+        Self::init(
+            buf,
+            ICMPV6_NEIGHBOR_ADVERT,
+            flags,
+            target,
+            ND_OPT_TARGET_LL_ADDR,
+            mac,
+        )
+    }
+
+    fn init(
+        mut buf: T,
+        icmp_type: u8,
+        flags: u8,
+        target: Ipv6Addr,
+        opt_type: u8,
+        mac: [u8; 6],
+    ) -> Result<Self> {
+        check_len("邻居发现", buf.as_ref().len(), Self::LEN_WITH_LL_ADDR)?;
+        let b = buf.as_mut();
+        b[..Self::LEN_WITH_LL_ADDR].fill(0);
+        b[TYPE] = icmp_type;
+        b[ND_FLAGS] = flags;
+        b[ND_TARGET..ND_TARGET + 16].copy_from_slice(&target.octets());
+        b[ND_HDR_LEN] = opt_type;
+        b[ND_HDR_LEN + 1] = (ND_OPT_LL_ADDR_LEN / 8) as u8;
+        b[ND_HDR_LEN + 2..ND_HDR_LEN + 8].copy_from_slice(&mac);
+        Ok(Self { buf })
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for NdMsg<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NdMsg")
+            .field("solicit", &self.is_solicit())
+            .field("target", &self.target())
+            .field("router", &self.router())
+            .field("solicited", &self.solicited())
+            .field("override", &self.override_flag())
+            .field("ll_addr", &self.ll_addr().map(MacFmt))
+            .finish()
+    }
+}
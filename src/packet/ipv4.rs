@@ -0,0 +1,500 @@
+//! IPv4 头部（`rte_ipv4_hdr`）
+
+use super::{check_len, get_u16, set_u16, tcp, udp};
+use crate::error::{DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::os::raw::c_void;
+
+const VERSION_IHL: usize = 0;
+const TYPE_OF_SERVICE: usize = 1;
+const TOTAL_LENGTH: usize = 2;
+const PACKET_ID: usize = 4;
+const FRAGMENT_OFFSET: usize = 6;
+const TIME_TO_LIVE: usize = 8;
+const NEXT_PROTO_ID: usize = 9;
+const HDR_CHECKSUM: usize = 10;
+const SRC_ADDR: usize = 12;
+const DST_ADDR: usize = 16;
+
+/// TCP 和 UDP 头部中校验和字段的偏移
+const TCP_CKSUM: usize = 16;
+const UDP_CKSUM: usize = 6;
+
+/// IPv4 头部视图
+///
+/// 创建时检查版本号、IHL 以及 `total_length`：头部（含选项）和 `total_length`
+/// 指示的整个 IP 数据包都必须位于缓冲区内。以太网填充等 `total_length`
+/// 之后的字节不属于负载。
+#[derive(Clone, Copy)]
+pub struct Ipv4Hdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Ipv4Hdr<T> {
+    /// 不含选项的头部长度
+    pub const MIN_LEN: usize =
+        (constants::RTE_IPV4_MIN_IHL * constants::RTE_IPV4_IHL_MULTIPLIER) as usize;
+
+    /// 在 IPv4 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        let hdr = Self::header_only(buf)?;
+        let header_len = hdr.header_len();
+        let total_length = hdr.total_length() as usize;
+        if total_length < header_len || total_length > hdr.buf.as_ref().len() {
+            return Err(DpdkError::invalid(format!(
+                "IPv4 total_length 为 {}，头部长度 {}，缓冲区 {} 字节",
+                total_length,
+                header_len,
+                hdr.buf.as_ref().len()
+            )));
+        }
+        Ok(hdr)
+    }
+
+    /// 只检查版本号和 IHL，头部（含选项）位于缓冲区内即可
+    ///
+    /// 不检查 `total_length`，用于多段数据包的第一个段：负载截断到缓冲区末尾。
+    pub(crate) fn header_only(buf: T) -> Result<Self> {
+        let data = buf.as_ref();
+        check_len("IPv4 ", data.len(), Self::MIN_LEN)?;
+        let hdr = Self { buf };
+        if hdr.version() != 4 {
+            return Err(DpdkError::invalid(format!(
+                "IP 版本号为 {}，不是 IPv4",
+                hdr.version()
+            )));
+        }
+        let header_len = hdr.header_len();
+        if header_len < Self::MIN_LEN {
+            return Err(DpdkError::invalid(format!(
+                "IPv4 IHL 为 {}，小于最小值 {}",
+                hdr.ihl(),
+                constants::RTE_IPV4_MIN_IHL
+            )));
+        }
+        check_len("IPv4 ", hdr.buf.as_ref().len(), header_len)?;
+        Ok(hdr)
+    }
+
+    /// 版本号
+    pub fn version(&self) -> u8 {
+        self.buf.as_ref()[VERSION_IHL] >> 4
+    }
+
+    /// 头部长度，以 4 字节为单位
+    pub fn ihl(&self) -> u8 {
+        self.buf.as_ref()[VERSION_IHL] & constants::RTE_IPV4_HDR_IHL_MASK as u8
+    }
+
+    /// 头部长度（含选项），以字节为单位
+    pub fn header_len(&self) -> usize {
+        self.ihl() as usize * constants::RTE_IPV4_IHL_MULTIPLIER as usize
+    }
+
+    /// 服务类型字段
+    pub fn type_of_service(&self) -> u8 {
+        self.buf.as_ref()[TYPE_OF_SERVICE]
+    }
+
+    /// DSCP
+    pub fn dscp(&self) -> u8 {
+        self.type_of_service() >> 2
+    }
+
+    /// ECN
+    pub fn ecn(&self) -> u8 {
+        self.type_of_service() & constants::RTE_IPV4_HDR_ECN_MASK as u8
+    }
+
+    /// 整个 IP 数据包的长度
+    pub fn total_length(&self) -> u16 {
+        get_u16(self.buf.as_ref(), TOTAL_LENGTH)
+    }
+
+    pub fn packet_id(&self) -> u16 {
+        get_u16(self.buf.as_ref(), PACKET_ID)
+    }
+
+    /// 标志位与分片偏移字段的原始值
+    pub fn fragment_offset(&self) -> u16 {
+        get_u16(self.buf.as_ref(), FRAGMENT_OFFSET)
+    }
+
+    /// 分片在原始数据包中的偏移，以字节为单位
+    pub fn frag_offset_bytes(&self) -> usize {
+        (self.fragment_offset() & constants::RTE_IPV4_HDR_OFFSET_MASK as u16) as usize
+            * constants::RTE_IPV4_HDR_OFFSET_UNITS as usize
+    }
+
+    /// DF 标志
+    pub fn dont_fragment(&self) -> bool {
+        self.fragment_offset() & constants::RTE_IPV4_HDR_DF_FLAG as u16 != 0
+    }
+
+    /// MF 标志
+    pub fn more_fragments(&self) -> bool {
+        self.fragment_offset() & constants::RTE_IPV4_HDR_MF_FLAG as u16 != 0
+    }
+
+    /// 是否是分片（MF 置位或偏移不为 0）
+    pub fn is_fragment(&self) -> bool {
+        self.more_fragments() || self.frag_offset_bytes() != 0
+    }
+
+    pub fn ttl(&self) -> u8 {
+        self.buf.as_ref()[TIME_TO_LIVE]
+    }
+
+    /// 上层协议号，如 `IPPROTO_UDP`
+    pub fn next_proto_id(&self) -> u8 {
+        self.buf.as_ref()[NEXT_PROTO_ID]
+    }
+
+    pub fn hdr_checksum(&self) -> u16 {
+        get_u16(self.buf.as_ref(), HDR_CHECKSUM)
+    }
+
+    pub fn src_addr(&self) -> Ipv4Addr {
+        self.addr(SRC_ADDR)
+    }
+
+    pub fn dst_addr(&self) -> Ipv4Addr {
+        self.addr(DST_ADDR)
+    }
+
+    /// 头部校验和是否正确
+    pub fn checksum_valid(&self) -> bool {
+        unsafe { rte_ipv4_cksum(self.as_raw()) == 0 }
+    }
+
+    /// IPv4 选项
+    pub fn options(&self) -> &[u8] {
+        &self.buf.as_ref()[Self::MIN_LEN..self.header_len()]
+    }
+
+    /// 头部之后、`total_length` 之内的数据
+    pub fn payload(&self) -> &[u8] {
+        let (start, end) = self.payload_range();
+        &self.buf.as_ref()[start..end]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    fn addr(&self, off: usize) -> Ipv4Addr {
+        let b = &self.buf.as_ref()[off..off + 4];
+        Ipv4Addr::new(b[0], b[1], b[2], b[3])
+    }
+
+    /// 负载的范围；`total_length` 越界时截断到缓冲区末尾
+    fn payload_range(&self) -> (usize, usize) {
+        let start = self.header_len();
+        let end = (self.total_length() as usize)
+            .min(self.buf.as_ref().len())
+            .max(start);
+        (start, end)
+    }
+
+    fn as_raw(&self) -> *const rte_ipv4_hdr {
+        self.buf.as_ref().as_ptr() as *const rte_ipv4_hdr
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Ipv4Hdr<T> {
+    pub fn set_type_of_service(&mut self, tos: u8) {
+        self.buf.as_mut()[TYPE_OF_SERVICE] = tos;
+    }
+
+    /// 修改 `total_length`，负载随之变化，但不会超出缓冲区
+    pub fn set_total_length(&mut self, len: u16) {
+        set_u16(self.buf.as_mut(), TOTAL_LENGTH, len);
+    }
+
+    pub fn set_packet_id(&mut self, id: u16) {
+        set_u16(self.buf.as_mut(), PACKET_ID, id);
+    }
+
+    /// 设置标志位与分片偏移字段的原始值
+    pub fn set_fragment_offset(&mut self, value: u16) {
+        set_u16(self.buf.as_mut(), FRAGMENT_OFFSET, value);
+    }
+
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.buf.as_mut()[TIME_TO_LIVE] = ttl;
+    }
+
+    pub fn set_next_proto_id(&mut self, proto: u8) {
+        self.buf.as_mut()[NEXT_PROTO_ID] = proto;
+    }
+
+    pub fn set_hdr_checksum(&mut self, cksum: u16) {
+        set_u16(self.buf.as_mut(), HDR_CHECKSUM, cksum);
+    }
+
+    pub fn set_src_addr(&mut self, addr: Ipv4Addr) {
+        self.buf.as_mut()[SRC_ADDR..SRC_ADDR + 4].copy_from_slice(&addr.octets());
+    }
+
+    pub fn set_dst_addr(&mut self, addr: Ipv4Addr) {
+        self.buf.as_mut()[DST_ADDR..DST_ADDR + 4].copy_from_slice(&addr.octets());
+    }
+
+    /// 交换源和目的地址
+    pub fn swap_addrs(&mut self) {
+        let (src, dst) = self.buf.as_mut()[SRC_ADDR..DST_ADDR + 4].split_at_mut(4);
+        src.swap_with_slice(dst);
+    }
+
+    /// 重新计算头部校验和（`rte_ipv4_cksum`）
+    pub fn update_checksum(&mut self) {
+        self.set_hdr_checksum(0);
+        // `rte_ipv4_cksum` 的结果按内存中的字节顺序计算，原样写回即为网络字节序。
+        let cksum = unsafe { rte_ipv4_cksum(self.as_raw()) };
+        self.buf.as_mut()[HDR_CHECKSUM..HDR_CHECKSUM + 2].copy_from_slice(&cksum.to_ne_bytes());
+    }
+
+    /// 重新计算 TCP 或 UDP 校验和（`rte_ipv4_udptcp_cksum`）
+    ///
+    /// 校验和覆盖伪头部和整个 L4 数据，因此需要 IP 头部的上下文。
+    /// 分片只包含部分 L4 数据，返回错误。
+    pub fn update_l4_checksum(&mut self) -> Result<()> {
+        if self.is_fragment() {
+            return Err(DpdkError::invalid(
+                "IPv4 分片不包含完整的 L4 数据，不能计算校验和",
+            ));
+        }
+        let (start, end) = self.payload_range();
+        if end != self.total_length() as usize {
+            return Err(DpdkError::invalid(format!(
+                "IPv4 total_length {} 超过缓冲区长度 {}",
+                self.total_length(),
+                self.buf.as_ref().len()
+            )));
+        }
+        let proto = self.next_proto_id() as u32;
+        let (what, off, min_len) = if proto == constants::IPPROTO_TCP {
+            ("TCP ", TCP_CKSUM, tcp::HDR_MIN_LEN)
+        } else if proto == constants::IPPROTO_UDP {
+            ("UDP ", UDP_CKSUM, udp::HDR_LEN)
+        } else {
+            return Err(DpdkError::invalid(format!(
+                "上层协议 {} 不是 TCP 或 UDP",
+                proto
+            )));
+        };
+        check_len(what, end - start, min_len)?;
+        let off = start + off;
+        set_u16(self.buf.as_mut(), off, 0);
+        let l4 = self.buf.as_ref()[start..].as_ptr() as *const c_void;
+        let cksum = unsafe { rte_ipv4_udptcp_cksum(self.as_raw(), l4) };
+        self.buf.as_mut()[off..off + 2].copy_from_slice(&cksum.to_ne_bytes());
+        Ok(())
+    }
+
+    /// 可写的 IPv4 选项
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let end = self.header_len();
+        &mut self.buf.as_mut()[Self::MIN_LEN..end]
+    }
+
+    /// 头部之后、`total_length` 之内的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let (start, end) = self.payload_range();
+        &mut self.buf.as_mut()[start..end]
+    }
+}
+
+impl<'a> Ipv4Hdr<&'a [u8]> {
+    /// 消耗视图，返回负载，用于解析下一层头部
+    pub fn into_payload(self) -> &'a [u8] {
+        let (start, end) = self.payload_range();
+        &self.buf[start..end]
+    }
+}
+
+impl<'a> Ipv4Hdr<&'a mut [u8]> {
+    /// 消耗视图，返回可写的负载，用于解析下一层头部
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        let (start, end) = self.payload_range();
+        &mut self.buf[start..end]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for Ipv4Hdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv4Hdr")
+            .field("ihl", &self.ihl())
+            .field("total_length", &self.total_length())
+            .field("packet_id", &self.packet_id())
+            .field("fragment_offset", &self.fragment_offset())
+            .field("ttl", &self.ttl())
+            .field("next_proto_id", &self.next_proto_id())
+            .field("src_addr", &self.src_addr())
+            .field("dst_addr", &self.dst_addr())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::set_u32;
+    use super::*;
+
+    /// 维基百科 IPv4 校验和示例中的头部，校验和为 0xb861
+    const EXAMPLE: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xb8, 0x61, 0xc0, 0xa8, 0x00,
+        0x01, 0xc0, 0xa8, 0x00, 0xc7,
+    ];
+
+    /// 10.0.0.1 -> 10.0.0.2 的 IPv4 头部，之后跟 `l4`，缓冲区末尾再加 `pad` 字节填充
+    fn packet(proto: u8, l4: &[u8], pad: usize) -> Vec<u8> {
+        let total = 20 + l4.len();
+        let mut buf = vec![0u8; total + pad];
+        buf[VERSION_IHL] = 0x45;
+        set_u16(&mut buf, TOTAL_LENGTH, total as u16);
+        buf[TIME_TO_LIVE] = 64;
+        buf[NEXT_PROTO_ID] = proto;
+        buf[SRC_ADDR..SRC_ADDR + 4].copy_from_slice(&[10, 0, 0, 1]);
+        buf[DST_ADDR..DST_ADDR + 4].copy_from_slice(&[10, 0, 0, 2]);
+        buf[20..total].copy_from_slice(l4);
+        buf
+    }
+
+    fn udp(payload: &[u8]) -> Vec<u8> {
+        let mut l4 = vec![0u8; 8];
+        set_u16(&mut l4, 0, 1234);
+        set_u16(&mut l4, 2, 5678);
+        set_u16(&mut l4, 4, (8 + payload.len()) as u16);
+        l4.extend_from_slice(payload);
+        l4
+    }
+
+    #[test]
+    fn example_header() {
+        // total_length 为 115，超出只有头部的缓冲区
+        assert!(Ipv4Hdr::new(&EXAMPLE[..]).is_err());
+        let mut buf = EXAMPLE.to_vec();
+        buf.resize(0x73, 0);
+        let ip = Ipv4Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.header_len(), 20);
+        assert!(ip.dont_fragment());
+        assert!(!ip.is_fragment());
+        assert_eq!(ip.next_proto_id(), 17);
+        assert_eq!(ip.src_addr(), Ipv4Addr::new(192, 168, 0, 1));
+        assert_eq!(ip.dst_addr(), Ipv4Addr::new(192, 168, 0, 199));
+        assert_eq!(ip.hdr_checksum(), 0xb861);
+        assert!(ip.checksum_valid());
+    }
+
+    #[test]
+    fn header_checksum_round_trip() {
+        let mut buf = EXAMPLE.to_vec();
+        buf.resize(0x73, 0);
+        let mut ip = Ipv4Hdr::new(&mut buf[..]).unwrap();
+        ip.set_hdr_checksum(0);
+        assert!(!ip.checksum_valid());
+        ip.update_checksum();
+        assert_eq!(ip.hdr_checksum(), 0xb861);
+        assert!(ip.checksum_valid());
+        ip.set_ttl(63);
+        assert!(!ip.checksum_valid());
+        ip.update_checksum();
+        assert!(ip.checksum_valid());
+        assert_eq!(&buf[10..12], &[0xb9, 0x61]);
+    }
+
+    #[test]
+    fn ihl_and_options() {
+        let mut buf = packet(17, &udp(b""), 0);
+        buf[VERSION_IHL] = 0x44;
+        assert!(Ipv4Hdr::new(&buf[..]).is_err());
+        buf[VERSION_IHL] = 0x65;
+        assert!(Ipv4Hdr::new(&buf[..]).is_err());
+
+        // IHL 为 6，选项超出缓冲区
+        let mut buf = packet(17, &[], 0);
+        buf[VERSION_IHL] = 0x46;
+        assert!(Ipv4Hdr::new(&buf[..]).is_err());
+
+        let mut buf = packet(17, &[1, 1, 1, 0, 0xaa], 0);
+        buf[VERSION_IHL] = 0x46;
+        let ip = Ipv4Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.ihl(), 6);
+        assert_eq!(ip.options(), &[1, 1, 1, 0]);
+        assert_eq!(ip.payload(), &[0xaa]);
+        assert!(Ipv4Hdr::new(&buf[..19]).is_err());
+    }
+
+    #[test]
+    fn total_length_bounds() {
+        let mut buf = packet(17, &udp(b"abcde"), 4);
+        {
+            let ip = Ipv4Hdr::new(&buf[..]).unwrap();
+            // 以太网填充不属于负载
+            assert_eq!(ip.payload().len(), 13);
+        }
+        set_u16(&mut buf, TOTAL_LENGTH, 19);
+        assert!(Ipv4Hdr::new(&buf[..]).is_err());
+        set_u16(&mut buf, TOTAL_LENGTH, 38);
+        assert!(Ipv4Hdr::new(&buf[..]).is_err());
+        set_u16(&mut buf, TOTAL_LENGTH, 37);
+        assert_eq!(Ipv4Hdr::new(&buf[..]).unwrap().payload().len(), 17);
+        set_u16(&mut buf, TOTAL_LENGTH, 20);
+        assert!(Ipv4Hdr::new(&buf[..]).unwrap().payload().is_empty());
+    }
+
+    #[test]
+    fn udp_checksum_round_trip() {
+        let mut buf = packet(17, &udp(b"abcde"), 4);
+        let mut ip = Ipv4Hdr::new(&mut buf[..]).unwrap();
+        ip.update_l4_checksum().unwrap();
+        assert_eq!(get_u16(ip.payload(), UDP_CKSUM), 0xa70a);
+        // 填充字节不参与计算
+        ip.update_l4_checksum().unwrap();
+        assert_eq!(get_u16(ip.payload(), UDP_CKSUM), 0xa70a);
+    }
+
+    #[test]
+    fn tcp_checksum_round_trip() {
+        let mut l4 = vec![0u8; 20];
+        set_u16(&mut l4, 0, 80);
+        set_u16(&mut l4, 2, 40000);
+        set_u32(&mut l4, 4, 1);
+        set_u32(&mut l4, 8, 2);
+        l4[12] = 0x50;
+        l4[13] = 0x18;
+        set_u16(&mut l4, 14, 1024);
+        l4.extend_from_slice(b"abcde");
+        let mut buf = packet(6, &l4, 0);
+        let mut ip = Ipv4Hdr::new(&mut buf[..]).unwrap();
+        ip.update_l4_checksum().unwrap();
+        assert_eq!(get_u16(ip.payload(), TCP_CKSUM), 0xd16a);
+    }
+
+    #[test]
+    fn l4_checksum_rejects() {
+        let mut buf = packet(17, &udp(b"abcde"), 0);
+        let mut ip = Ipv4Hdr::new(&mut buf[..]).unwrap();
+        ip.set_fragment_offset(constants::RTE_IPV4_HDR_MF_FLAG as u16);
+        assert!(ip.is_fragment());
+        assert!(ip.update_l4_checksum().is_err());
+        ip.set_fragment_offset(1);
+        assert_eq!(ip.frag_offset_bytes(), 8);
+        assert!(ip.update_l4_checksum().is_err());
+        ip.set_fragment_offset(constants::RTE_IPV4_HDR_DF_FLAG as u16);
+        assert!(ip.update_l4_checksum().is_ok());
+
+        ip.set_next_proto_id(1);
+        assert!(ip.update_l4_checksum().is_err());
+        ip.set_next_proto_id(6);
+        // 13 字节不足以容纳 TCP 头部
+        assert!(ip.update_l4_checksum().is_err());
+        ip.set_next_proto_id(17);
+        ip.set_total_length(100);
+        assert!(ip.update_l4_checksum().is_err());
+    }
+}
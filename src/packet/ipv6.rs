@@ -0,0 +1,626 @@
+//! IPv6 头部（`rte_ipv6_hdr`）与扩展头部
+
+use super::{check_len, get_u16, get_u32, set_u16, set_u32, tcp, udp};
+use crate::error::{DpdkError, Result};
+use crate::*;
+use std::fmt;
+use std::net::Ipv6Addr;
+use std::os::raw::c_void;
+
+const VTC_FLOW: usize = 0;
+const PAYLOAD_LEN: usize = 4;
+const PROTO: usize = 6;
+const HOP_LIMITS: usize = 7;
+const SRC_ADDR: usize = 8;
+const DST_ADDR: usize = 24;
+
+pub(super) const HDR_LEN: usize = 40;
+
+/// TCP、UDP 和 ICMPv6 头部中校验和字段的偏移
+const TCP_CKSUM: usize = 16;
+const UDP_CKSUM: usize = 6;
+const ICMPV6_CKSUM: usize = 2;
+const ICMPV6_HDR_LEN: usize = 4;
+
+/// IPv6 头部视图
+///
+/// 创建时检查版本号和 `payload_len`：固定头部和 `payload_len` 指示的负载
+/// （含扩展头部）都必须位于缓冲区内。
+#[derive(Clone, Copy)]
+pub struct Ipv6Hdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> Ipv6Hdr<T> {
+    /// 固定头部长度
+    pub const LEN: usize = HDR_LEN;
+
+    /// 在 IPv6 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        let hdr = Self::header_only(buf)?;
+        let total = HDR_LEN + hdr.payload_len() as usize;
+        if total > hdr.buf.as_ref().len() {
+            return Err(DpdkError::invalid(format!(
+                "IPv6 payload_len 为 {}，缓冲区 {} 字节",
+                hdr.payload_len(),
+                hdr.buf.as_ref().len()
+            )));
+        }
+        Ok(hdr)
+    }
+
+    /// 只检查版本号，固定头部位于缓冲区内即可
+    ///
+    /// 不检查 `payload_len`，用于多段数据包的第一个段：负载截断到缓冲区末尾。
+    pub(crate) fn header_only(buf: T) -> Result<Self> {
+        check_len("IPv6 ", buf.as_ref().len(), HDR_LEN)?;
+        let hdr = Self { buf };
+        if hdr.version() != 6 {
+            return Err(DpdkError::invalid(format!(
+                "IP 版本号为 {}，不是 IPv6",
+                hdr.version()
+            )));
+        }
+        Ok(hdr)
+    }
+
+    /// 版本号
+    pub fn version(&self) -> u8 {
+        self.buf.as_ref()[VTC_FLOW] >> 4
+    }
+
+    /// 版本号、traffic class 和流标签字段的原始值
+    pub fn vtc_flow(&self) -> u32 {
+        get_u32(self.buf.as_ref(), VTC_FLOW)
+    }
+
+    pub fn traffic_class(&self) -> u8 {
+        ((self.vtc_flow() & constants::RTE_IPV6_HDR_TC_MASK) >> constants::RTE_IPV6_HDR_TC_SHIFT)
+            as u8
+    }
+
+    pub fn flow_label(&self) -> u32 {
+        self.vtc_flow() & constants::RTE_IPV6_HDR_FL_MASK
+    }
+
+    /// 固定头部之后的长度，含扩展头部
+    pub fn payload_len(&self) -> u16 {
+        get_u16(self.buf.as_ref(), PAYLOAD_LEN)
+    }
+
+    /// 下一个头部的协议号，可能是扩展头部
+    pub fn proto(&self) -> u8 {
+        self.buf.as_ref()[PROTO]
+    }
+
+    pub fn hop_limits(&self) -> u8 {
+        self.buf.as_ref()[HOP_LIMITS]
+    }
+
+    pub fn src_addr(&self) -> Ipv6Addr {
+        self.addr(SRC_ADDR)
+    }
+
+    pub fn dst_addr(&self) -> Ipv6Addr {
+        self.addr(DST_ADDR)
+    }
+
+    /// 固定头部之后、`payload_len` 之内的数据，含扩展头部
+    pub fn payload(&self) -> &[u8] {
+        let end = self.payload_end();
+        &self.buf.as_ref()[HDR_LEN..end]
+    }
+
+    /// 按顺序遍历扩展头部
+    pub fn ext_headers(&self) -> Ipv6ExtHeaders<'_> {
+        Ipv6ExtHeaders {
+            proto: self.proto(),
+            rest: self.payload(),
+        }
+    }
+
+    /// 跳过所有扩展头部，返回上层协议号及上层数据在 `payload()` 中的偏移
+    ///
+    /// 扩展头部被截断时返回错误。遇到分片头部时返回 `IPPROTO_FRAGMENT`
+    /// 之后的协议，但只有偏移为 0 的分片才包含上层头部。
+    pub fn upper_layer(&self) -> Result<(u8, usize)> {
+        let mut headers = self.ext_headers();
+        let mut offset = 0;
+        for ext in headers.by_ref() {
+            offset += ext.len();
+        }
+        if !headers.is_done() {
+            return Err(DpdkError::invalid(format!(
+                "IPv6 扩展头部 {} 超出负载长度",
+                headers.proto
+            )));
+        }
+        Ok((headers.proto, offset))
+    }
+
+    /// TCP、UDP 或 ICMPv6 校验和是否正确
+    pub fn l4_checksum_valid(&self) -> Result<bool> {
+        let (proto, offset) = self.upper_layer()?;
+        let l4 = &self.payload()[offset..];
+        let min_len = l4_cksum_offset(proto)?.1;
+        check_len(proto_name(proto), l4.len(), min_len)?;
+        if offset == 0 && l4.len() == self.payload_len() as usize {
+            let ret = unsafe {
+                rte_ipv6_udptcp_cksum_verify(self.as_raw(), l4.as_ptr() as *const c_void)
+            };
+            return Ok(ret == 0);
+        }
+        Ok(self.l4_raw_sum(proto, offset, l4.len()) == 0xffff)
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    fn addr(&self, off: usize) -> Ipv6Addr {
+        let mut octets = [0u8; 16];
+        octets.copy_from_slice(&self.buf.as_ref()[off..off + 16]);
+        Ipv6Addr::from(octets)
+    }
+
+    /// 负载的结束位置；`payload_len` 越界时截断到缓冲区末尾
+    fn payload_end(&self) -> usize {
+        (HDR_LEN + self.payload_len() as usize).min(self.buf.as_ref().len())
+    }
+
+    fn as_raw(&self) -> *const rte_ipv6_hdr {
+        self.buf.as_ref().as_ptr() as *const rte_ipv6_hdr
+    }
+
+    /// 伪头部与上层数据（含校验和字段）的反码和，未取反
+    ///
+    /// 伪头部中的长度和协议号取自上层数据，而不是 IPv6 头部，
+    /// 用于有扩展头部或 `payload_len` 超出缓冲区的情况。
+    fn l4_raw_sum(&self, proto: u8, offset: usize, l4_len: usize) -> u16 {
+        let l4 = self.payload()[offset..].as_ptr() as *const c_void;
+        let mut phdr = [0u8; 40];
+        phdr[0..16].copy_from_slice(&self.buf.as_ref()[SRC_ADDR..SRC_ADDR + 16]);
+        phdr[16..32].copy_from_slice(&self.buf.as_ref()[DST_ADDR..DST_ADDR + 16]);
+        set_u32(&mut phdr, 32, l4_len as u32);
+        phdr[39] = proto;
+        let sum = unsafe {
+            rte_raw_cksum(phdr.as_ptr() as *const c_void, phdr.len()) as u32
+                + rte_raw_cksum(l4, l4_len) as u32
+        };
+        let sum = (sum & 0xffff) + (sum >> 16);
+        ((sum & 0xffff) + (sum >> 16)) as u16
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Ipv6Hdr<T> {
+    /// 设置版本号、traffic class 和流标签字段的原始值
+    pub fn set_vtc_flow(&mut self, vtc_flow: u32) {
+        set_u32(self.buf.as_mut(), VTC_FLOW, vtc_flow);
+    }
+
+    pub fn set_traffic_class(&mut self, tc: u8) {
+        let vtc_flow = (self.vtc_flow() & !constants::RTE_IPV6_HDR_TC_MASK)
+            | ((tc as u32) << constants::RTE_IPV6_HDR_TC_SHIFT);
+        self.set_vtc_flow(vtc_flow);
+    }
+
+    /// 设置流标签，只使用低 20 位
+    pub fn set_flow_label(&mut self, label: u32) {
+        let vtc_flow = (self.vtc_flow() & !constants::RTE_IPV6_HDR_FL_MASK)
+            | (label & constants::RTE_IPV6_HDR_FL_MASK);
+        self.set_vtc_flow(vtc_flow);
+    }
+
+    /// 修改 `payload_len`，负载随之变化，但不会超出缓冲区
+    pub fn set_payload_len(&mut self, len: u16) {
+        set_u16(self.buf.as_mut(), PAYLOAD_LEN, len);
+    }
+
+    pub fn set_proto(&mut self, proto: u8) {
+        self.buf.as_mut()[PROTO] = proto;
+    }
+
+    pub fn set_hop_limits(&mut self, hop_limits: u8) {
+        self.buf.as_mut()[HOP_LIMITS] = hop_limits;
+    }
+
+    pub fn set_src_addr(&mut self, addr: Ipv6Addr) {
+        self.buf.as_mut()[SRC_ADDR..SRC_ADDR + 16].copy_from_slice(&addr.octets());
+    }
+
+    pub fn set_dst_addr(&mut self, addr: Ipv6Addr) {
+        self.buf.as_mut()[DST_ADDR..DST_ADDR + 16].copy_from_slice(&addr.octets());
+    }
+
+    /// 交换源和目的地址
+    pub fn swap_addrs(&mut self) {
+        let (src, dst) = self.buf.as_mut()[SRC_ADDR..DST_ADDR + 16].split_at_mut(16);
+        src.swap_with_slice(dst);
+    }
+
+    /// 重新计算 TCP、UDP 或 ICMPv6 校验和
+    ///
+    /// 没有扩展头部时使用 `rte_ipv6_udptcp_cksum`，否则跳过扩展头部后自行计算。
+    pub fn update_l4_checksum(&mut self) -> Result<()> {
+        let (proto, offset) = self.upper_layer()?;
+        let (field, min_len) = l4_cksum_offset(proto)?;
+        let l4_len = self.payload().len() - offset;
+        check_len(proto_name(proto), l4_len, min_len)?;
+        let off = HDR_LEN + offset + field;
+        set_u16(self.buf.as_mut(), off, 0);
+        let cksum = if offset == 0 && l4_len == self.payload_len() as usize {
+            // 没有扩展头部时伪头部与 IPv6 头部一致，结果为 0 时已替换为 0xffff。
+            let l4 = self.payload().as_ptr() as *const c_void;
+            unsafe { rte_ipv6_udptcp_cksum(self.as_raw(), l4) }
+        } else {
+            // UDP 用 0 表示没有校验和，计算结果为 0 时写入 0xffff。
+            match !self.l4_raw_sum(proto, offset, l4_len) {
+                0 if proto as u32 == constants::IPPROTO_UDP => 0xffff,
+                cksum => cksum,
+            }
+        };
+        // 反码和按内存中的字节顺序计算，原样写回即为网络字节序。
+        self.buf.as_mut()[off..off + 2].copy_from_slice(&cksum.to_ne_bytes());
+        Ok(())
+    }
+
+    /// 固定头部之后、`payload_len` 之内的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let end = self.payload_end();
+        &mut self.buf.as_mut()[HDR_LEN..end]
+    }
+}
+
+impl<'a> Ipv6Hdr<&'a [u8]> {
+    /// 消耗视图，返回负载（含扩展头部）
+    pub fn into_payload(self) -> &'a [u8] {
+        let end = self.payload_end();
+        &self.buf[HDR_LEN..end]
+    }
+}
+
+impl<'a> Ipv6Hdr<&'a mut [u8]> {
+    /// 消耗视图，返回可写的负载（含扩展头部）
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        let end = self.payload_end();
+        &mut self.buf[HDR_LEN..end]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for Ipv6Hdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6Hdr")
+            .field("traffic_class", &self.traffic_class())
+            .field("flow_label", &self.flow_label())
+            .field("payload_len", &self.payload_len())
+            .field("proto", &self.proto())
+            .field("hop_limits", &self.hop_limits())
+            .field("src_addr", &self.src_addr())
+            .field("dst_addr", &self.dst_addr())
+            .finish()
+    }
+}
+
+/// 上层协议校验和字段的偏移和头部最小长度
+fn l4_cksum_offset(proto: u8) -> Result<(usize, usize)> {
+    match proto as u32 {
+        constants::IPPROTO_TCP => Ok((TCP_CKSUM, tcp::HDR_MIN_LEN)),
+        constants::IPPROTO_UDP => Ok((UDP_CKSUM, udp::HDR_LEN)),
+        constants::IPPROTO_ICMPV6 => Ok((ICMPV6_CKSUM, ICMPV6_HDR_LEN)),
+        _ => Err(DpdkError::invalid(format!(
+            "上层协议 {} 不是 TCP、UDP 或 ICMPv6",
+            proto
+        ))),
+    }
+}
+
+fn proto_name(proto: u8) -> &'static str {
+    match proto as u32 {
+        constants::IPPROTO_TCP => "TCP ",
+        constants::IPPROTO_UDP => "UDP ",
+        _ => "ICMPv6 ",
+    }
+}
+
+/// IPv6 扩展头部
+#[derive(Clone, Copy)]
+pub struct Ipv6ExtHdr<'a> {
+    proto: u8,
+    data: &'a [u8],
+}
+
+impl<'a> Ipv6ExtHdr<'a> {
+    /// 本扩展头部的类型，如 `IPPROTO_ROUTING`
+    pub fn proto(&self) -> u8 {
+        self.proto
+    }
+
+    /// 下一个头部的协议号
+    pub fn next_header(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// 扩展头部的长度，以字节为单位
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// 扩展头部的长度不会为 0
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// 扩展头部的全部字节，包括开头的下一个头部和长度字段
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// 是否是分片头部（`IPPROTO_FRAGMENT`）
+    pub fn is_fragment(&self) -> bool {
+        self.proto as u32 == constants::IPPROTO_FRAGMENT
+    }
+
+    /// 分片偏移（字节），不是分片头部时返回 `None`
+    pub fn frag_offset_bytes(&self) -> Option<usize> {
+        self.is_fragment()
+            .then(|| (get_u16(self.data, 2) as u32 & constants::RTE_IPV6_EHDR_FO_MASK) as usize)
+    }
+
+    /// 分片头部的 M 标志，不是分片头部时返回 `None`
+    pub fn more_fragments(&self) -> Option<bool> {
+        self.is_fragment()
+            .then(|| get_u16(self.data, 2) as u32 & constants::RTE_IPV6_EHDR_MF_MASK != 0)
+    }
+
+    /// 分片头部的标识，不是分片头部时返回 `None`
+    pub fn frag_ident(&self) -> Option<u32> {
+        self.is_fragment().then(|| get_u32(self.data, 4))
+    }
+}
+
+impl fmt::Debug for Ipv6ExtHdr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ipv6ExtHdr")
+            .field("proto", &self.proto)
+            .field("next_header", &self.next_header())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// IPv6 扩展头部的迭代器
+///
+/// 支持 hop-by-hop 选项、路由、分片、目的选项和 AH 头部；遇到其他协议号时结束，
+/// 此时 `proto` 即为上层协议。
+#[derive(Clone)]
+pub struct Ipv6ExtHeaders<'a> {
+    proto: u8,
+    rest: &'a [u8],
+}
+
+impl Ipv6ExtHeaders<'_> {
+    /// 迭代是否因遇到非扩展头部而正常结束，为 `false` 说明扩展头部被截断
+    fn is_done(&self) -> bool {
+        !is_ext(self.proto)
+    }
+}
+
+impl<'a> Iterator for Ipv6ExtHeaders<'a> {
+    type Item = Ipv6ExtHdr<'a>;
+
+    fn next(&mut self) -> Option<Ipv6ExtHdr<'a>> {
+        let len = ext_len(self.proto, self.rest)?;
+        let (data, rest) = self.rest.split_at(len);
+        let ext = Ipv6ExtHdr {
+            proto: self.proto,
+            data,
+        };
+        self.proto = data[0];
+        self.rest = rest;
+        Some(ext)
+    }
+}
+
+fn is_ext(proto: u8) -> bool {
+    matches!(
+        proto as u32,
+        constants::IPPROTO_HOPOPTS
+            | constants::IPPROTO_ROUTING
+            | constants::IPPROTO_FRAGMENT
+            | constants::IPPROTO_DSTOPTS
+            | constants::IPPROTO_AH
+    )
+}
+
+/// 扩展头部的长度（与 `rte_ipv6_get_next_ext` 相同），不是扩展头部或被截断时返回 `None`
+fn ext_len(proto: u8, data: &[u8]) -> Option<usize> {
+    if !is_ext(proto) || data.len() < 2 {
+        return None;
+    }
+    let len = match proto as u32 {
+        constants::IPPROTO_FRAGMENT => constants::RTE_IPV6_FRAG_HDR_SIZE as usize,
+        constants::IPPROTO_AH => (data[1] as usize + 2) * 4,
+        _ => (data[1] as usize + 1) * 8,
+    };
+    (len <= data.len()).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOPOPTS: u8 = constants::IPPROTO_HOPOPTS as u8;
+    const ROUTING: u8 = constants::IPPROTO_ROUTING as u8;
+    const FRAGMENT: u8 = constants::IPPROTO_FRAGMENT as u8;
+    const DSTOPTS: u8 = constants::IPPROTO_DSTOPTS as u8;
+    const AH: u8 = constants::IPPROTO_AH as u8;
+    const UDP: u8 = constants::IPPROTO_UDP as u8;
+
+    /// 2001:db8::1 -> 2001:db8::2 的 IPv6 数据包，`payload` 含扩展头部
+    fn packet(proto: u8, payload: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0u8; HDR_LEN];
+        set_u32(&mut buf, VTC_FLOW, 6 << 28);
+        set_u16(&mut buf, PAYLOAD_LEN, payload.len() as u16);
+        buf[PROTO] = proto;
+        buf[HOP_LIMITS] = 64;
+        buf[SRC_ADDR..SRC_ADDR + 16]
+            .copy_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        buf[DST_ADDR..DST_ADDR + 16]
+            .copy_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        buf.extend_from_slice(payload);
+        buf
+    }
+
+    /// 以 8 字节为单位的扩展头部：下一个头部、长度字段，其余填 0
+    fn ext(next: u8, hdr_ext_len: u8) -> Vec<u8> {
+        let mut data = vec![0u8; (hdr_ext_len as usize + 1) * 8];
+        data[0] = next;
+        data[1] = hdr_ext_len;
+        data
+    }
+
+    fn udp(payload: &[u8]) -> Vec<u8> {
+        let mut l4 = vec![0u8; udp::HDR_LEN];
+        set_u16(&mut l4, 0, 1000);
+        set_u16(&mut l4, 2, 2000);
+        set_u16(&mut l4, 4, (udp::HDR_LEN + payload.len()) as u16);
+        l4.extend_from_slice(payload);
+        l4
+    }
+
+    #[test]
+    fn payload_len_bounds() {
+        let mut buf = packet(UDP, &udp(b"abc"));
+        assert_eq!(Ipv6Hdr::new(&buf[..]).unwrap().payload().len(), 11);
+        assert!(Ipv6Hdr::new(&buf[..buf.len() - 1]).is_err());
+        assert!(Ipv6Hdr::new(&buf[..HDR_LEN - 1]).is_err());
+        buf[VTC_FLOW] = 0x45;
+        assert!(Ipv6Hdr::new(&buf[..]).is_err());
+    }
+
+    #[test]
+    fn ext_chain() {
+        let mut payload = ext(ROUTING, 0);
+        payload.extend(ext(FRAGMENT, 1));
+        // 分片头部：偏移 0，M 标志置位，标识 0x12345678
+        payload.extend_from_slice(&[UDP, 0, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78]);
+        payload.extend(udp(b"abc"));
+        let buf = packet(HOPOPTS, &payload);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+
+        let exts: Vec<_> = ip.ext_headers().collect();
+        let protos: Vec<_> = exts.iter().map(|e| (e.proto(), e.len())).collect();
+        assert_eq!(protos, [(HOPOPTS, 8), (ROUTING, 16), (FRAGMENT, 8)]);
+        assert_eq!(exts[0].frag_offset_bytes(), None);
+        assert_eq!(exts[2].next_header(), UDP);
+        assert_eq!(exts[2].frag_offset_bytes(), Some(0));
+        assert_eq!(exts[2].more_fragments(), Some(true));
+        assert_eq!(exts[2].frag_ident(), Some(0x1234_5678));
+        assert_eq!(ip.upper_layer().unwrap(), (UDP, 32));
+    }
+
+    #[test]
+    fn fragment_offset() {
+        // 偏移 1480 字节，M 标志清零，分片头部的长度字段是保留字段，不参与计算
+        let frag = [UDP, 0xff, 0x05, 0xc8, 0, 0, 0, 1];
+        let buf = packet(FRAGMENT, &frag);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        let hdr = ip.ext_headers().next().unwrap();
+        assert_eq!(hdr.len(), 8);
+        assert_eq!(hdr.frag_offset_bytes(), Some(1480));
+        assert_eq!(hdr.more_fragments(), Some(false));
+        assert_eq!(ip.upper_layer().unwrap(), (UDP, 8));
+    }
+
+    #[test]
+    fn ah_length_units() {
+        // AH 的长度以 4 字节为单位，再加 2
+        let mut ah = vec![0u8; 24];
+        ah[0] = UDP;
+        ah[1] = 4;
+        let mut payload = ah.clone();
+        payload.extend(udp(b""));
+        let buf = packet(AH, &payload);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.ext_headers().next().unwrap().len(), 24);
+        assert_eq!(ip.upper_layer().unwrap(), (UDP, 24));
+
+        // 按 8 字节为单位计算时为 40 字节，会超出只有 AH 的负载
+        let buf = packet(AH, &ah);
+        assert_eq!(
+            Ipv6Hdr::new(&buf[..]).unwrap().upper_layer().unwrap(),
+            (UDP, 24)
+        );
+        let buf = packet(AH, &ah[..23]);
+        assert!(Ipv6Hdr::new(&buf[..]).unwrap().upper_layer().is_err());
+    }
+
+    #[test]
+    fn truncated_ext_headers() {
+        // 长度字段指示 16 字节，负载只有 8 字节
+        let mut hop = ext(UDP, 0);
+        hop[1] = 1;
+        let buf = packet(HOPOPTS, &hop);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.ext_headers().count(), 0);
+        assert!(ip.upper_layer().is_err());
+
+        // 不足以包含长度字段
+        let buf = packet(DSTOPTS, &[UDP]);
+        assert!(Ipv6Hdr::new(&buf[..]).unwrap().upper_layer().is_err());
+        let buf = packet(ROUTING, &[]);
+        assert!(Ipv6Hdr::new(&buf[..]).unwrap().upper_layer().is_err());
+
+        // 分片头部固定为 8 字节
+        let buf = packet(FRAGMENT, &[UDP, 0, 0, 0, 0, 0, 0]);
+        assert!(Ipv6Hdr::new(&buf[..]).unwrap().upper_layer().is_err());
+
+        // 第二个扩展头部被截断
+        let mut payload = ext(ROUTING, 0);
+        payload.extend_from_slice(&[UDP, 0, 0, 0]);
+        let buf = packet(HOPOPTS, &payload);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.ext_headers().count(), 1);
+        assert!(ip.upper_layer().is_err());
+    }
+
+    #[test]
+    fn chain_ends_in_unknown_header() {
+        // 扩展头部之后是未知协议，迭代在此结束并返回该协议
+        let mut payload = ext(DSTOPTS, 0);
+        payload.extend(ext(253, 0));
+        let buf = packet(HOPOPTS, &payload);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        assert_eq!(ip.ext_headers().count(), 2);
+        assert_eq!(ip.upper_layer().unwrap(), (253, 16));
+        assert!(ip.l4_checksum_valid().is_err());
+
+        let buf = packet(constants::IPPROTO_NONE as u8, &[]);
+        let ip = Ipv6Hdr::new(&buf[..]).unwrap();
+        assert_eq!(
+            ip.upper_layer().unwrap(),
+            (constants::IPPROTO_NONE as u8, 0)
+        );
+    }
+
+    #[test]
+    fn udp_checksum_round_trip() {
+        let mut buf = packet(UDP, &udp(b"abc"));
+        let mut ip = Ipv6Hdr::new(&mut buf[..]).unwrap();
+        ip.update_l4_checksum().unwrap();
+        assert_eq!(get_u16(ip.payload(), UDP_CKSUM), 0xd448);
+        assert!(ip.l4_checksum_valid().unwrap());
+
+        // 跳过扩展头部后伪头部只覆盖 UDP 数据，结果相同
+        let mut payload = ext(UDP, 0);
+        payload.extend(udp(b"abc"));
+        let mut buf = packet(DSTOPTS, &payload);
+        let mut ip = Ipv6Hdr::new(&mut buf[..]).unwrap();
+        ip.update_l4_checksum().unwrap();
+        assert_eq!(get_u16(&ip.payload()[8..], UDP_CKSUM), 0xd448);
+        assert!(ip.l4_checksum_valid().unwrap());
+        ip.payload_mut()[8 + udp::HDR_LEN] ^= 1;
+        assert!(!ip.l4_checksum_valid().unwrap());
+    }
+}
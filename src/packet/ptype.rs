@@ -0,0 +1,299 @@
+//! 数据包类型（`RTE_PTYPE_*`）的软件识别
+
+use crate::mbuf::Mbuf;
+use crate::*;
+use std::ffi::CStr;
+use std::fmt;
+use std::os::raw::c_char;
+
+/// 数据包类型，即 `RTE_PTYPE_*` 各层取值的组合
+///
+/// 每个 getter 返回对应层掩码下的原始值，可与 `RTE_PTYPE_L3_IPV4` 等常量比较。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PacketType(u32);
+
+impl PacketType {
+    pub fn from_bits(bits: u32) -> Self {
+        PacketType(bits)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// 是否没有识别出任何一层
+    pub fn is_unknown(&self) -> bool {
+        self.0 == constants::RTE_PTYPE_UNKNOWN
+    }
+
+    pub fn l2(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_L2_MASK
+    }
+
+    pub fn l3(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_L3_MASK
+    }
+
+    pub fn l4(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_L4_MASK
+    }
+
+    pub fn tunnel(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_TUNNEL_MASK
+    }
+
+    pub fn inner_l2(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_INNER_L2_MASK
+    }
+
+    pub fn inner_l3(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_INNER_L3_MASK
+    }
+
+    pub fn inner_l4(&self) -> u32 {
+        self.0 & constants::RTE_PTYPE_INNER_L4_MASK
+    }
+
+    /// 是否识别出隧道
+    pub fn is_tunnel(&self) -> bool {
+        self.tunnel() != 0
+    }
+
+    pub fn l2_type(&self) -> L2Type {
+        match self.l2() {
+            constants::RTE_PTYPE_L2_ETHER => L2Type::Ether,
+            constants::RTE_PTYPE_L2_ETHER_TIMESYNC => L2Type::EtherTimesync,
+            constants::RTE_PTYPE_L2_ETHER_ARP => L2Type::EtherArp,
+            constants::RTE_PTYPE_L2_ETHER_LLDP => L2Type::EtherLldp,
+            constants::RTE_PTYPE_L2_ETHER_NSH => L2Type::EtherNsh,
+            constants::RTE_PTYPE_L2_ETHER_VLAN => L2Type::EtherVlan,
+            constants::RTE_PTYPE_L2_ETHER_QINQ => L2Type::EtherQinq,
+            constants::RTE_PTYPE_L2_ETHER_PPPOE => L2Type::EtherPppoe,
+            constants::RTE_PTYPE_L2_ETHER_FCOE => L2Type::EtherFcoe,
+            constants::RTE_PTYPE_L2_ETHER_MPLS => L2Type::EtherMpls,
+            _ => L2Type::Unknown,
+        }
+    }
+
+    pub fn l3_type(&self) -> L3Type {
+        match self.l3() {
+            constants::RTE_PTYPE_L3_IPV4 => L3Type::Ipv4,
+            constants::RTE_PTYPE_L3_IPV4_EXT => L3Type::Ipv4Ext,
+            constants::RTE_PTYPE_L3_IPV4_EXT_UNKNOWN => L3Type::Ipv4ExtUnknown,
+            constants::RTE_PTYPE_L3_IPV6 => L3Type::Ipv6,
+            constants::RTE_PTYPE_L3_IPV6_EXT => L3Type::Ipv6Ext,
+            constants::RTE_PTYPE_L3_IPV6_EXT_UNKNOWN => L3Type::Ipv6ExtUnknown,
+            _ => L3Type::Unknown,
+        }
+    }
+
+    pub fn l4_type(&self) -> L4Type {
+        match self.l4() {
+            constants::RTE_PTYPE_L4_TCP => L4Type::Tcp,
+            constants::RTE_PTYPE_L4_UDP => L4Type::Udp,
+            constants::RTE_PTYPE_L4_FRAG => L4Type::Frag,
+            constants::RTE_PTYPE_L4_SCTP => L4Type::Sctp,
+            constants::RTE_PTYPE_L4_ICMP => L4Type::Icmp,
+            constants::RTE_PTYPE_L4_NONFRAG => L4Type::NonFrag,
+            constants::RTE_PTYPE_L4_IGMP => L4Type::Igmp,
+            _ => L4Type::Unknown,
+        }
+    }
+
+    /// 隧道类型，不是隧道数据包时为 `None`
+    pub fn tunnel_type(&self) -> Option<TunnelType> {
+        let tunnel = match self.tunnel() {
+            constants::RTE_PTYPE_TUNNEL_IP => TunnelType::Ip,
+            constants::RTE_PTYPE_TUNNEL_GRE => TunnelType::Gre,
+            constants::RTE_PTYPE_TUNNEL_VXLAN => TunnelType::Vxlan,
+            constants::RTE_PTYPE_TUNNEL_NVGRE => TunnelType::Nvgre,
+            constants::RTE_PTYPE_TUNNEL_GENEVE => TunnelType::Geneve,
+            constants::RTE_PTYPE_TUNNEL_GRENAT => TunnelType::Grenat,
+            constants::RTE_PTYPE_TUNNEL_GTPC => TunnelType::Gtpc,
+            constants::RTE_PTYPE_TUNNEL_GTPU => TunnelType::Gtpu,
+            constants::RTE_PTYPE_TUNNEL_ESP => TunnelType::Esp,
+            constants::RTE_PTYPE_TUNNEL_L2TP => TunnelType::L2tp,
+            constants::RTE_PTYPE_TUNNEL_VXLAN_GPE => TunnelType::VxlanGpe,
+            constants::RTE_PTYPE_TUNNEL_MPLS_IN_GRE => TunnelType::MplsInGre,
+            constants::RTE_PTYPE_TUNNEL_MPLS_IN_UDP => TunnelType::MplsInUdp,
+            _ => return None,
+        };
+        Some(tunnel)
+    }
+
+    /// 内层 L2 类型，只有 `Ether`、`EtherVlan`、`EtherQinq` 三种取值
+    pub fn inner_l2_type(&self) -> L2Type {
+        match self.inner_l2() {
+            constants::RTE_PTYPE_INNER_L2_ETHER => L2Type::Ether,
+            constants::RTE_PTYPE_INNER_L2_ETHER_VLAN => L2Type::EtherVlan,
+            constants::RTE_PTYPE_INNER_L2_ETHER_QINQ => L2Type::EtherQinq,
+            _ => L2Type::Unknown,
+        }
+    }
+
+    pub fn inner_l3_type(&self) -> L3Type {
+        match self.inner_l3() {
+            constants::RTE_PTYPE_INNER_L3_IPV4 => L3Type::Ipv4,
+            constants::RTE_PTYPE_INNER_L3_IPV4_EXT => L3Type::Ipv4Ext,
+            constants::RTE_PTYPE_INNER_L3_IPV4_EXT_UNKNOWN => L3Type::Ipv4ExtUnknown,
+            constants::RTE_PTYPE_INNER_L3_IPV6 => L3Type::Ipv6,
+            constants::RTE_PTYPE_INNER_L3_IPV6_EXT => L3Type::Ipv6Ext,
+            constants::RTE_PTYPE_INNER_L3_IPV6_EXT_UNKNOWN => L3Type::Ipv6ExtUnknown,
+            _ => L3Type::Unknown,
+        }
+    }
+
+    /// 内层 L4 类型，没有 `Igmp`
+    pub fn inner_l4_type(&self) -> L4Type {
+        match self.inner_l4() {
+            constants::RTE_PTYPE_INNER_L4_TCP => L4Type::Tcp,
+            constants::RTE_PTYPE_INNER_L4_UDP => L4Type::Udp,
+            constants::RTE_PTYPE_INNER_L4_FRAG => L4Type::Frag,
+            constants::RTE_PTYPE_INNER_L4_SCTP => L4Type::Sctp,
+            constants::RTE_PTYPE_INNER_L4_ICMP => L4Type::Icmp,
+            constants::RTE_PTYPE_INNER_L4_NONFRAG => L4Type::NonFrag,
+            _ => L4Type::Unknown,
+        }
+    }
+}
+
+/// L2 类型（`RTE_PTYPE_L2_*`、`RTE_PTYPE_INNER_L2_*`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum L2Type {
+    Unknown,
+    Ether,
+    /// IEEE 1588 时间同步报文
+    EtherTimesync,
+    EtherArp,
+    EtherLldp,
+    EtherNsh,
+    EtherVlan,
+    EtherQinq,
+    EtherPppoe,
+    EtherFcoe,
+    EtherMpls,
+}
+
+/// L3 类型（`RTE_PTYPE_L3_*`、`RTE_PTYPE_INNER_L3_*`）
+///
+/// `Ext` 表示带 IPv4 选项或 IPv6 扩展头部，`ExtUnknown` 表示网卡不确定是否带有。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum L3Type {
+    Unknown,
+    Ipv4,
+    Ipv4Ext,
+    Ipv4ExtUnknown,
+    Ipv6,
+    Ipv6Ext,
+    Ipv6ExtUnknown,
+}
+
+impl L3Type {
+    pub fn is_ipv4(&self) -> bool {
+        matches!(
+            self,
+            L3Type::Ipv4 | L3Type::Ipv4Ext | L3Type::Ipv4ExtUnknown
+        )
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        matches!(
+            self,
+            L3Type::Ipv6 | L3Type::Ipv6Ext | L3Type::Ipv6ExtUnknown
+        )
+    }
+}
+
+/// L4 类型（`RTE_PTYPE_L4_*`、`RTE_PTYPE_INNER_L4_*`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum L4Type {
+    Unknown,
+    Tcp,
+    Udp,
+    /// IP 分片，L4 头部不一定在这个分片中
+    Frag,
+    Sctp,
+    Icmp,
+    /// 未分片但不是以上任何一种协议
+    NonFrag,
+    Igmp,
+}
+
+/// 隧道类型（`RTE_PTYPE_TUNNEL_*`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TunnelType {
+    /// IP-in-IP
+    Ip,
+    Gre,
+    Vxlan,
+    Nvgre,
+    Geneve,
+    /// 网卡无法进一步区分的 GRE、Teredo 或 VXLAN 封装
+    Grenat,
+    Gtpc,
+    Gtpu,
+    Esp,
+    L2tp,
+    VxlanGpe,
+    MplsInGre,
+    MplsInUdp,
+}
+
+impl fmt::Debug for PacketType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = [0 as c_char; 256];
+        let ret = unsafe { rte_get_ptype_name(self.0, buf.as_mut_ptr(), buf.len()) };
+        if ret < 0 {
+            return write!(f, "PacketType({:#010x})", self.0);
+        }
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        write!(f, "PacketType({})", name.to_string_lossy().trim_end())
+    }
+}
+
+/// `rte_net_get_ptype` 解析出的各层头部长度
+///
+/// `tunnel_len` 为隧道头部长度，不包括外层 L4 头部；`inner_*` 只对隧道数据包有意义。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HdrLens {
+    pub l2_len: u8,
+    pub l3_len: u16,
+    pub l4_len: u8,
+    pub tunnel_len: u16,
+    pub inner_l2_len: u8,
+    pub inner_l3_len: u16,
+    pub inner_l4_len: u8,
+}
+
+impl Mbuf {
+    /// mbuf 中记录的数据包类型，由网卡或 `set_packet_type` 填写
+    pub fn packet_type(&self) -> PacketType {
+        PacketType(unsafe { (*self.as_ptr()).__bindgen_anon_2.packet_type })
+    }
+
+    /// 设置 mbuf 中记录的数据包类型，GRO 等库按它判断如何处理数据包
+    pub fn set_packet_type(&mut self, ptype: PacketType) {
+        unsafe { (*self.as_ptr()).__bindgen_anon_2.packet_type = ptype.0 }
+    }
+
+    /// 在软件中解析头部，识别数据包类型（`rte_net_get_ptype`）
+    ///
+    /// 解析跨段进行，不要求数据包连续。能识别 VLAN/QinQ、IPv4/IPv6（含扩展头部）、
+    /// TCP/UDP/SCTP，以及 IP-in-IP 和 GRE 隧道；VXLAN、GENEVE 这类基于 UDP 端口的隧道
+    /// 不会被识别，需要用 `tunnel`。
+    pub fn detect_ptype(&self) -> (PacketType, HdrLens) {
+        let mut lens = unsafe { std::mem::zeroed::<rte_net_hdr_lens>() };
+        let ptype =
+            unsafe { rte_net_get_ptype(self.as_ptr(), &mut lens, constants::RTE_PTYPE_ALL_MASK) };
+        let lens = HdrLens {
+            l2_len: lens.l2_len,
+            l3_len: lens.l3_len,
+            l4_len: lens.l4_len,
+            tunnel_len: lens.tunnel_len,
+            inner_l2_len: lens.inner_l2_len,
+            inner_l3_len: lens.inner_l3_len,
+            inner_l4_len: lens.inner_l4_len,
+        };
+        (PacketType(ptype), lens)
+    }
+}
@@ -0,0 +1,260 @@
+//! TCP 头部（`rte_tcp_hdr`）
+
+use super::{check_len, get_u16, get_u32, set_u16, set_u32};
+use crate::error::{DpdkError, Result};
+use crate::*;
+use bitflags::bitflags;
+use std::fmt;
+
+const SRC_PORT: usize = 0;
+const DST_PORT: usize = 2;
+const SENT_SEQ: usize = 4;
+const RECV_ACK: usize = 8;
+const DATA_OFF: usize = 12;
+const TCP_FLAGS: usize = 13;
+const RX_WIN: usize = 14;
+const CKSUM: usize = 16;
+const TCP_URP: usize = 18;
+
+pub(super) const HDR_MIN_LEN: usize = 20;
+
+bitflags! {
+    /// TCP 标志位（`RTE_TCP_*_FLAG`）
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct TcpFlags: u8 {
+        const FIN = constants::RTE_TCP_FIN_FLAG as u8;
+        const SYN = constants::RTE_TCP_SYN_FLAG as u8;
+        const RST = constants::RTE_TCP_RST_FLAG as u8;
+        const PSH = constants::RTE_TCP_PSH_FLAG as u8;
+        const ACK = constants::RTE_TCP_ACK_FLAG as u8;
+        const URG = constants::RTE_TCP_URG_FLAG as u8;
+        const ECE = constants::RTE_TCP_ECE_FLAG as u8;
+        const CWR = constants::RTE_TCP_CWR_FLAG as u8;
+    }
+}
+
+/// TCP 头部视图
+///
+/// 创建时检查数据偏移：头部（含选项）必须位于缓冲区内。
+/// 负载为头部之后缓冲区中的所有数据，其长度由 IP 层决定。
+#[derive(Clone, Copy)]
+pub struct TcpHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> TcpHdr<T> {
+    /// 不含选项的头部长度
+    pub const MIN_LEN: usize = HDR_MIN_LEN;
+
+    /// 在 TCP 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("TCP ", buf.as_ref().len(), HDR_MIN_LEN)?;
+        let hdr = Self { buf };
+        let header_len = hdr.header_len();
+        if header_len < HDR_MIN_LEN {
+            return Err(DpdkError::invalid(format!(
+                "TCP 数据偏移为 {}，小于最小值 5",
+                header_len / 4
+            )));
+        }
+        check_len("TCP ", hdr.buf.as_ref().len(), header_len)?;
+        Ok(hdr)
+    }
+
+    pub fn src_port(&self) -> u16 {
+        get_u16(self.buf.as_ref(), SRC_PORT)
+    }
+
+    pub fn dst_port(&self) -> u16 {
+        get_u16(self.buf.as_ref(), DST_PORT)
+    }
+
+    pub fn sent_seq(&self) -> u32 {
+        get_u32(self.buf.as_ref(), SENT_SEQ)
+    }
+
+    pub fn recv_ack(&self) -> u32 {
+        get_u32(self.buf.as_ref(), RECV_ACK)
+    }
+
+    /// 数据偏移字段的原始值，高 4 位为以 4 字节为单位的头部长度
+    pub fn data_off(&self) -> u8 {
+        self.buf.as_ref()[DATA_OFF]
+    }
+
+    /// 头部长度（含选项），以字节为单位
+    pub fn header_len(&self) -> usize {
+        (self.data_off() >> 4) as usize * 4
+    }
+
+    pub fn tcp_flags(&self) -> TcpFlags {
+        TcpFlags::from_bits_retain(self.buf.as_ref()[TCP_FLAGS])
+    }
+
+    pub fn rx_win(&self) -> u16 {
+        get_u16(self.buf.as_ref(), RX_WIN)
+    }
+
+    pub fn cksum(&self) -> u16 {
+        get_u16(self.buf.as_ref(), CKSUM)
+    }
+
+    pub fn tcp_urp(&self) -> u16 {
+        get_u16(self.buf.as_ref(), TCP_URP)
+    }
+
+    /// TCP 选项
+    pub fn options(&self) -> &[u8] {
+        &self.buf.as_ref()[HDR_MIN_LEN..self.header_len()]
+    }
+
+    /// 头部之后的数据
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[self.header_len()..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> TcpHdr<T> {
+    pub fn set_src_port(&mut self, port: u16) {
+        set_u16(self.buf.as_mut(), SRC_PORT, port);
+    }
+
+    pub fn set_dst_port(&mut self, port: u16) {
+        set_u16(self.buf.as_mut(), DST_PORT, port);
+    }
+
+    pub fn set_sent_seq(&mut self, seq: u32) {
+        set_u32(self.buf.as_mut(), SENT_SEQ, seq);
+    }
+
+    pub fn set_recv_ack(&mut self, ack: u32) {
+        set_u32(self.buf.as_mut(), RECV_ACK, ack);
+    }
+
+    pub fn set_tcp_flags(&mut self, flags: TcpFlags) {
+        self.buf.as_mut()[TCP_FLAGS] = flags.bits();
+    }
+
+    pub fn set_rx_win(&mut self, win: u16) {
+        set_u16(self.buf.as_mut(), RX_WIN, win);
+    }
+
+    /// 设置校验和，计算见 `Ipv4Hdr::update_l4_checksum`
+    pub fn set_cksum(&mut self, cksum: u16) {
+        set_u16(self.buf.as_mut(), CKSUM, cksum);
+    }
+
+    pub fn set_tcp_urp(&mut self, urp: u16) {
+        set_u16(self.buf.as_mut(), TCP_URP, urp);
+    }
+
+    /// 交换源和目的端口
+    pub fn swap_ports(&mut self) {
+        let (src, dst) = self.buf.as_mut()[SRC_PORT..DST_PORT + 2].split_at_mut(2);
+        src.swap_with_slice(dst);
+    }
+
+    /// 可写的 TCP 选项
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let end = self.header_len();
+        &mut self.buf.as_mut()[HDR_MIN_LEN..end]
+    }
+
+    /// 头部之后的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let start = self.header_len();
+        &mut self.buf.as_mut()[start..]
+    }
+}
+
+impl<'a> TcpHdr<&'a [u8]> {
+    /// 消耗视图，返回负载
+    pub fn into_payload(self) -> &'a [u8] {
+        let start = self.header_len();
+        &self.buf[start..]
+    }
+}
+
+impl<'a> TcpHdr<&'a mut [u8]> {
+    /// 消耗视图，返回可写的负载
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        let start = self.header_len();
+        &mut self.buf[start..]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for TcpHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpHdr")
+            .field("src_port", &self.src_port())
+            .field("dst_port", &self.dst_port())
+            .field("sent_seq", &self.sent_seq())
+            .field("recv_ack", &self.recv_ack())
+            .field("header_len", &self.header_len())
+            .field("tcp_flags", &self.tcp_flags())
+            .field("rx_win", &self.rx_win())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(data_off: u8, buf_len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; buf_len];
+        set_u16(&mut buf, SRC_PORT, 80);
+        set_u16(&mut buf, DST_PORT, 40000);
+        buf[DATA_OFF] = data_off << 4;
+        buf
+    }
+
+    #[test]
+    fn data_offset_bounds() {
+        assert!(TcpHdr::new(&segment(5, 19)[..]).is_err());
+        assert!(TcpHdr::new(&segment(4, 40)[..]).is_err());
+        // 选项超出缓冲区
+        assert!(TcpHdr::new(&segment(6, 23)[..]).is_err());
+        let buf = segment(6, 30);
+        let tcp = TcpHdr::new(&buf[..]).unwrap();
+        assert_eq!(tcp.header_len(), 24);
+        assert_eq!(tcp.options().len(), 4);
+        assert_eq!(tcp.payload().len(), 6);
+    }
+
+    #[test]
+    fn max_data_offset() {
+        let buf = segment(15, 60);
+        let tcp = TcpHdr::new(&buf[..]).unwrap();
+        assert_eq!(tcp.header_len(), 60);
+        assert_eq!(tcp.options().len(), 40);
+        assert!(tcp.payload().is_empty());
+    }
+
+    #[test]
+    fn setters() {
+        let mut buf = segment(5, 20);
+        let mut tcp = TcpHdr::new(&mut buf[..]).unwrap();
+        tcp.set_sent_seq(0x0102_0304);
+        tcp.set_recv_ack(0xa0b0_c0d0);
+        tcp.set_tcp_flags(TcpFlags::SYN | TcpFlags::ACK);
+        tcp.set_rx_win(0x1122);
+        tcp.swap_ports();
+        assert_eq!(tcp.sent_seq(), 0x0102_0304);
+        assert_eq!(tcp.recv_ack(), 0xa0b0_c0d0);
+        assert_eq!(tcp.tcp_flags(), TcpFlags::SYN | TcpFlags::ACK);
+        assert_eq!((tcp.src_port(), tcp.dst_port()), (40000, 80));
+        assert_eq!(
+            &buf[..16],
+            &[
+                0x9c, 0x40, 0x00, 0x50, 0x01, 0x02, 0x03, 0x04, 0xa0, 0xb0, 0xc0, 0xd0, 0x50, 0x12,
+                0x11, 0x22
+            ]
+        );
+    }
+}
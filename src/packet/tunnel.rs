@@ -0,0 +1,1105 @@
+//! VXLAN、GENEVE、GRE 隧道头部与封装/解封装
+//!
+//! 三种隧道都承载完整的内层以太网帧：GENEVE 和 GRE 的协议类型为 `RTE_ETHER_TYPE_TEB`。
+//! 封装和解封装同时维护 mbuf 的 `tx_offload` 长度，使内层 offload 在隧道中仍然可用。
+
+use super::{check_len, get_u16, get_u32, ipv6, set_u16, set_u32, udp};
+use super::{EtherHdr, Ipv4Hdr, Ipv6Hdr, UdpHdr};
+use crate::error::{DpdkError, Result};
+use crate::ethdev::TxOffload;
+use crate::mbuf::{Mbuf, TxOffloadLens};
+use crate::*;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+const VXLAN_HDR_LEN: usize = 8;
+const VXLAN_FLAG_I: u8 = 0x08;
+
+const GENEVE_MIN_LEN: usize = 8;
+const GENEVE_FLAG_OAM: u8 = 0x80;
+const GENEVE_FLAG_CRITICAL: u8 = 0x40;
+
+const GRE_MIN_LEN: usize = 4;
+const GRE_FLAG_C: u16 = 0x8000;
+const GRE_FLAG_K: u16 = 0x2000;
+const GRE_FLAG_S: u16 = 0x1000;
+const GRE_VERSION_MASK: u16 = 0x0007;
+
+/// 外层 UDP 源端口的取值范围起点，源端口由内层流的哈希决定，便于网络中的 ECMP
+const UDP_SRC_PORT_BASE: u16 = 49152;
+
+/// VXLAN 头部视图
+#[derive(Clone, Copy)]
+pub struct VxlanHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> VxlanHdr<T> {
+    /// 头部长度
+    pub const LEN: usize = VXLAN_HDR_LEN;
+
+    /// 在 VXLAN 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("VXLAN ", buf.as_ref().len(), VXLAN_HDR_LEN)?;
+        Ok(Self { buf })
+    }
+
+    /// 标志字节
+    pub fn flags(&self) -> u8 {
+        self.buf.as_ref()[0]
+    }
+
+    /// VNI 是否有效（I 标志）
+    pub fn vni_valid(&self) -> bool {
+        self.flags() & VXLAN_FLAG_I != 0
+    }
+
+    /// 24 位的 VXLAN 网络标识
+    pub fn vni(&self) -> u32 {
+        get_u32(self.buf.as_ref(), 4) >> 8
+    }
+
+    /// 头部之后的内层以太网帧
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[VXLAN_HDR_LEN..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> VxlanHdr<T> {
+    pub fn set_flags(&mut self, flags: u8) {
+        self.buf.as_mut()[0] = flags;
+    }
+
+    /// 设置 VNI 并置位 I 标志，只使用低 24 位
+    pub fn set_vni(&mut self, vni: u32) {
+        let buf = self.buf.as_mut();
+        buf[0] |= VXLAN_FLAG_I;
+        let reserved = buf[7] as u32;
+        set_u32(buf, 4, ((vni & 0x00ff_ffff) << 8) | reserved);
+    }
+
+    /// 头部之后的可写内层以太网帧
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[VXLAN_HDR_LEN..]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for VxlanHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VxlanHdr")
+            .field("flags", &format_args!("{:#04x}", self.flags()))
+            .field("vni", &self.vni())
+            .finish()
+    }
+}
+
+/// GENEVE 头部视图
+///
+/// 创建时检查选项长度：头部和全部选项都必须位于缓冲区内。
+#[derive(Clone, Copy)]
+pub struct GeneveHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> GeneveHdr<T> {
+    /// 不含选项的头部长度
+    pub const MIN_LEN: usize = GENEVE_MIN_LEN;
+
+    /// 在 GENEVE 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("GENEVE ", buf.as_ref().len(), GENEVE_MIN_LEN)?;
+        let hdr = Self { buf };
+        check_len("GENEVE ", hdr.buf.as_ref().len(), hdr.header_len())?;
+        Ok(hdr)
+    }
+
+    /// 版本号
+    pub fn version(&self) -> u8 {
+        self.buf.as_ref()[0] >> 6
+    }
+
+    /// 选项长度，以 4 字节为单位
+    pub fn opt_len(&self) -> u8 {
+        self.buf.as_ref()[0] & 0x3f
+    }
+
+    /// 是否是控制报文（O 标志）
+    pub fn oam(&self) -> bool {
+        self.buf.as_ref()[1] & GENEVE_FLAG_OAM != 0
+    }
+
+    /// 是否包含关键选项（C 标志）
+    pub fn critical(&self) -> bool {
+        self.buf.as_ref()[1] & GENEVE_FLAG_CRITICAL != 0
+    }
+
+    /// 内层协议类型，承载以太网帧时为 `RTE_ETHER_TYPE_TEB`
+    pub fn proto(&self) -> u16 {
+        get_u16(self.buf.as_ref(), 2)
+    }
+
+    /// 24 位的虚拟网络标识
+    pub fn vni(&self) -> u32 {
+        get_u32(self.buf.as_ref(), 4) >> 8
+    }
+
+    /// 头部长度，含选项
+    pub fn header_len(&self) -> usize {
+        GENEVE_MIN_LEN + self.opt_len() as usize * 4
+    }
+
+    /// 选项
+    pub fn options(&self) -> &[u8] {
+        &self.buf.as_ref()[GENEVE_MIN_LEN..self.header_len()]
+    }
+
+    /// 头部和选项之后的数据
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[self.header_len()..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> GeneveHdr<T> {
+    pub fn set_oam(&mut self, oam: bool) {
+        self.set_flag(GENEVE_FLAG_OAM, oam);
+    }
+
+    pub fn set_critical(&mut self, critical: bool) {
+        self.set_flag(GENEVE_FLAG_CRITICAL, critical);
+    }
+
+    pub fn set_proto(&mut self, proto: u16) {
+        set_u16(self.buf.as_mut(), 2, proto);
+    }
+
+    /// 设置 VNI，只使用低 24 位
+    pub fn set_vni(&mut self, vni: u32) {
+        let buf = self.buf.as_mut();
+        let reserved = buf[7] as u32;
+        set_u32(buf, 4, ((vni & 0x00ff_ffff) << 8) | reserved);
+    }
+
+    /// 可写的选项
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let end = self.header_len();
+        &mut self.buf.as_mut()[GENEVE_MIN_LEN..end]
+    }
+
+    /// 头部和选项之后的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let start = self.header_len();
+        &mut self.buf.as_mut()[start..]
+    }
+
+    fn set_flag(&mut self, flag: u8, on: bool) {
+        let buf = self.buf.as_mut();
+        if on {
+            buf[1] |= flag;
+        } else {
+            buf[1] &= !flag;
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for GeneveHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneveHdr")
+            .field("version", &self.version())
+            .field("opt_len", &self.opt_len())
+            .field("oam", &self.oam())
+            .field("critical", &self.critical())
+            .field("proto", &format_args!("{:#06x}", self.proto()))
+            .field("vni", &self.vni())
+            .finish()
+    }
+}
+
+/// GRE 头部视图
+///
+/// 创建时根据 C、K、S 标志检查可选字段是否位于缓冲区内。
+/// 可选字段的有无在创建后不能修改，只能修改其中的值。
+#[derive(Clone, Copy)]
+pub struct GreHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> GreHdr<T> {
+    /// 不含可选字段的头部长度
+    pub const MIN_LEN: usize = GRE_MIN_LEN;
+
+    /// 在 GRE 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        check_len("GRE ", buf.as_ref().len(), GRE_MIN_LEN)?;
+        let hdr = Self { buf };
+        check_len("GRE ", hdr.buf.as_ref().len(), hdr.header_len())?;
+        Ok(hdr)
+    }
+
+    /// 标志和版本号字段的原始值
+    pub fn flags(&self) -> u16 {
+        get_u16(self.buf.as_ref(), 0)
+    }
+
+    pub fn version(&self) -> u8 {
+        (self.flags() & GRE_VERSION_MASK) as u8
+    }
+
+    /// 内层协议类型，承载以太网帧时为 `RTE_ETHER_TYPE_TEB`
+    pub fn proto(&self) -> u16 {
+        get_u16(self.buf.as_ref(), 2)
+    }
+
+    /// 校验和，没有 C 标志时返回 `None`
+    pub fn checksum(&self) -> Option<u16> {
+        self.checksum_offset()
+            .map(|off| get_u16(self.buf.as_ref(), off))
+    }
+
+    /// 密钥，没有 K 标志时返回 `None`
+    pub fn key(&self) -> Option<u32> {
+        self.key_offset().map(|off| get_u32(self.buf.as_ref(), off))
+    }
+
+    /// 序列号，没有 S 标志时返回 `None`
+    pub fn seq(&self) -> Option<u32> {
+        self.seq_offset().map(|off| get_u32(self.buf.as_ref(), off))
+    }
+
+    /// 头部长度，含可选字段
+    pub fn header_len(&self) -> usize {
+        let flags = self.flags();
+        let mut len = GRE_MIN_LEN;
+        for flag in [GRE_FLAG_C, GRE_FLAG_K, GRE_FLAG_S] {
+            if flags & flag != 0 {
+                len += 4;
+            }
+        }
+        len
+    }
+
+    /// 头部之后的数据
+    pub fn payload(&self) -> &[u8] {
+        &self.buf.as_ref()[self.header_len()..]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    fn checksum_offset(&self) -> Option<usize> {
+        (self.flags() & GRE_FLAG_C != 0).then_some(GRE_MIN_LEN)
+    }
+
+    fn key_offset(&self) -> Option<usize> {
+        let flags = self.flags();
+        let off = if flags & GRE_FLAG_C != 0 { 8 } else { 4 };
+        (flags & GRE_FLAG_K != 0).then_some(off)
+    }
+
+    fn seq_offset(&self) -> Option<usize> {
+        (self.flags() & GRE_FLAG_S != 0).then(|| self.header_len() - 4)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> GreHdr<T> {
+    pub fn set_proto(&mut self, proto: u16) {
+        set_u16(self.buf.as_mut(), 2, proto);
+    }
+
+    /// 修改校验和，没有 C 标志时不做修改并返回 `false`
+    pub fn set_checksum(&mut self, checksum: u16) -> bool {
+        let Some(off) = self.checksum_offset() else {
+            return false;
+        };
+        set_u16(self.buf.as_mut(), off, checksum);
+        true
+    }
+
+    /// 修改密钥，没有 K 标志时不做修改并返回 `false`
+    pub fn set_key(&mut self, key: u32) -> bool {
+        let Some(off) = self.key_offset() else {
+            return false;
+        };
+        set_u32(self.buf.as_mut(), off, key);
+        true
+    }
+
+    /// 修改序列号，没有 S 标志时不做修改并返回 `false`
+    pub fn set_seq(&mut self, seq: u32) -> bool {
+        let Some(off) = self.seq_offset() else {
+            return false;
+        };
+        set_u32(self.buf.as_mut(), off, seq);
+        true
+    }
+
+    /// 头部之后的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let start = self.header_len();
+        &mut self.buf.as_mut()[start..]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for GreHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GreHdr")
+            .field("version", &self.version())
+            .field("proto", &format_args!("{:#06x}", self.proto()))
+            .field("checksum", &self.checksum())
+            .field("key", &self.key())
+            .field("seq", &self.seq())
+            .finish()
+    }
+}
+
+/// 隧道类型及其标识
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tunnel {
+    Vxlan { vni: u32 },
+    Geneve { vni: u32 },
+    Gre { key: Option<u32> },
+}
+
+impl Tunnel {
+    /// 隧道头部长度，不含外层 UDP 头部；GENEVE 封装时不带选项
+    fn header_len(&self) -> usize {
+        match self {
+            Tunnel::Vxlan { .. } => VXLAN_HDR_LEN,
+            Tunnel::Geneve { .. } => GENEVE_MIN_LEN,
+            Tunnel::Gre { key: None } => GRE_MIN_LEN,
+            Tunnel::Gre { key: Some(_) } => GRE_MIN_LEN + 4,
+        }
+    }
+
+    /// 外层 L4 头部长度，GRE 直接承载在 IP 之上
+    fn outer_l4_len(&self) -> usize {
+        match self {
+            Tunnel::Gre { .. } => 0,
+            _ => udp::HDR_LEN,
+        }
+    }
+
+    fn tx_flag(&self) -> u64 {
+        match self {
+            Tunnel::Vxlan { .. } => constants::RTE_MBUF_F_TX_TUNNEL_VXLAN,
+            Tunnel::Geneve { .. } => constants::RTE_MBUF_F_TX_TUNNEL_GENEVE,
+            Tunnel::Gre { .. } => constants::RTE_MBUF_F_TX_TUNNEL_GRE,
+        }
+    }
+}
+
+/// 隧道数据包中各层的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TunnelLayout {
+    pub tunnel: Tunnel,
+    /// 外层以太网头部长度，含 VLAN 标签
+    pub outer_l2_len: usize,
+    /// 外层 IP 头部长度，含 IPv4 选项或 IPv6 扩展头部
+    pub outer_l3_len: usize,
+    /// 外层 L4 头部和隧道头部的总长度
+    pub tunnel_len: usize,
+}
+
+impl TunnelLayout {
+    /// 内层以太网头部在数据包中的偏移
+    pub fn inner_offset(&self) -> usize {
+        self.outer_l2_len + self.outer_l3_len + self.tunnel_len
+    }
+}
+
+impl Mbuf {
+    /// 识别第一个段中的 VXLAN、GENEVE 或 GRE 隧道
+    ///
+    /// VXLAN 和 GENEVE 按默认 UDP 目的端口识别。不是隧道数据包时返回 `Ok(None)`，
+    /// 外层头部被截断时返回错误。外层 IP 分片不会被识别为隧道。
+    ///
+    /// 只要求外层头部位于第一个段中，外层 IP 和 UDP 的长度字段可以超出第一个段，
+    /// 因此多段数据包同样可以识别。
+    pub fn tunnel(&self) -> Result<Option<TunnelLayout>> {
+        parse_tunnel(self.data())
+    }
+
+    /// 隧道数据包的内层以太网头部，不是隧道数据包时返回 `Ok(None)`
+    pub fn inner_ether(&self) -> Result<Option<EtherHdr<&[u8]>>> {
+        let Some(layout) = self.tunnel()? else {
+            return Ok(None);
+        };
+        EtherHdr::new(&self.data()[layout.inner_offset()..]).map(Some)
+    }
+
+    /// 隧道数据包的可写内层以太网头部，不是隧道数据包时返回 `Ok(None)`
+    ///
+    /// # Panics
+    ///
+    /// 数据区与其他 mbuf 共享时会 panic，见 `data_mut`。
+    pub fn inner_ether_mut(&mut self) -> Result<Option<EtherHdr<&mut [u8]>>> {
+        let Some(layout) = self.tunnel()? else {
+            return Ok(None);
+        };
+        EtherHdr::new(&mut self.data_mut()[layout.inner_offset()..]).map(Some)
+    }
+
+    /// 去掉外层头部，返回隧道信息
+    ///
+    /// 同时清除外层和隧道相关的发送 offload 标志，`l2_len` 改为内层以太网头部长度，
+    /// `outer_l2_len`/`outer_l3_len` 清零。
+    pub fn decap(&mut self) -> Result<Tunnel> {
+        let layout = self
+            .tunnel()?
+            .ok_or_else(|| DpdkError::invalid("不是 VXLAN、GENEVE 或 GRE 隧道数据包"))?;
+        let inner_l2_len = EtherHdr::new(&self.data()[layout.inner_offset()..])?.header_len();
+        self.adj(layout.inner_offset())?;
+
+        let outer_flags = constants::RTE_MBUF_F_TX_TUNNEL_MASK
+            | constants::RTE_MBUF_F_TX_OUTER_IPV4
+            | constants::RTE_MBUF_F_TX_OUTER_IPV6
+            | constants::RTE_MBUF_F_TX_OUTER_IP_CKSUM
+            | constants::RTE_MBUF_F_TX_OUTER_UDP_CKSUM;
+        self.set_ol_flags(self.ol_flags() & !outer_flags);
+        self.set_tx_offload_lens(decap_lens(inner_l2_len, self.tx_offload_lens()))?;
+        Ok(layout.tunnel)
+    }
+}
+
+/// 解封装后的 offload 长度：`l2_len` 为内层以太网头部长度，外层长度清零
+fn decap_lens(inner_l2_len: usize, lens: TxOffloadLens) -> TxOffloadLens {
+    TxOffloadLens {
+        l2_len: inner_l2_len as u16,
+        outer_l2_len: 0,
+        outer_l3_len: 0,
+        ..lens
+    }
+}
+
+/// 在从外层以太网头部开始的数据中识别隧道，只检查头部本身，见 `Mbuf::tunnel`
+fn parse_tunnel(data: &[u8]) -> Result<Option<TunnelLayout>> {
+    let eth = EtherHdr::new(data)?;
+    let outer_l2_len = eth.header_len();
+    let ether_type = eth.ether_type() as u32;
+    let (proto, outer_l3_len, l4) = if ether_type == constants::RTE_ETHER_TYPE_IPV4 {
+        let ip = Ipv4Hdr::header_only(eth.into_payload())?;
+        if ip.is_fragment() {
+            return Ok(None);
+        }
+        (ip.next_proto_id(), ip.header_len(), ip.into_payload())
+    } else if ether_type == constants::RTE_ETHER_TYPE_IPV6 {
+        let ip = Ipv6Hdr::header_only(eth.into_payload())?;
+        if ip.ext_headers().any(|ext| ext.is_fragment()) {
+            return Ok(None);
+        }
+        let (proto, offset) = ip.upper_layer()?;
+        (proto, ipv6::HDR_LEN + offset, &ip.into_payload()[offset..])
+    } else {
+        return Ok(None);
+    };
+
+    let teb = constants::RTE_ETHER_TYPE_TEB as u16;
+    let (tunnel, tunnel_len) = if proto as u32 == constants::IPPROTO_UDP {
+        let udp = UdpHdr::header_only(l4)?;
+        let dst_port = udp.dst_port() as u32;
+        if dst_port == constants::RTE_VXLAN_DEFAULT_PORT {
+            let vxlan = VxlanHdr::new(udp.into_payload())?;
+            if !vxlan.vni_valid() {
+                return Ok(None);
+            }
+            let tunnel = Tunnel::Vxlan { vni: vxlan.vni() };
+            (tunnel, udp::HDR_LEN + VXLAN_HDR_LEN)
+        } else if dst_port == constants::RTE_GENEVE_DEFAULT_PORT {
+            let geneve = GeneveHdr::new(udp.into_payload())?;
+            if geneve.version() != 0 || geneve.proto() != teb {
+                return Ok(None);
+            }
+            let tunnel = Tunnel::Geneve { vni: geneve.vni() };
+            (tunnel, udp::HDR_LEN + geneve.header_len())
+        } else {
+            return Ok(None);
+        }
+    } else if proto as u32 == constants::IPPROTO_GRE {
+        let gre = GreHdr::new(l4)?;
+        if gre.version() != 0 || gre.proto() != teb {
+            return Ok(None);
+        }
+        (Tunnel::Gre { key: gre.key() }, gre.header_len())
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(TunnelLayout {
+        tunnel,
+        outer_l2_len,
+        outer_l3_len,
+        tunnel_len,
+    }))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum OuterIp {
+    V4 { src: Ipv4Addr, dst: Ipv4Addr },
+    V6 { src: Ipv6Addr, dst: Ipv6Addr },
+}
+
+/// 隧道封装
+///
+/// 在数据包前插入外层以太网、IPv4 或 IPv6、UDP（VXLAN/GENEVE）和隧道头部，
+/// 数据包本身作为内层以太网帧。外层 IPv4 校验和由软件计算。外层为 IPv4 时 UDP 校验和为 0；
+/// 外层为 IPv6 时 RFC 8200 不允许 UDP 校验和为 0，端口开启 `TxOffload::OUTER_UDP_CKSUM`
+/// 时由网卡计算，否则由软件计算。
+///
+/// ```ignore
+/// let encap = TunnelEncap::new(Tunnel::Vxlan { vni: 42 }, local_mac, gateway_mac)
+///     .ipv4(Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 2));
+/// encap.encap(&mut mbuf)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct TunnelEncap {
+    tunnel: Tunnel,
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    outer: Option<OuterIp>,
+    ttl: u8,
+    tos: u8,
+    udp_src_port: Option<u16>,
+    tx_offloads: TxOffload,
+}
+
+impl TunnelEncap {
+    pub fn new(tunnel: Tunnel, src_mac: [u8; 6], dst_mac: [u8; 6]) -> Self {
+        TunnelEncap {
+            tunnel,
+            src_mac,
+            dst_mac,
+            outer: None,
+            ttl: 64,
+            tos: 0,
+            udp_src_port: None,
+            tx_offloads: TxOffload::empty(),
+        }
+    }
+
+    /// 外层 IPv4 地址
+    pub fn ipv4(mut self, src: Ipv4Addr, dst: Ipv4Addr) -> Self {
+        self.outer = Some(OuterIp::V4 { src, dst });
+        self
+    }
+
+    /// 外层 IPv6 地址
+    pub fn ipv6(mut self, src: Ipv6Addr, dst: Ipv6Addr) -> Self {
+        self.outer = Some(OuterIp::V6 { src, dst });
+        self
+    }
+
+    /// 外层 TTL 或 hop limit，默认为 64
+    pub fn ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 外层 IPv4 TOS 或 IPv6 traffic class，默认为 0
+    pub fn tos(mut self, tos: u8) -> Self {
+        self.tos = tos;
+        self
+    }
+
+    /// 固定外层 UDP 源端口
+    ///
+    /// 默认根据 mbuf 的 RSS 哈希在 49152 以上选取，同一条内层流使用同一个端口。
+    pub fn udp_src_port(mut self, port: u16) -> Self {
+        self.udp_src_port = Some(port);
+        self
+    }
+
+    /// 端口开启的发送 offload（`Port::tx_offloads`），用于选择外层 UDP 校验和的计算方式
+    pub fn tx_offloads(mut self, offloads: TxOffload) -> Self {
+        self.tx_offloads = offloads;
+        self
+    }
+
+    /// 外层头部的总长度
+    pub fn outer_len(&self) -> usize {
+        EtherHdr::<&[u8]>::LEN
+            + self.outer_l3_len()
+            + self.tunnel.outer_l4_len()
+            + self.tunnel.header_len()
+    }
+
+    fn outer_l3_len(&self) -> usize {
+        match self.outer {
+            Some(OuterIp::V6 { .. }) => ipv6::HDR_LEN,
+            _ => Ipv4Hdr::<&[u8]>::MIN_LEN,
+        }
+    }
+
+    /// 封装数据包
+    ///
+    /// 外层头部占用头部预留空间。mbuf 已请求内层校验和或分段 offload 时，
+    /// 同时设置隧道类型和 `RTE_MBUF_F_TX_OUTER_IPV4`/`IPV6` 标志；
+    /// 无论是否请求 offload，`l2_len` 都会加上外层 L4 和隧道头部长度，
+    /// 并设置 `outer_l2_len`/`outer_l3_len`。
+    ///
+    /// 外层为 IPv6 且端口没有开启 `OUTER_UDP_CKSUM` 时，外层 UDP 校验和由软件计算，
+    /// 这要求内层校验和已经填好：mbuf 已请求内层校验和或分段 offload 时返回错误。
+    pub fn encap(&self, mbuf: &mut Mbuf) -> Result<()> {
+        let outer = self
+            .outer
+            .ok_or_else(|| DpdkError::invalid("隧道封装需要指定外层 IPv4 或 IPv6 地址"))?;
+        let inner_l2_len = mbuf.ether()?.header_len();
+        let l2_len = EtherHdr::<&[u8]>::LEN;
+        let l3_len = self.outer_l3_len();
+        let l3_payload_len = self.tunnel.outer_l4_len() + self.tunnel.header_len() + mbuf.len();
+        let l3_total_len = l3_total_len(outer, l3_payload_len);
+        if l3_total_len > u16::MAX as usize {
+            return Err(DpdkError::invalid(format!(
+                "封装后外层 IP 长度 {} 超过 65535",
+                l3_total_len
+            )));
+        }
+        let inner_offloads = constants::RTE_MBUF_F_TX_L4_MASK
+            | constants::RTE_MBUF_F_TX_IP_CKSUM
+            | constants::RTE_MBUF_F_TX_TCP_SEG
+            | constants::RTE_MBUF_F_TX_UDP_SEG;
+        let inner_offload = mbuf.ol_flags() & inner_offloads != 0;
+        let outer_udp = match (outer, self.tunnel) {
+            (_, Tunnel::Gre { .. }) => OuterUdpCksum::None,
+            (OuterIp::V4 { .. }, _) => OuterUdpCksum::None,
+            (OuterIp::V6 { .. }, _) if self.tx_offloads.contains(TxOffload::OUTER_UDP_CKSUM) => {
+                OuterUdpCksum::Offload
+            }
+            (OuterIp::V6 { .. }, _) => OuterUdpCksum::Software,
+        };
+        if outer_udp == OuterUdpCksum::Software && inner_offload {
+            return Err(DpdkError::invalid(
+                "外层 IPv6 UDP 校验和需要网卡计算：内层校验和由网卡填写，软件无法预先计算",
+            ));
+        }
+        let src_port = self.udp_src_port.unwrap_or_else(|| flow_src_port(mbuf));
+
+        let hdr = mbuf.prepend(self.outer_len())?;
+        self.write_outer(hdr, outer, l3_payload_len, src_port);
+
+        let udp_cksum = l2_len + l3_len + 6;
+        match outer_udp {
+            OuterUdpCksum::None => {}
+            // 硬件 offload 要求校验和字段预先填入伪头部校验和。
+            OuterUdpCksum::Offload => {
+                let ip = mbuf.data()[l2_len..].as_ptr() as *const rte_ipv6_hdr;
+                let cksum = unsafe { rte_ipv6_phdr_cksum(ip, 0) };
+                mbuf.data_mut()[udp_cksum..udp_cksum + 2].copy_from_slice(&cksum.to_ne_bytes());
+            }
+            OuterUdpCksum::Software => {
+                let cksum = ipv6_udp_cksum(mbuf, l2_len, l3_payload_len)?;
+                mbuf.data_mut()[udp_cksum..udp_cksum + 2].copy_from_slice(&cksum.to_ne_bytes());
+            }
+        }
+
+        let mut ol_flags = mbuf.ol_flags();
+        if inner_offload || outer_udp == OuterUdpCksum::Offload {
+            ol_flags &= !constants::RTE_MBUF_F_TX_TUNNEL_MASK;
+            ol_flags |= self.tunnel.tx_flag();
+            ol_flags |= match outer {
+                OuterIp::V4 { .. } => constants::RTE_MBUF_F_TX_OUTER_IPV4,
+                OuterIp::V6 { .. } => constants::RTE_MBUF_F_TX_OUTER_IPV6,
+            };
+            if outer_udp == OuterUdpCksum::Offload {
+                ol_flags |= constants::RTE_MBUF_F_TX_OUTER_UDP_CKSUM;
+            }
+            mbuf.set_ol_flags(ol_flags);
+        }
+        mbuf.set_tx_offload_lens(self.encap_lens(inner_l2_len, mbuf.tx_offload_lens()))
+    }
+
+    /// 封装后的 offload 长度：`l2_len` 加上外层 L4 和隧道头部，并设置外层长度
+    fn encap_lens(&self, inner_l2_len: usize, lens: TxOffloadLens) -> TxOffloadLens {
+        TxOffloadLens {
+            l2_len: (self.tunnel.outer_l4_len() + self.tunnel.header_len() + inner_l2_len) as u16,
+            outer_l2_len: EtherHdr::<&[u8]>::LEN as u16,
+            outer_l3_len: self.outer_l3_len() as u16,
+            ..lens
+        }
+    }
+
+    /// 在长度为 `outer_len` 的缓冲区中写入外层以太网、IP、UDP（如有）和隧道头部
+    ///
+    /// `l3_payload_len` 是外层 IP 负载的长度，即外层 L4、隧道头部和内层帧的总长度。
+    fn write_outer(&self, hdr: &mut [u8], outer: OuterIp, l3_payload_len: usize, src_port: u16) {
+        let l3_total_len = l3_total_len(outer, l3_payload_len);
+        let (l2, rest) = hdr.split_at_mut(EtherHdr::<&[u8]>::LEN);
+        let (l3, rest) = rest.split_at_mut(self.outer_l3_len());
+        l2[0..6].copy_from_slice(&self.dst_mac);
+        l2[6..12].copy_from_slice(&self.src_mac);
+        let l4_proto = match self.tunnel {
+            Tunnel::Gre { .. } => constants::IPPROTO_GRE as u8,
+            _ => constants::IPPROTO_UDP as u8,
+        };
+        match outer {
+            OuterIp::V4 { src, dst } => {
+                set_u16(l2, 12, constants::RTE_ETHER_TYPE_IPV4 as u16);
+                l3[0] = 0x45;
+                l3[1] = self.tos;
+                set_u16(l3, 2, l3_total_len as u16);
+                set_u16(l3, 4, 0);
+                set_u16(l3, 6, constants::RTE_IPV4_HDR_DF_FLAG as u16);
+                l3[8] = self.ttl;
+                l3[9] = l4_proto;
+                set_u16(l3, 10, 0);
+                l3[12..16].copy_from_slice(&src.octets());
+                l3[16..20].copy_from_slice(&dst.octets());
+                // `rte_ipv4_cksum` 的结果按内存中的字节顺序计算，原样写回即为网络字节序。
+                let cksum = unsafe { rte_ipv4_cksum(l3.as_ptr() as *const rte_ipv4_hdr) };
+                l3[10..12].copy_from_slice(&cksum.to_ne_bytes());
+            }
+            OuterIp::V6 { src, dst } => {
+                set_u16(l2, 12, constants::RTE_ETHER_TYPE_IPV6 as u16);
+                set_u32(l3, 0, (6 << 28) | ((self.tos as u32) << 20));
+                set_u16(l3, 4, l3_total_len as u16);
+                l3[6] = l4_proto;
+                l3[7] = self.ttl;
+                l3[8..24].copy_from_slice(&src.octets());
+                l3[24..40].copy_from_slice(&dst.octets());
+            }
+        }
+        self.write_tunnel(rest, l3_payload_len, src_port);
+    }
+
+    /// 写入外层 UDP（如有）和隧道头部
+    fn write_tunnel(&self, buf: &mut [u8], l4_len: usize, src_port: u16) {
+        let teb = constants::RTE_ETHER_TYPE_TEB as u16;
+        let (udp, hdr) = buf.split_at_mut(self.tunnel.outer_l4_len());
+        match self.tunnel {
+            Tunnel::Vxlan { vni } => {
+                write_udp(
+                    udp,
+                    src_port,
+                    constants::RTE_VXLAN_DEFAULT_PORT as u16,
+                    l4_len,
+                );
+                hdr.fill(0);
+                hdr[0] = VXLAN_FLAG_I;
+                set_u32(hdr, 4, (vni & 0x00ff_ffff) << 8);
+            }
+            Tunnel::Geneve { vni } => {
+                write_udp(
+                    udp,
+                    src_port,
+                    constants::RTE_GENEVE_DEFAULT_PORT as u16,
+                    l4_len,
+                );
+                hdr.fill(0);
+                set_u16(hdr, 2, teb);
+                set_u32(hdr, 4, (vni & 0x00ff_ffff) << 8);
+            }
+            Tunnel::Gre { key } => {
+                let flags = if key.is_some() { GRE_FLAG_K } else { 0 };
+                set_u16(hdr, 0, flags);
+                set_u16(hdr, 2, teb);
+                if let Some(key) = key {
+                    set_u32(hdr, 4, key);
+                }
+            }
+        }
+    }
+}
+
+/// 外层 IP 头部中的长度字段：IPv4 包括头部，IPv6 只包括负载
+fn l3_total_len(outer: OuterIp, l3_payload_len: usize) -> usize {
+    match outer {
+        OuterIp::V4 { .. } => l3_payload_len + Ipv4Hdr::<&[u8]>::MIN_LEN,
+        OuterIp::V6 { .. } => l3_payload_len,
+    }
+}
+
+fn write_udp(udp: &mut [u8], src_port: u16, dst_port: u16, len: usize) {
+    set_u16(udp, 0, src_port);
+    set_u16(udp, 2, dst_port);
+    set_u16(udp, 4, len as u16);
+    set_u16(udp, 6, 0);
+}
+
+/// 外层 UDP 校验和的计算方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OuterUdpCksum {
+    /// 没有 UDP 头部，或外层为 IPv4，校验和为 0
+    None,
+    Offload,
+    Software,
+}
+
+/// 计算外层 IPv6 UDP 校验和，`l3_ofs` 是 IPv6 头部的偏移，`l4_len` 是 UDP 长度
+///
+/// 外层头部位于第一个段中，UDP 负载可以跨越多个段。
+fn ipv6_udp_cksum(mbuf: &Mbuf, l3_ofs: usize, l4_len: usize) -> Result<u16> {
+    let ip = mbuf.data()[l3_ofs..].as_ptr() as *const rte_ipv6_hdr;
+    let l4_ofs = l3_ofs + ipv6::HDR_LEN;
+    let mut raw = 0u16;
+    let ret = unsafe { rte_raw_cksum_mbuf(mbuf.as_ptr(), l4_ofs as u32, l4_len as u32, &mut raw) };
+    if ret < 0 {
+        return Err(DpdkError::invalid("外层 UDP 长度超出 mbuf"));
+    }
+    // 以下结果按内存中的字节顺序计算，原样写回即为网络字节序。
+    let mut sum = unsafe { rte_ipv6_phdr_cksum(ip, 0) } as u32 + raw as u32;
+    sum = (sum >> 16) + (sum & 0xffff);
+    sum = (sum >> 16) + (sum & 0xffff);
+    // 校验和为 0 表示没有校验和，按 RFC 768 改为全 1。
+    let cksum = !(sum as u16);
+    Ok(if cksum == 0 { 0xffff } else { cksum })
+}
+
+/// 由 RSS 哈希选取外层 UDP 源端口，没有哈希时使用范围起点
+fn flow_src_port(mbuf: &Mbuf) -> u16 {
+    if mbuf.ol_flags() & constants::RTE_MBUF_F_RX_RSS_HASH == 0 {
+        return UDP_SRC_PORT_BASE;
+    }
+    let hash = unsafe { (*mbuf.as_ptr()).__bindgen_anon_3.hash.rss };
+    UDP_SRC_PORT_BASE | (hash as u16 & 0x3fff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x01];
+    const DST_MAC: [u8; 6] = [0x02, 0, 0, 0, 0, 0x02];
+    const TEB: u16 = constants::RTE_ETHER_TYPE_TEB as u16;
+
+    #[test]
+    fn gre_option_offsets() {
+        let mut buf = [0u8; 20];
+        set_u16(&mut buf, 0, GRE_FLAG_C | GRE_FLAG_K | GRE_FLAG_S);
+        set_u16(&mut buf, 2, TEB);
+        set_u16(&mut buf, 4, 0x1111);
+        set_u32(&mut buf, 8, 0x2222_2222);
+        set_u32(&mut buf, 12, 0x3333_3333);
+        let gre = GreHdr::new(&buf[..]).unwrap();
+        assert_eq!(gre.header_len(), 16);
+        assert_eq!(gre.checksum(), Some(0x1111));
+        assert_eq!(gre.key(), Some(0x2222_2222));
+        assert_eq!(gre.seq(), Some(0x3333_3333));
+        assert_eq!(gre.payload().len(), 4);
+        assert!(GreHdr::new(&buf[..15]).is_err());
+
+        // 没有 C 标志时密钥紧跟在基本头部之后
+        set_u16(&mut buf, 0, GRE_FLAG_K | GRE_FLAG_S);
+        let gre = GreHdr::new(&buf[..]).unwrap();
+        assert_eq!(gre.header_len(), 12);
+        assert_eq!(gre.checksum(), None);
+        assert_eq!(gre.key(), Some(0x1111_0000));
+        assert_eq!(gre.seq(), Some(0x2222_2222));
+
+        set_u16(&mut buf, 0, GRE_FLAG_C | GRE_FLAG_S);
+        let gre = GreHdr::new(&buf[..]).unwrap();
+        assert_eq!(gre.key(), None);
+        assert_eq!(gre.seq(), Some(0x2222_2222));
+
+        set_u16(&mut buf, 0, 0);
+        let mut gre = GreHdr::new(&mut buf[..4]).unwrap();
+        assert_eq!(gre.header_len(), 4);
+        assert!(!gre.set_key(1));
+        assert!(!gre.set_checksum(1));
+        assert!(!gre.set_seq(1));
+    }
+
+    #[test]
+    fn gre_setters() {
+        let mut buf = [0u8; 12];
+        set_u16(&mut buf, 0, GRE_FLAG_K | GRE_FLAG_S);
+        let mut gre = GreHdr::new(&mut buf[..]).unwrap();
+        assert!(gre.set_key(0xaabb_ccdd));
+        assert!(gre.set_seq(7));
+        assert!(!gre.set_checksum(1));
+        assert_eq!(&buf[4..12], &[0xaa, 0xbb, 0xcc, 0xdd, 0, 0, 0, 7]);
+    }
+
+    #[test]
+    fn geneve_opt_len_bounds() {
+        let mut buf = vec![0u8; 16];
+        // opt_len 为 2，即 8 字节选项
+        buf[0] = 2;
+        set_u16(&mut buf, 2, TEB);
+        let geneve = GeneveHdr::new(&buf[..]).unwrap();
+        assert_eq!(geneve.header_len(), 16);
+        assert_eq!(geneve.options().len(), 8);
+        assert!(geneve.payload().is_empty());
+        assert!(GeneveHdr::new(&buf[..15]).is_err());
+        assert!(GeneveHdr::new(&buf[..7]).is_err());
+
+        // opt_len 最大为 63，版本号位不计入
+        buf[0] = 0xff;
+        assert!(GeneveHdr::new(&buf[..]).is_err());
+        buf.resize(GENEVE_MIN_LEN + 63 * 4, 0);
+        let geneve = GeneveHdr::new(&buf[..]).unwrap();
+        assert_eq!(geneve.version(), 3);
+        assert_eq!(geneve.opt_len(), 63);
+        assert_eq!(geneve.options().len(), 252);
+    }
+
+    #[test]
+    fn vni_packing() {
+        let mut buf = [0u8; 8];
+        buf[7] = 0x5a;
+        let mut vxlan = VxlanHdr::new(&mut buf[..]).unwrap();
+        assert!(!vxlan.vni_valid());
+        // 只使用低 24 位，保留字节不变
+        vxlan.set_vni(0xff12_3456);
+        assert!(vxlan.vni_valid());
+        assert_eq!(vxlan.vni(), 0x12_3456);
+        assert_eq!(buf, [0x08, 0, 0, 0, 0x12, 0x34, 0x56, 0x5a]);
+
+        let mut buf = [0u8; 8];
+        let mut geneve = GeneveHdr::new(&mut buf[..]).unwrap();
+        geneve.set_vni(0xab_cdef);
+        geneve.set_oam(true);
+        geneve.set_critical(true);
+        geneve.set_oam(false);
+        assert_eq!(geneve.vni(), 0xab_cdef);
+        assert!(!geneve.oam());
+        assert!(geneve.critical());
+        assert_eq!(buf, [0, 0x40, 0, 0, 0xab, 0xcd, 0xef, 0]);
+    }
+
+    /// 没有 VLAN 标签的内层 IPv4 帧，总长 `len`
+    fn inner_frame(len: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; len];
+        frame[0..6].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x0a]);
+        frame[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x0b]);
+        set_u16(&mut frame, 12, constants::RTE_ETHER_TYPE_IPV4 as u16);
+        frame
+    }
+
+    /// 在内层帧前写入外层头部，返回整个数据包
+    fn encapsulate(encap: &TunnelEncap, inner: &[u8]) -> Vec<u8> {
+        let outer = encap.outer.unwrap();
+        let l3_payload_len = encap.tunnel.outer_l4_len() + encap.tunnel.header_len() + inner.len();
+        let mut buf = vec![0u8; encap.outer_len()];
+        encap.write_outer(&mut buf, outer, l3_payload_len, 50000);
+        buf.extend_from_slice(inner);
+        buf
+    }
+
+    fn encaps() -> Vec<TunnelEncap> {
+        let v4 = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+        let v6: (Ipv6Addr, Ipv6Addr) = ("fd00::1".parse().unwrap(), "fd00::2".parse().unwrap());
+        let mut encaps = Vec::new();
+        for tunnel in [
+            Tunnel::Vxlan { vni: 42 },
+            Tunnel::Geneve { vni: 0x12_3456 },
+            Tunnel::Gre { key: None },
+            Tunnel::Gre { key: Some(7) },
+        ] {
+            let base = TunnelEncap::new(tunnel, SRC_MAC, DST_MAC);
+            encaps.push(base.clone().ipv4(v4.0, v4.1));
+            encaps.push(base.ipv6(v6.0, v6.1));
+        }
+        encaps
+    }
+
+    #[test]
+    fn encap_layout_round_trip() {
+        let inner = inner_frame(60);
+        for encap in encaps() {
+            let pkt = encapsulate(&encap, &inner);
+            let layout = parse_tunnel(&pkt).unwrap().unwrap();
+            assert_eq!(layout.tunnel, encap.tunnel);
+            assert_eq!(layout.outer_l2_len, EtherHdr::<&[u8]>::LEN);
+            assert_eq!(layout.outer_l3_len, encap.outer_l3_len());
+            assert_eq!(layout.inner_offset(), encap.outer_len());
+            assert_eq!(&pkt[layout.inner_offset()..], &inner[..]);
+
+            // 外层长度字段覆盖到数据包末尾
+            let l3 = &pkt[layout.outer_l2_len..];
+            let l4_len = match encap.outer.unwrap() {
+                OuterIp::V4 { .. } => {
+                    let ip = Ipv4Hdr::new(l3).unwrap();
+                    assert!(ip.checksum_valid());
+                    assert!(ip.dont_fragment());
+                    assert_eq!(ip.total_length() as usize, l3.len());
+                    ip.payload().len()
+                }
+                OuterIp::V6 { .. } => {
+                    let ip = Ipv6Hdr::new(l3).unwrap();
+                    assert_eq!(ip.payload_len() as usize, l3.len() - ipv6::HDR_LEN);
+                    ip.payload().len()
+                }
+            };
+            if encap.tunnel.outer_l4_len() != 0 {
+                let udp = UdpHdr::new(&l3[layout.outer_l3_len..]).unwrap();
+                assert_eq!(udp.dgram_len() as usize, l4_len);
+                assert_eq!(udp.src_port(), 50000);
+            }
+        }
+    }
+
+    #[test]
+    fn first_segment_only() {
+        // 多段数据包的第一个段只包含外层头部和内层以太网头部，长度字段超出该段
+        let inner = inner_frame(1500);
+        for encap in encaps() {
+            let pkt = encapsulate(&encap, &inner);
+            let first = &pkt[..encap.outer_len() + EtherHdr::<&[u8]>::LEN];
+            let layout = parse_tunnel(first).unwrap().unwrap();
+            assert_eq!(layout.tunnel, encap.tunnel);
+            assert_eq!(layout.inner_offset(), encap.outer_len());
+            // 隧道头部本身被截断时仍然返回错误
+            assert!(parse_tunnel(&pkt[..encap.outer_len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn not_a_tunnel() {
+        let inner = inner_frame(60);
+        let mut arp = inner.clone();
+        set_u16(&mut arp, 12, constants::RTE_ETHER_TYPE_ARP as u16);
+        assert_eq!(parse_tunnel(&arp).unwrap(), None);
+
+        // VXLAN 端口但没有 I 标志
+        let vxlan = TunnelEncap::new(Tunnel::Vxlan { vni: 1 }, SRC_MAC, DST_MAC)
+            .ipv4(Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST);
+        let mut pkt = encapsulate(&vxlan, &inner);
+        let flags = EtherHdr::<&[u8]>::LEN + Ipv4Hdr::<&[u8]>::MIN_LEN + udp::HDR_LEN;
+        pkt[flags] = 0;
+        assert_eq!(parse_tunnel(&pkt).unwrap(), None);
+
+        // 外层 IPv4 分片
+        let mut pkt = encapsulate(&vxlan, &inner);
+        pkt[EtherHdr::<&[u8]>::LEN + 6] |= 0x20;
+        assert_eq!(parse_tunnel(&pkt).unwrap(), None);
+
+        // GRE 承载的不是以太网帧
+        let gre = TunnelEncap::new(Tunnel::Gre { key: None }, SRC_MAC, DST_MAC)
+            .ipv4(Ipv4Addr::LOCALHOST, Ipv4Addr::LOCALHOST);
+        let mut pkt = encapsulate(&gre, &inner);
+        let proto = EtherHdr::<&[u8]>::LEN + Ipv4Hdr::<&[u8]>::MIN_LEN + 2;
+        set_u16(&mut pkt, proto, constants::RTE_ETHER_TYPE_IPV4 as u16);
+        assert_eq!(parse_tunnel(&pkt).unwrap(), None);
+    }
+
+    #[test]
+    fn offload_lens() {
+        let inner = TxOffloadLens {
+            l2_len: 18,
+            l3_len: 20,
+            l4_len: 20,
+            tso_segsz: 1400,
+            ..Default::default()
+        };
+        for encap in encaps() {
+            let lens = encap.encap_lens(18, inner);
+            let tunnel_len = encap.outer_len() - 14 - encap.outer_l3_len();
+            assert_eq!(lens.l2_len as usize, tunnel_len + 18);
+            assert_eq!(lens.outer_l2_len, 14);
+            assert_eq!(lens.outer_l3_len as usize, encap.outer_l3_len());
+            assert_eq!((lens.l3_len, lens.l4_len, lens.tso_segsz), (20, 20, 1400));
+            // 解封装恢复内层长度
+            assert_eq!(decap_lens(18, lens), inner);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! UDP 头部（`rte_udp_hdr`）
+
+use super::{check_len, get_u16, set_u16};
+use crate::error::{DpdkError, Result};
+use std::fmt;
+
+const SRC_PORT: usize = 0;
+const DST_PORT: usize = 2;
+const DGRAM_LEN: usize = 4;
+const DGRAM_CKSUM: usize = 6;
+
+pub(super) const HDR_LEN: usize = 8;
+
+/// UDP 头部视图
+///
+/// 创建时检查 `dgram_len`：整个数据报都必须位于缓冲区内。
+#[derive(Clone, Copy)]
+pub struct UdpHdr<T> {
+    buf: T,
+}
+
+impl<T: AsRef<[u8]>> UdpHdr<T> {
+    /// 头部长度
+    pub const LEN: usize = HDR_LEN;
+
+    /// 在 UDP 头部起始位置的缓冲区上创建视图
+    pub fn new(buf: T) -> Result<Self> {
+        let hdr = Self::header_only(buf)?;
+        let dgram_len = hdr.dgram_len() as usize;
+        if dgram_len < HDR_LEN || dgram_len > hdr.buf.as_ref().len() {
+            return Err(DpdkError::invalid(format!(
+                "UDP dgram_len 为 {}，缓冲区 {} 字节",
+                dgram_len,
+                hdr.buf.as_ref().len()
+            )));
+        }
+        Ok(hdr)
+    }
+
+    /// 只检查头部位于缓冲区内，不检查 `dgram_len`
+    ///
+    /// 用于多段数据包的第一个段：负载截断到缓冲区末尾。
+    pub(crate) fn header_only(buf: T) -> Result<Self> {
+        check_len("UDP ", buf.as_ref().len(), HDR_LEN)?;
+        Ok(Self { buf })
+    }
+
+    pub fn src_port(&self) -> u16 {
+        get_u16(self.buf.as_ref(), SRC_PORT)
+    }
+
+    pub fn dst_port(&self) -> u16 {
+        get_u16(self.buf.as_ref(), DST_PORT)
+    }
+
+    /// 头部和负载的总长度
+    pub fn dgram_len(&self) -> u16 {
+        get_u16(self.buf.as_ref(), DGRAM_LEN)
+    }
+
+    pub fn dgram_cksum(&self) -> u16 {
+        get_u16(self.buf.as_ref(), DGRAM_CKSUM)
+    }
+
+    /// 头部长度
+    pub fn header_len(&self) -> usize {
+        HDR_LEN
+    }
+
+    /// 头部之后、`dgram_len` 之内的数据
+    pub fn payload(&self) -> &[u8] {
+        let end = self.payload_end();
+        &self.buf.as_ref()[HDR_LEN..end]
+    }
+
+    /// 取回底层缓冲区
+    pub fn into_inner(self) -> T {
+        self.buf
+    }
+
+    /// 负载的结束位置；`dgram_len` 越界时截断到缓冲区末尾
+    fn payload_end(&self) -> usize {
+        (self.dgram_len() as usize)
+            .min(self.buf.as_ref().len())
+            .max(HDR_LEN)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> UdpHdr<T> {
+    pub fn set_src_port(&mut self, port: u16) {
+        set_u16(self.buf.as_mut(), SRC_PORT, port);
+    }
+
+    pub fn set_dst_port(&mut self, port: u16) {
+        set_u16(self.buf.as_mut(), DST_PORT, port);
+    }
+
+    /// 修改 `dgram_len`，负载随之变化，但不会超出缓冲区
+    pub fn set_dgram_len(&mut self, len: u16) {
+        set_u16(self.buf.as_mut(), DGRAM_LEN, len);
+    }
+
+    /// 设置校验和，计算见 `Ipv4Hdr::update_l4_checksum`
+    pub fn set_dgram_cksum(&mut self, cksum: u16) {
+        set_u16(self.buf.as_mut(), DGRAM_CKSUM, cksum);
+    }
+
+    /// 交换源和目的端口
+    pub fn swap_ports(&mut self) {
+        let (src, dst) = self.buf.as_mut()[SRC_PORT..DST_PORT + 2].split_at_mut(2);
+        src.swap_with_slice(dst);
+    }
+
+    /// 头部之后、`dgram_len` 之内的可写数据
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let end = self.payload_end();
+        &mut self.buf.as_mut()[HDR_LEN..end]
+    }
+}
+
+impl<'a> UdpHdr<&'a [u8]> {
+    /// 消耗视图，返回负载
+    pub fn into_payload(self) -> &'a [u8] {
+        let end = self.payload_end();
+        &self.buf[HDR_LEN..end]
+    }
+}
+
+impl<'a> UdpHdr<&'a mut [u8]> {
+    /// 消耗视图，返回可写的负载
+    pub fn into_payload_mut(self) -> &'a mut [u8] {
+        let end = self.payload_end();
+        &mut self.buf[HDR_LEN..end]
+    }
+}
+
+impl<T: AsRef<[u8]>> fmt::Debug for UdpHdr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UdpHdr")
+            .field("src_port", &self.src_port())
+            .field("dst_port", &self.dst_port())
+            .field("dgram_len", &self.dgram_len())
+            .field("dgram_cksum", &format_args!("{:#06x}", self.dgram_cksum()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dgram(dgram_len: u16, buf_len: usize) -> Vec<u8> {
+        let mut buf = vec![0u8; buf_len];
+        set_u16(&mut buf, SRC_PORT, 1234);
+        set_u16(&mut buf, DST_PORT, 5678);
+        set_u16(&mut buf, DGRAM_LEN, dgram_len);
+        buf
+    }
+
+    #[test]
+    fn dgram_len_bounds() {
+        assert!(UdpHdr::new(&dgram(8, 7)[..7]).is_err());
+        assert!(UdpHdr::new(&dgram(7, 16)[..]).is_err());
+        assert!(UdpHdr::new(&dgram(17, 16)[..]).is_err());
+        let buf = dgram(8, 8);
+        assert!(UdpHdr::new(&buf[..]).unwrap().payload().is_empty());
+    }
+
+    #[test]
+    fn padding_is_not_payload() {
+        let buf = dgram(12, 20);
+        let udp = UdpHdr::new(&buf[..]).unwrap();
+        assert_eq!(udp.payload().len(), 4);
+        assert_eq!(udp.into_payload().len(), 4);
+    }
+
+    #[test]
+    fn setters() {
+        let mut buf = dgram(12, 12);
+        let mut udp = UdpHdr::new(&mut buf[..]).unwrap();
+        udp.swap_ports();
+        assert_eq!((udp.src_port(), udp.dst_port()), (5678, 1234));
+        udp.set_dgram_cksum(0xabcd);
+        // 超出缓冲区的长度只截断负载
+        udp.set_dgram_len(100);
+        assert_eq!(udp.payload().len(), 4);
+        assert_eq!(&buf[..8], &[0x16, 0x2e, 0x04, 0xd2, 0x00, 0x64, 0xab, 0xcd]);
+    }
+}
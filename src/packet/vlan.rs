@@ -0,0 +1,169 @@
+//! VLAN 标签（802.1Q/QinQ）以及 mbuf 上的 VLAN 元数据
+//!
+//! 端口开启 `RxOffload::VLAN_STRIP`/`QINQ_STRIP` 时，网卡把剥离的标签写入
+//! `vlan_tci`/`vlan_tci_outer` 并设置 `RTE_MBUF_F_RX_VLAN*`/`RTE_MBUF_F_RX_QINQ*` 标志；
+//! 开启 `TxOffload::VLAN_INSERT`/`QINQ_INSERT` 时，发送前由网卡插入标签。
+
+use crate::error::{DpdkError, Result};
+use crate::mbuf::Mbuf;
+use crate::*;
+use std::fmt;
+
+/// 以太网头部中最多识别的 VLAN 标签层数
+pub(super) const MAX_VLAN_TAGS: usize = 2;
+
+/// 是否是 VLAN 标签的 TPID
+pub(super) fn is_vlan_tpid(ether_type: u16) -> bool {
+    let ether_type = ether_type as u32;
+    ether_type == constants::RTE_ETHER_TYPE_VLAN
+        || ether_type == constants::RTE_ETHER_TYPE_QINQ
+        || ether_type == constants::RTE_ETHER_TYPE_QINQ1
+        || ether_type == constants::RTE_ETHER_TYPE_QINQ2
+        || ether_type == constants::RTE_ETHER_TYPE_QINQ3
+}
+
+/// 数据包中的一个 VLAN 标签
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VlanTag {
+    /// 标签协议标识，如 `RTE_ETHER_TYPE_VLAN` 或 `RTE_ETHER_TYPE_QINQ`
+    pub tpid: u16,
+    /// 优先级、DEI 和 VLAN ID
+    pub tci: u16,
+}
+
+impl VlanTag {
+    /// 由优先级、DEI 和 VLAN ID 组成 TCI
+    pub fn tci_from_parts(pcp: u8, dei: bool, vid: u16) -> u16 {
+        ((pcp as u16 & 0x7) << 13) | ((dei as u16) << 12) | (vid & 0x0fff)
+    }
+
+    /// 优先级（PCP）
+    pub fn pcp(&self) -> u8 {
+        (self.tci >> 13) as u8
+    }
+
+    /// 可丢弃标志（DEI）
+    pub fn dei(&self) -> bool {
+        self.tci & 0x1000 != 0
+    }
+
+    /// VLAN ID
+    pub fn vid(&self) -> u16 {
+        self.tci & 0x0fff
+    }
+}
+
+impl fmt::Debug for VlanTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VlanTag")
+            .field("tpid", &format_args!("{:#06x}", self.tpid))
+            .field("pcp", &self.pcp())
+            .field("dei", &self.dei())
+            .field("vid", &self.vid())
+            .finish()
+    }
+}
+
+impl Mbuf {
+    /// mbuf 的 `vlan_tci` 字段，只有 `rx_vlan` 标志置位时才有意义
+    pub fn vlan_tci(&self) -> u16 {
+        unsafe { (*self.as_ptr()).vlan_tci }
+    }
+
+    /// mbuf 的 `vlan_tci_outer` 字段，只有 `rx_qinq` 标志置位时才有意义
+    pub fn vlan_tci_outer(&self) -> u16 {
+        unsafe { (*self.as_ptr()).vlan_tci_outer }
+    }
+
+    /// 接收时网卡识别出的 VLAN TCI（`RTE_MBUF_F_RX_VLAN`）
+    ///
+    /// QinQ 数据包中为内层标签。
+    pub fn rx_vlan(&self) -> Option<u16> {
+        (self.ol_flags() & constants::RTE_MBUF_F_RX_VLAN != 0).then(|| self.vlan_tci())
+    }
+
+    /// 接收时网卡识别出的 QinQ 外层 TCI（`RTE_MBUF_F_RX_QINQ`）
+    pub fn rx_qinq_outer(&self) -> Option<u16> {
+        (self.ol_flags() & constants::RTE_MBUF_F_RX_QINQ != 0).then(|| self.vlan_tci_outer())
+    }
+
+    /// VLAN 标签是否已从数据中剥离（`RTE_MBUF_F_RX_VLAN_STRIPPED`）
+    pub fn rx_vlan_stripped(&self) -> bool {
+        self.ol_flags() & constants::RTE_MBUF_F_RX_VLAN_STRIPPED != 0
+    }
+
+    /// QinQ 的两层标签是否都已从数据中剥离（`RTE_MBUF_F_RX_QINQ_STRIPPED`）
+    pub fn rx_qinq_stripped(&self) -> bool {
+        self.ol_flags() & constants::RTE_MBUF_F_RX_QINQ_STRIPPED != 0
+    }
+
+    /// 请求网卡在发送时插入 802.1Q 标签
+    ///
+    /// 端口需要开启 `TxOffload::VLAN_INSERT`。
+    pub fn set_tx_vlan(&mut self, tci: u16) {
+        unsafe {
+            let m = self.as_ptr();
+            (*m).vlan_tci = tci;
+            (*m).ol_flags |= constants::RTE_MBUF_F_TX_VLAN;
+        }
+    }
+
+    /// 请求网卡在发送时插入 QinQ 的两层标签
+    ///
+    /// 端口需要开启 `TxOffload::QINQ_INSERT`。
+    pub fn set_tx_qinq(&mut self, outer_tci: u16, inner_tci: u16) {
+        unsafe {
+            let m = self.as_ptr();
+            (*m).vlan_tci_outer = outer_tci;
+            (*m).vlan_tci = inner_tci;
+            (*m).ol_flags |= constants::RTE_MBUF_F_TX_QINQ | constants::RTE_MBUF_F_TX_VLAN;
+        }
+    }
+
+    /// 在软件中插入 802.1Q 标签（`rte_vlan_insert`）
+    ///
+    /// 标签插入在源 MAC 地址之后，占用头部预留空间，同时清除
+    /// `RTE_MBUF_F_TX_VLAN` 和 `RTE_MBUF_F_RX_VLAN_STRIPPED` 标志。
+    pub fn vlan_insert(&mut self, tci: u16) -> Result<()> {
+        if !self.is_writable() {
+            return Err(DpdkError::invalid(
+                "mbuf 的数据区被共享，不能插入 VLAN 标签",
+            ));
+        }
+        let mut m = self.as_ptr();
+        let ret = unsafe {
+            (*m).vlan_tci = tci;
+            rte_vlan_insert(&mut m)
+        };
+        // 数据区不共享时 rte_vlan_insert 在原 mbuf 上修改，不会替换指针
+        debug_assert_eq!(m, self.as_ptr());
+        if ret < 0 {
+            return Err(DpdkError::new("rte_vlan_insert", -ret));
+        }
+        Ok(())
+    }
+
+    /// 在软件中剥离外层 802.1Q 标签（`rte_vlan_strip`），返回其 TCI
+    ///
+    /// 剥离后 TCI 写入 `vlan_tci`，并设置 `RTE_MBUF_F_RX_VLAN` 和
+    /// `RTE_MBUF_F_RX_VLAN_STRIPPED`，与网卡剥离的结果一致。
+    /// 只处理 TPID 为 `RTE_ETHER_TYPE_VLAN` 的标签。
+    pub fn vlan_strip(&mut self) -> Result<u16> {
+        let hdr_len = constants::RTE_ETHER_HDR_LEN as usize + constants::RTE_VLAN_HLEN as usize;
+        if self.data_len() < hdr_len {
+            return Err(DpdkError::invalid(format!(
+                "第一个段只有 {} 字节，不足以包含 VLAN 标签",
+                self.data_len()
+            )));
+        }
+        if !self.is_writable() {
+            return Err(DpdkError::invalid(
+                "mbuf 的数据区被共享，不能剥离 VLAN 标签",
+            ));
+        }
+        if unsafe { rte_vlan_strip(self.as_ptr()) } != 0 {
+            return Err(DpdkError::invalid("数据包没有 802.1Q 标签"));
+        }
+        Ok(self.vlan_tci())
+    }
+}